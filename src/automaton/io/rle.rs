@@ -7,7 +7,7 @@
 
 #![allow(missing_docs)]
 
-use num::BigInt;
+use num::{BigInt, One, ToPrimitive, Zero};
 use pest::Parser;
 
 use super::*;
@@ -30,9 +30,10 @@ struct CxrleHeader {
     /// Position of top-left of pattern (i.e. most negative coordinates).
     pub pos: BigVec2D,
     /// Number of generations simulated.
-    pub gen: isize,
+    pub gen: BigInt,
 }
 /// A single "content item" that may be repeated in an RLE pattern.
+#[derive(Debug, Copy, Clone)]
 enum RleItem<C> {
     /// A cell state.
     Cell(C),
@@ -42,16 +43,95 @@ enum RleItem<C> {
 
 type TokenPair<'a> = pest::iterators::Pair<'a, Rule>;
 
+/// The rule used when an RLE header does not specify one, as in Golly.
+const DEFAULT_RULE: &str = "B3/S23";
+/// The maximum length of a line of RLE content, as written by Golly.
+const MAX_LINE_LEN: usize = 70;
+
 /// Methods for encoding/decoding patterns to/from Golly Extended RLE.
 pub trait RleEncode: std::marker::Sized {
     /// Encode the pattern in Golly Extended RLE.
     fn to_rle(&self) -> String;
-    /// Decode a Golly Extended RLE pattern.
+    /// Decode a Golly Extended RLE pattern, using the rule given in its header
+    /// (or Conway's Game of Life if there is none).
     fn from_rle(s: &str) -> Result<Self, String>;
 }
 impl RleEncode for NdAutomaton<Dim2D> {
     fn to_rle(&self) -> String {
-        unimplemented!()
+        // Collect all the non-empty cells, ordered the same way they appear in
+        // the RLE: top row first, and left to right within each row.
+        let mut cells = vec![];
+        collect_cells(&self.tree.slice, &mut cells);
+        cells.sort_by(|(pos1, _), (pos2, _)| {
+            pos2[Y].cmp(&pos1[Y]).then_with(|| pos1[X].cmp(&pos2[X]))
+        });
+
+        // Compute the bounding rectangle of the pattern.
+        let (min, max) = match cells.first() {
+            Some((first_pos, _)) => cells.iter().fold(
+                (first_pos.clone(), first_pos.clone()),
+                |(min, max), (pos, _)| (NdVec::min(&min, pos), NdVec::max(&max, pos)),
+            ),
+            None => (BigVec2D::origin(), BigVec2D::repeat(-1)),
+        };
+        let size = max.clone() - &min + &BigInt::from(1);
+
+        // Bounded grids are written after the rule, as in Golly.
+        let mut rule_str = self.sim.get_rule().to_string();
+        if !self.get_topology().is_unbounded() {
+            rule_str = format!("{}:{}", rule_str, self.get_topology());
+        }
+
+        // Y coordinates increase upwards in NDCell, but downwards in RLE, so
+        // reflect over the X axis.
+        let mut ret = format!(
+            "#CXRLE Pos={},{} Gen={}\nx = {}, y = {}, rule = {}\n",
+            min[X],
+            -&max[Y] - 1,
+            self.generations,
+            size[X],
+            size[Y],
+            rule_str,
+        );
+
+        // Cells are stored relative to the background, but written as their
+        // actual states. If the background is not empty, then every cell in
+        // the bounding rectangle must be written, since the cells that are
+        // left out of an RLE are empty.
+        let background = self.sim.get_background();
+        let end_x = &max[X] + 1;
+
+        let mut content = RleContentWriter::default();
+        let mut next_pos = NdVec([min[X].clone(), max[Y].clone()]);
+        for (pos, cell_state) in cells {
+            // Skip to the row containing this cell.
+            let rows = &next_pos[Y] - &pos[Y];
+            if rows > BigInt::from(0) {
+                if background == 0 {
+                    content.push_big_item(rows, RleItem::EndRow);
+                } else {
+                    content.push_cells(&end_x - &next_pos[X], background);
+                    content.push_item(1, RleItem::EndRow);
+                    for _ in num::range(BigInt::one(), rows) {
+                        content.push_cells(size[X].clone(), background);
+                        content.push_item(1, RleItem::EndRow);
+                    }
+                }
+                next_pos = NdVec([min[X].clone(), pos[Y].clone()]);
+            }
+            // Skip to the column containing this cell.
+            content.push_cells(&pos[X] - &next_pos[X], background);
+            content.push_cells(BigInt::one(), swap_background(cell_state, background));
+            next_pos = pos;
+            next_pos[X] += 1;
+        }
+        if background != 0 {
+            content.push_cells(&end_x - &next_pos[X], background);
+        }
+        content.push_str("!");
+
+        ret.push_str(&content.finish());
+        ret
     }
     fn from_rle(s: &str) -> Result<Self, String> {
         let mut header: Option<RleHeader> = None;
@@ -106,19 +186,41 @@ impl RleEncode for NdAutomaton<Dim2D> {
                 _ => Err("Unexpected token pair")?,
             }
         }
-        let _header = header.ok_or("Missing RLE header")?;
+        let header = header.ok_or("Missing RLE header")?;
 
         let mut ret = NdAutomaton::default();
+        let rule_str = header.rule.as_deref().unwrap_or(DEFAULT_RULE);
+        // A bounded grid may be given after the rule, as in Golly.
+        let (rule_str, topology) = match rule_str.split_once(':') {
+            Some((rule_str, topology_str)) => (rule_str, topology_str.parse()?),
+            None => (rule_str, Topology::unbounded()),
+        };
+        ret.sim = Simulation::new(rule::from_str(rule_str)?, BigInt::one());
         let mut pos = BigVec2D::origin();
         let x_start: BigInt;
 
         if let Some(cxrle) = cxrle {
-            ret.generations = cxrle.gen;
+            ret.set_generation_count(cxrle.gen);
             pos = cxrle.pos;
             x_start = pos[X].clone();
         } else {
             x_start = 0.into();
         }
+        // Cells are stored relative to the background. If the background is
+        // not empty, then the cells in the bounding rectangle that are left
+        // out of the RLE are empty, so they must be stored too.
+        let background = ret.sim.get_background();
+        if background != 0 {
+            let (width, height) = (header.x.max(0) as usize, header.y.max(0) as usize);
+            if cell_array.len() < height {
+                cell_array.resize_with(height, Vec::new);
+            }
+            for row in &mut cell_array {
+                if row.len() < width {
+                    row.resize(width, 0);
+                }
+            }
+        }
         // Y coordinates increase upwards in NDCell, but downwards in RLE, so
         // reflect over the Y axis.
         pos[Y] *= -1;
@@ -126,17 +228,111 @@ impl RleEncode for NdAutomaton<Dim2D> {
 
         for row in cell_array {
             for cell in row {
-                ret.tree.set_cell(&pos, cell);
+                ret.tree.set_cell(&pos, swap_background(cell, background));
                 pos[X] += 1;
             }
             pos[X] = x_start.clone();
             pos[Y] -= 1;
         }
+        ret.set_topology(topology)?;
 
         Ok(ret)
     }
 }
 
+/// Appends the position and state of every non-empty cell in the given slice
+/// to `cells`.
+fn collect_cells(slice: &NdTreeSlice<u8, Dim2D>, cells: &mut Vec<(BigVec2D, u8)>) {
+    if slice.root.is_empty() {
+        return;
+    }
+    for (branch_idx, branch) in slice.root.branch_iter() {
+        let branch_offset = &slice.offset + slice.root.branch_offset(branch_idx);
+        match branch {
+            NdTreeBranch::Leaf(cell_state) => {
+                if *cell_state != 0 {
+                    cells.push((branch_offset, *cell_state));
+                }
+            }
+            NdTreeBranch::Node(node) => collect_cells(
+                &NdTreeSlice {
+                    root: node.clone(),
+                    offset: branch_offset,
+                },
+                cells,
+            ),
+        }
+    }
+}
+
+/// A helper for writing RLE content that wraps lines the same way Golly does.
+#[derive(Default)]
+struct RleContentWriter {
+    lines: Vec<String>,
+    current_line: String,
+    /// Run of cells that has not been written yet, because it may be extended.
+    run: Option<(u8, BigInt)>,
+}
+impl RleContentWriter {
+    /// Appends a run of `n` cells in the given state, merging it with the
+    /// preceding run if that is in the same state.
+    fn push_cells(&mut self, n: BigInt, cell_state: u8) {
+        if n.is_zero() {
+            return;
+        }
+        match &mut self.run {
+            Some((run_state, run_len)) if *run_state == cell_state => *run_len += n,
+            _ => {
+                self.flush_run();
+                self.run = Some((cell_state, n));
+            }
+        }
+    }
+    /// Writes the pending run of cells, if there is one.
+    fn flush_run(&mut self) {
+        if let Some((run_state, run_len)) = self.run.take() {
+            self.push_big_item(run_len, RleItem::Cell(run_state));
+        }
+    }
+    /// Appends a run of `n` identical items.
+    fn push_item(&mut self, n: usize, item: RleItem<u8>) {
+        let mut s = String::new();
+        if n > 1 {
+            s.push_str(&n.to_string());
+        }
+        match item {
+            RleItem::Cell(cell_state) => cell_state.push_to_string(&mut s),
+            RleItem::EndRow => s.push('$'),
+        }
+        self.push_str(&s);
+    }
+    /// Appends a run of `n` identical items, which may be too long to count
+    /// using a usize, in which case it is split into several runs.
+    fn push_big_item(&mut self, mut n: BigInt, item: RleItem<u8>) {
+        let max_run = BigInt::from(usize::MAX);
+        while n > max_run {
+            self.push_item(usize::MAX, item);
+            n -= &max_run;
+        }
+        self.push_item(n.to_usize().unwrap(), item);
+    }
+    /// Appends a string that must not be split across lines.
+    fn push_str(&mut self, s: &str) {
+        self.flush_run();
+        if self.current_line.len() + s.len() > MAX_LINE_LEN {
+            self.lines.push(std::mem::take(&mut self.current_line));
+        }
+        self.current_line.push_str(s);
+    }
+    /// Returns all the content, with a newline at the end of each line.
+    fn finish(mut self) -> String {
+        self.lines.push(self.current_line);
+        let mut ret = self.lines.join("\n");
+        ret.push('\n');
+        ret
+    }
+}
+
 fn parse_header(pair: TokenPair) -> Result<RleHeader, String> {
     let mut inners = pair.into_inner();
     let x: isize = inners
@@ -157,7 +353,7 @@ fn parse_header(pair: TokenPair) -> Result<RleHeader, String> {
 
 fn parse_cxrle(pair: TokenPair) -> Result<CxrleHeader, String> {
     let mut pos: BigVec2D = BigVec2D::origin();
-    let mut gen: isize = 0;
+    let mut gen = BigInt::zero();
     for kv_pair in pair.into_inner() {
         let mut inners = kv_pair.into_inner();
         let k = inners.next().ok_or("Invalid CXRLE key")?.as_str();
@@ -276,6 +472,8 @@ fn char_diff(ch1: char, ch2: char) -> u8 {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
 
     /// Tests that we can read and write all 256 cell states in RLE format.
@@ -368,4 +566,171 @@ o$3o!
         assert_eq!(1, result.tree.get_cell(&NdVec::big([11, 12])));
         assert_eq!(1, result.tree.get_cell(&NdVec::big([12, 12])));
     }
+
+    fn load_rle(s: &str) -> NdAutomaton<Dim2D> {
+        RleEncode::from_rle(s).unwrap()
+    }
+
+    /// Tests that the rule in the RLE header is used to simulate the pattern.
+    #[test]
+    fn test_rle_rule() {
+        let rle = "x = 1, y = 1, rule = B36/S23\no!";
+        assert_eq!("B36/S23", load_rle(rle).sim.get_rule().to_string());
+        let rle = "x = 1, y = 1, rule = HighLife\no!";
+        assert_eq!("B36/S23", load_rle(rle).sim.get_rule().to_string());
+        // Golly assumes Life if no rule is given.
+        let rle = "x = 1, y = 1\no!";
+        assert_eq!("B3/S23", load_rle(rle).sim.get_rule().to_string());
+
+        let rle = "x = 1, y = 1, rule = NotARule\no!";
+        let result: Result<NdAutomaton<Dim2D>, String> = RleEncode::from_rle(rle);
+        assert_eq!(
+            Err("Unknown rule: \"NotARule\"".to_owned()),
+            result.map(|_| ())
+        );
+    }
+
+    /// Tests that a bounded grid after the rule in the RLE header is used to
+    /// simulate the pattern.
+    #[test]
+    fn test_rle_topology() {
+        // The bottom row is outside of the grid, so it is removed.
+        let rle = "#CXRLE Pos=-2,-2\nx = 5, y = 6, rule = B3/S23:P5,5\n5o$5o$5o$5o$5o$5o!";
+        let automaton = load_rle(rle);
+        assert_eq!("P5,5", automaton.get_topology().to_string());
+        assert_eq!(BigInt::from(25), automaton.tree.get_root().population);
+        assert_eq!(1, automaton.tree.get_cell(&NdVec::big([2, 1])));
+        assert_eq!(0, automaton.tree.get_cell(&NdVec::big([2, -4])));
+
+        let rle = "x = 1, y = 1, rule = B3/S23:X5,5\no!";
+        let result: Result<NdAutomaton<Dim2D>, String> = RleEncode::from_rle(rle);
+        assert_eq!(
+            Err("Unsupported topology: \"X5,5\"".to_owned()),
+            result.map(|_| ())
+        );
+    }
+
+    /// Tests that writing and then reading an RLE reproduces it exactly.
+    #[test]
+    fn test_rle_round_trip() {
+        for &s in &[
+            // Empty pattern
+            "#CXRLE Pos=0,0 Gen=0\nx = 0, y = 0, rule = B3/S23\n!\n",
+            // Glider
+            "#CXRLE Pos=10,-15 Gen=0\nx = 3, y = 3, rule = B3/S23\n.A$2.A$3A!\n",
+            // Pattern from test_indexed_ndtree()
+            "#CXRLE Pos=-4,-4 Gen=7\nx = 8, y = 8, rule = B3/S23\n4.A$5.A$4.A.A$5.A.A$A5.A$.A5.A$A.A$.A.A!\n",
+            // Gosper glider gun synthesis, which is wide enough to wrap lines
+            "#CXRLE Pos=-23,-7 Gen=-31\nx = 47, y = 14, rule = B3/S23\n16.A$16.A.A16.A$16.2A17.A.A$A.A10.A21.2A$.2A11.2A$.A11.2A3$10.2A20.2A$\n11.2A19.A.A9.3A$10.A21.A11.A$27.A17.A$27.2A$26.A.A!\n",
+            // All cell states
+            "#CXRLE Pos=-1000000000000000000000,1000000000000000000000 Gen=123\nx = 7, y = 3, rule = B3/S23\n2A2.B.C$.pA2xXyA$3.yOyN!\n",
+            // Glider on a torus
+            "#CXRLE Pos=-1,-1 Gen=0\nx = 3, y = 3, rule = B3/S23:T8,8\n.A$2.A$3A!\n",
+        ] {
+            let automaton = load_rle(s);
+            assert_eq!(s, automaton.to_rle());
+        }
+    }
+
+    /// Tests writing an RLE with gaps between cells that are too long to count
+    /// using a usize.
+    #[test]
+    fn test_rle_write_huge_gaps() {
+        let max_run = BigInt::from(usize::MAX);
+        let mut automaton = NdAutomaton::<Dim2D>::default();
+        automaton.tree.set_cell(&NdVec::big([0, 0]), 1);
+        let far: BigInt = &max_run * 2 + 2;
+        automaton
+            .tree
+            .set_cell(&NdVec([far.clone(), BigInt::zero()]), 1);
+        automaton.tree.set_cell(&NdVec([BigInt::zero(), -far]), 1);
+        let rle = automaton.to_rle();
+        let content: String = rle.lines().skip(2).collect();
+        assert_eq!(format!("A{0}.{0}..A{0}${0}$2$A!", usize::MAX), content);
+    }
+
+    /// Tests that patterns are written and read with their actual cell states
+    /// when the background is not empty.
+    #[test]
+    fn test_rle_nonempty_background() {
+        fn stored_cells(automaton: &NdAutomaton<Dim2D>) -> HashSet<(BigVec2D, u8)> {
+            let mut cells = vec![];
+            collect_cells(&automaton.tree.slice, &mut cells);
+            cells.into_iter().collect()
+        }
+
+        // A lone cell in B036/S1237 dies, and so do its neighbors, but the
+        // rest of the grid becomes live.
+        let mut automaton = load_rle("x = 1, y = 1, rule = B036/S1237\no!");
+        automaton.step();
+        assert_eq!(1, automaton.get_background());
+        let rle = automaton.to_rle();
+        assert_eq!(
+            "#CXRLE Pos=-1,-1 Gen=1\nx = 3, y = 3, rule = B036/S1237\n3.$3.$3.!\n",
+            rle
+        );
+        let reloaded = load_rle(&rle);
+        assert_eq!(1, reloaded.get_background());
+        assert_eq!(stored_cells(&automaton), stored_cells(&reloaded));
+
+        // Round-trip a larger pattern at an odd generation, and check that it
+        // keeps evolving the same way.
+        let mut automaton =
+            load_rle("x = 8, y = 8, rule = B036/S1237\n4.o$5.o$4.o.o$5.o.o$o5.o$.o5.o$o.o$.o.o!");
+        for _ in 0..3 {
+            automaton.step();
+        }
+        let rle = automaton.to_rle();
+        let mut reloaded = load_rle(&rle);
+        assert_eq!(BigInt::from(3), reloaded.generations);
+        assert_eq!(stored_cells(&automaton), stored_cells(&reloaded));
+        assert_eq!(rle, reloaded.to_rle());
+        automaton.step();
+        reloaded.step();
+        assert_eq!(stored_cells(&automaton), stored_cells(&reloaded));
+
+        // Cells in the pattern's bounding rectangle that are left out of the
+        // RLE are empty, not in the background state.
+        let automaton = load_rle("#CXRLE Gen=1\nx = 2, y = 2, rule = B036/S1237\n$o!");
+        assert_eq!(1, automaton.tree.get_cell(&NdVec::big([0, -1])));
+        assert_eq!(1, automaton.tree.get_cell(&NdVec::big([1, -1])));
+        assert_eq!(0, automaton.tree.get_cell(&NdVec::big([0, -2])));
+        assert_eq!(1, automaton.tree.get_cell(&NdVec::big([1, -2])));
+        assert_eq!(0, automaton.tree.get_cell(&NdVec::big([2, -2])));
+    }
+
+    /// Tests that any RLE can be written and read back without changing the
+    /// pattern.
+    #[test]
+    fn test_rle_rewrite() {
+        for &s in &[
+            "
+#CXRLE Pos=10,-15
+# Comment
+x = 3, y = 3, rule = Life
+bo$2b
+o$3o!
+",
+            "x = 5, y = 1, rule = B3/S23\n5o!",
+            "#CXRLE Gen=4\nx = 1, y = 4, rule = B3/S23\n$$$o!",
+        ] {
+            let automaton = load_rle(s);
+            let rle = automaton.to_rle();
+            let reloaded = load_rle(&rle);
+            assert_eq!(automaton.generations, reloaded.generations);
+            assert_eq!(
+                automaton.tree.get_root().population,
+                reloaded.tree.get_root().population
+            );
+            let mut original_cells = vec![];
+            let mut reloaded_cells = vec![];
+            collect_cells(&automaton.tree.slice, &mut original_cells);
+            collect_cells(&reloaded.tree.slice, &mut reloaded_cells);
+            assert_eq!(
+                original_cells.into_iter().collect::<HashSet<_>>(),
+                reloaded_cells.into_iter().collect::<HashSet<_>>()
+            );
+            assert_eq!(rle, reloaded.to_rle());
+        }
+    }
 }
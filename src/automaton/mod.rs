@@ -14,6 +14,7 @@ pub mod projection;
 pub mod rule;
 pub mod simulation;
 pub mod space;
+mod topology;
 
 pub use io::*;
 pub use ndsimulate::*;
@@ -21,6 +22,7 @@ pub use projection::*;
 pub use rule::{DummyRule, Rule};
 pub use simulation::*;
 pub use space::*;
+pub use topology::*;
 
 /// ProjectedAutomaton functionality implemented by dispatching to
 /// NdProjectedAutomaton.
@@ -165,15 +167,28 @@ impl<D: Dim, P: Dim> NdProjectedAutomatonTrait<P> for NdProjectedAutomaton<D, P>
     }
 }
 
-/// A fully-fledged cellular automaton, including a grid (NdTree), rule & step
-/// size (Simulation), and generation count.
+/// A fully-fledged cellular automaton, including a grid (NdTree), rule, step
+/// size & algorithm (Simulation), and generation count.
 #[allow(missing_docs)]
 #[derive(Clone, Default)]
 pub struct NdAutomaton<D: Dim> {
     pub tree: NdTree<u8, D>,
     pub sim: Simulation<u8, D>,
-    // TODO make generation count be isize
-    pub generations: isize,
+    pub generations: BigInt,
+}
+impl<D: Dim> NdAutomaton<D> {
+    /// Returns the topology of the grid.
+    pub fn get_topology(&self) -> &Topology<D> {
+        self.sim.get_topology()
+    }
+    /// Sets the topology of the grid and removes any cells outside of it, or
+    /// returns an error if the rule cannot be simulated on it.
+    pub fn set_topology(&mut self, topology: Topology<D>) -> Result<(), String> {
+        self.sim.set_topology(topology)?;
+        self.sim.get_topology().clear_outside(&mut self.tree);
+        self.tree.shrink();
+        Ok(())
+    }
 }
 impl<D: Dim> NdSimulate for NdAutomaton<D> {
     fn get_ndim(&self) -> usize {
@@ -182,26 +197,93 @@ impl<D: Dim> NdSimulate for NdAutomaton<D> {
     fn get_population(&self) -> &BigInt {
         &self.tree.get_root().population
     }
-    fn get_step_size(&self) -> usize {
+    fn get_num_states(&self) -> usize {
+        self.sim.get_rule().num_states()
+    }
+    fn get_colors(&self) -> Option<&'static [[u8; 3]]> {
+        self.sim.get_rule().colors()
+    }
+    fn get_background(&self) -> u8 {
+        self.sim.get_background()
+    }
+    fn get_algorithm(&self) -> Algorithm {
+        self.sim.get_algorithm()
+    }
+    fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.sim.set_algorithm(algorithm);
+        self.sim.set_generation_count(&self.generations);
+    }
+    fn get_step_size(&self) -> &BigInt {
         self.sim.get_step_size()
     }
-    fn set_step_size(&mut self, step_size: usize) {
+    fn set_step_size(&mut self, step_size: BigInt) {
         self.sim.set_step_size(step_size);
     }
-    fn get_generation_count(&self) -> isize {
-        self.generations
+    fn get_hyperspeed_exponent(&self) -> Option<usize> {
+        self.sim.get_hyperspeed_exponent()
+    }
+    fn set_hyperspeed(&mut self, hyperspeed: bool) {
+        self.sim.set_hyperspeed(hyperspeed);
+    }
+    fn reset_hyperspeed(&mut self) {
+        self.sim.reset_hyperspeed();
     }
-    fn set_generation_count(&mut self, generations: isize) {
+    fn get_generation_count(&self) -> &BigInt {
+        &self.generations
+    }
+    fn set_generation_count(&mut self, generations: BigInt) {
         self.generations = generations;
+        self.sim.set_generation_count(&self.generations);
+    }
+    fn get_memory_budget(&self) -> usize {
+        self.sim.get_memory_budget()
+    }
+    fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.sim.set_memory_budget(memory_budget);
+    }
+    fn get_cache_stats(&self) -> CacheStats {
+        self.sim.get_cache_stats()
+    }
+    fn get_gc_root(&self) -> GcRoot {
+        GcRoot::new(&self.tree)
+    }
+    fn set_gc_roots(&mut self, roots: &[GcRoot]) {
+        self.sim.set_gc_roots(roots);
     }
     fn step(&mut self) {
+        // The step size may change during the step if hyperspeed is enabled.
+        let step_size = self.sim.get_step_size().clone();
+        self.sim.set_generation_count(&self.generations);
         self.sim.step(&mut self.tree);
-        self.generations += self.sim.get_step_size() as isize;
+        self.generations += step_size;
     }
     fn step_single(&mut self) {
+        self.sim.set_generation_count(&self.generations);
         self.sim.step_single(&mut self.tree);
         self.generations += 1;
     }
+    fn try_step(
+        &mut self,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        let step_size = self.sim.get_step_size().clone();
+        self.sim.set_generation_count(&self.generations);
+        self.sim.try_step(&mut self.tree, cancellation, progress)?;
+        self.generations += step_size;
+        Ok(())
+    }
+    fn try_step_single(
+        &mut self,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.sim.set_generation_count(&self.generations);
+        self.sim
+            .try_step_single(&mut self.tree, cancellation, progress)?;
+        self.generations += 1;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
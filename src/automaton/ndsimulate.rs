@@ -1,5 +1,7 @@
 use num::BigInt;
 
+use super::{Algorithm, CacheStats, CancellationToken, GcRoot, StepCancelled};
+
 /// Simulation-related methods whose type signatures are the same for all
 /// automata, regardless of dimensionality.
 pub trait NdSimulate {
@@ -7,18 +9,71 @@ pub trait NdSimulate {
     fn get_ndim(&self) -> usize;
     /// Returns the number of live cells in the simulation.
     fn get_population(&self) -> &BigInt;
+    /// Returns the number of cell states used by the rule.
+    fn get_num_states(&self) -> usize;
+    /// Returns the color of each cell state specified by the rule, if any.
+    fn get_colors(&self) -> Option<&'static [[u8; 3]]>;
+    /// Returns the state of the infinite background at the current
+    /// generation, relative to which cells are stored (see
+    /// `Simulator::get_background()`).
+    fn get_background(&self) -> u8;
+    /// Returns the algorithm used to simulate the automaton.
+    fn get_algorithm(&self) -> Algorithm;
+    /// Switches to a different algorithm, keeping the step size and other
+    /// settings.
+    fn set_algorithm(&mut self, algorithm: Algorithm);
     /// Returns the simulation step size.
-    fn get_step_size(&self) -> usize;
-    /// Sets the simulation step size.
-    fn set_step_size(&mut self, step_size: usize);
+    fn get_step_size(&self) -> &BigInt;
+    /// Sets the simulation step size, which is limited on bounded grids (see
+    /// `Simulator::set_step_size()`).
+    fn set_step_size(&mut self, step_size: BigInt);
+    /// Returns the base-2 logarithm of the step size if hyperspeed is enabled,
+    /// or `None` if it is disabled.
+    fn get_hyperspeed_exponent(&self) -> Option<usize>;
+    /// Enables or disables hyperspeed, in which the step size starts at 1 and
+    /// doubles after each step where the cache keeps up.
+    fn set_hyperspeed(&mut self, hyperspeed: bool);
+    /// Resets the step size to 1 if hyperspeed is enabled. This should be
+    /// called whenever the pattern is edited.
+    fn reset_hyperspeed(&mut self);
     /// Returns the number of generations that have elapsed in the simulation.
-    fn get_generation_count(&self) -> isize;
+    fn get_generation_count(&self) -> &BigInt;
     /// Sets the number of generations that have elapsed in the simulation.
-    fn set_generation_count(&mut self, generations: isize);
+    fn set_generation_count(&mut self, generations: BigInt);
+    /// Returns the maximum number of bytes that the simulation's caches should
+    /// use before cached results are evicted.
+    fn get_memory_budget(&self) -> usize;
+    /// Sets the maximum number of bytes that the simulation's caches should
+    /// use before cached results are evicted.
+    fn set_memory_budget(&mut self, memory_budget: usize);
+    /// Returns statistics about the memory used by the simulation's caches.
+    fn get_cache_stats(&self) -> CacheStats;
+    /// Returns the root of the automaton's pattern, which can be passed to
+    /// `set_gc_roots()` of another automaton.
+    fn get_gc_root(&self) -> GcRoot;
+    /// Sets the roots of other patterns (such as those in the undo history)
+    /// that should keep their cached results when garbage is collected.
+    fn set_gc_roots(&mut self, roots: &[GcRoot]);
     /// Step forward in the simulation by the step size.
     fn step(&mut self);
     /// Step forward one generation in the simulation.
     fn step_single(&mut self);
+    /// Step forward in the simulation by the step size, unless the step is
+    /// cancelled using the given token. See `Simulation::try_step()` for
+    /// details.
+    fn try_step(
+        &mut self,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled>;
+    /// Step forward one generation in the simulation, unless the step is
+    /// cancelled using the given token. See `Simulation::try_step()` for
+    /// details.
+    fn try_step_single(
+        &mut self,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled>;
 }
 
 /// A proxy trait for NdSimulate.
@@ -43,22 +98,75 @@ where
     fn get_population(&self) -> &BigInt {
         self.ndsim().get_population()
     }
-    fn get_step_size(&self) -> usize {
+    fn get_num_states(&self) -> usize {
+        self.ndsim().get_num_states()
+    }
+    fn get_colors(&self) -> Option<&'static [[u8; 3]]> {
+        self.ndsim().get_colors()
+    }
+    fn get_background(&self) -> u8 {
+        self.ndsim().get_background()
+    }
+    fn get_algorithm(&self) -> Algorithm {
+        self.ndsim().get_algorithm()
+    }
+    fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.ndsim_mut().set_algorithm(algorithm);
+    }
+    fn get_step_size(&self) -> &BigInt {
         self.ndsim().get_step_size()
     }
-    fn set_step_size(&mut self, step_size: usize) {
+    fn set_step_size(&mut self, step_size: BigInt) {
         self.ndsim_mut().set_step_size(step_size);
     }
-    fn get_generation_count(&self) -> isize {
+    fn get_hyperspeed_exponent(&self) -> Option<usize> {
+        self.ndsim().get_hyperspeed_exponent()
+    }
+    fn set_hyperspeed(&mut self, hyperspeed: bool) {
+        self.ndsim_mut().set_hyperspeed(hyperspeed);
+    }
+    fn reset_hyperspeed(&mut self) {
+        self.ndsim_mut().reset_hyperspeed();
+    }
+    fn get_generation_count(&self) -> &BigInt {
         self.ndsim().get_generation_count()
     }
-    fn set_generation_count(&mut self, generations: isize) {
+    fn set_generation_count(&mut self, generations: BigInt) {
         self.ndsim_mut().set_generation_count(generations);
     }
+    fn get_memory_budget(&self) -> usize {
+        self.ndsim().get_memory_budget()
+    }
+    fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.ndsim_mut().set_memory_budget(memory_budget);
+    }
+    fn get_cache_stats(&self) -> CacheStats {
+        self.ndsim().get_cache_stats()
+    }
+    fn get_gc_root(&self) -> GcRoot {
+        self.ndsim().get_gc_root()
+    }
+    fn set_gc_roots(&mut self, roots: &[GcRoot]) {
+        self.ndsim_mut().set_gc_roots(roots);
+    }
     fn step(&mut self) {
         self.ndsim_mut().step();
     }
     fn step_single(&mut self) {
         self.ndsim_mut().step_single();
     }
+    fn try_step(
+        &mut self,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.ndsim_mut().try_step(cancellation, progress)
+    }
+    fn try_step_single(
+        &mut self,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.ndsim_mut().try_step_single(cancellation, progress)
+    }
 }
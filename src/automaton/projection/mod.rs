@@ -38,7 +38,7 @@ impl<C: CellType, D: Dim, P: Dim> NdProjector<C, D, P> for NdProjection<C, D, P>
 
 /// A method for extracting or constructing a P-dimensional slice from a
 /// D-dimensional automaton.
-pub trait NdProjector<C: CellType, D: Dim, P: Dim> {
+pub trait NdProjector<C: CellType, D: Dim, P: Dim>: Send + Sync {
     /// Projects a D-dimensional NdTree into a P-dimensional NdTree.
     fn project(&self, tree: &NdTree<C, D>) -> NdTree<C, P>;
     /// Modifies part of a projected NdTree.
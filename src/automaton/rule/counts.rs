@@ -0,0 +1,67 @@
+//! Parsing and formatting of neighbor counts in rule strings.
+
+use std::fmt;
+
+/// Parses a set of neighbor counts, either as a string of digits (e.g. `345`)
+/// or as a comma-separated list of counts and inclusive ranges (e.g. `4,9-12`).
+/// Returns a Vec indexed by neighbor count.
+pub(super) fn parse_neighbor_counts(s: &str, max_neighbors: usize) -> Result<Vec<bool>, ()> {
+    let mut ret = vec![false; max_neighbors + 1];
+    let mut set = |n: usize| {
+        *ret.get_mut(n).ok_or(())? = true;
+        Ok(())
+    };
+    if s.contains(',') || s.contains('-') {
+        for item in s.split(',') {
+            let mut bounds = item
+                .splitn(2, '-')
+                .map(|n| n.parse::<usize>().map_err(|_| ()));
+            let lo = bounds.next().ok_or(())??;
+            let hi = bounds.next().unwrap_or(Ok(lo))?;
+            if lo > hi {
+                return Err(());
+            }
+            for n in lo..=hi {
+                set(n)?;
+            }
+        }
+    } else {
+        for ch in s.chars() {
+            set(ch.to_digit(10).ok_or(())? as usize)?;
+        }
+    }
+    Ok(ret)
+}
+
+/// Writes a set of neighbor counts in the format accepted by
+/// parse_neighbor_counts(), using a string of digits if possible.
+pub(super) fn write_neighbor_counts(f: &mut fmt::Formatter, counts: &[bool]) -> fmt::Result {
+    let included: Vec<usize> = (0..counts.len()).filter(|&n| counts[n]).collect();
+    if included.iter().all(|&n| n < 10) {
+        for n in included {
+            write!(f, "{}", n)?;
+        }
+        return Ok(());
+    }
+    // Group consecutive counts into ranges.
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for n in included {
+        match ranges.last_mut() {
+            Some((_, hi)) if *hi + 1 == n => *hi = n,
+            _ => ranges.push((n, n)),
+        }
+    }
+    let items: Vec<String> = ranges
+        .iter()
+        .map(|&(lo, hi)| {
+            // A single count above 9 must be written as a range so that it
+            // is not mistaken for a string of digits.
+            if lo == hi && ranges.len() > 1 {
+                lo.to_string()
+            } else {
+                format!("{}-{}", lo, hi)
+            }
+        })
+        .collect();
+    write!(f, "{}", items.join(","))
+}
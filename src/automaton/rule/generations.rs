@@ -0,0 +1,153 @@
+use super::counts::{parse_neighbor_counts, write_neighbor_counts};
+use super::Rule;
+use crate::automaton::space::*;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// A multi-state outer-totalistic range-1 Moore-neighborhood rule (e.g. Brian's
+/// Brain, `B2/S/C3`).
+///
+/// State 0 is dead and state 1 is alive. A live cell that does not survive
+/// enters state 2 and then "decays" through the remaining states, one per
+/// generation, until it dies. Only live cells count as neighbors.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Generations<D: Dim> {
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    states: u8,
+    neighbor_offsets: Vec<IVec<D>>,
+}
+impl<D: Dim> fmt::Debug for Generations<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Generations<Dim{}D>({})", D::NDIM, self)
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for Generations<D> {
+    type Error = ();
+    /// Parses a rule string in B/S/C notation (e.g. `B2/S/C3`) or S/B/C
+    /// notation (e.g. `345/2/4`).
+    ///
+    /// Neighbor counts are either a string of digits (e.g. `345`) or a
+    /// comma-separated list of counts and ranges (e.g. `4,9-12`), which allows
+    /// counts above 9 in higher dimensions.
+    fn try_from(s: &str) -> Result<Self, ()> {
+        let captures = [
+            r"^[Bb](?P<b>[\d,\-]*)/[Ss](?P<s>[\d,\-]*)/[CcGg]?(?P<c>\d+)$",
+            r"^[Ss](?P<s>[\d,\-]*)/[Bb](?P<b>[\d,\-]*)/[CcGg]?(?P<c>\d+)$",
+            r"^(?P<s>[\d,\-]*)/(?P<b>[\d,\-]*)/[CcGg]?(?P<c>\d+)$",
+        ]
+        .iter()
+        .find_map(|regex| Regex::new(regex).unwrap().captures(s))
+        .ok_or(())?;
+        let states: u8 = captures["c"].parse().map_err(|_| ())?;
+        if states < 2 {
+            return Err(());
+        }
+        let neighbor_offsets: Vec<IVec<D>> = IRect::<D>::centered(NdVec::origin(), 1)
+            .iter()
+            .filter(|offset| *offset != NdVec::origin())
+            .collect();
+        let max_neighbors = neighbor_offsets.len();
+        Ok(Self {
+            birth: parse_neighbor_counts(&captures["b"], max_neighbors)?,
+            survival: parse_neighbor_counts(&captures["s"], max_neighbors)?,
+            states,
+            neighbor_offsets,
+        })
+    }
+}
+
+impl<D: Dim> fmt::Display for Generations<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        write_neighbor_counts(f, &self.birth)?;
+        write!(f, "/S")?;
+        write_neighbor_counts(f, &self.survival)?;
+        write!(f, "/C{}", self.states)
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for Generations<D> {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn num_states(&self) -> usize {
+        self.states as usize
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        let live_neighbors = || {
+            self.neighbor_offsets
+                .iter()
+                .filter(|offset| napkin[offset] == 1)
+                .count()
+        };
+        match napkin[&NdVec::origin()] {
+            0 => self.birth[live_neighbors()] as u8,
+            1 if self.survival[live_neighbors()] => 1,
+            cell_state if cell_state < self.states => (cell_state + 1) % self.states,
+            // States that this rule doesn't have (e.g. from an RLE) die.
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generations_parse() {
+        for &(input, canonical) in &[
+            ("B2/S/C3", "B2/S/C3"),
+            ("/2/3", "B2/S/C3"),
+            ("345/2/4", "B2/S345/C4"),
+            ("S345/B2/C4", "B2/S345/C4"),
+            ("B3/S23/C2", "B3/S23/C2"),
+            ("b2/s/g3", "B2/S/C3"),
+        ] {
+            let rule = Generations::<Dim2D>::try_from(input).unwrap();
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule, Generations::<Dim2D>::try_from(canonical).unwrap());
+        }
+
+        // Counts above 9 are only allowed in higher dimensions.
+        for &(input, canonical) in &[
+            ("B5/S45/C5", "B5/S45/C5"),
+            ("B4,9-12/S10-10/C3", "B4,9-12/S10-10/C3"),
+            ("B4,9,10,11,12/S/C3", "B4,9-12/S/C3"),
+            ("B13-14,26/S/C10", "B13-14,26/S/C10"),
+        ] {
+            let rule = Generations::<Dim3D>::try_from(input).unwrap();
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule, Generations::<Dim3D>::try_from(canonical).unwrap());
+        }
+        assert!(Generations::<Dim2D>::try_from("B10-10/S/C3").is_err());
+        assert!(Generations::<Dim2D>::try_from("B9/S/C3").is_err());
+        assert!(Generations::<Dim3D>::try_from("B27-27/S/C3").is_err());
+
+        for &s in &[
+            "B3/S23",
+            "B2/S/C1",
+            "B2/S/C256",
+            "B2/S/C",
+            "B3-1/S/C3",
+            "B3,/S/C3",
+        ] {
+            assert!(Generations::<Dim2D>::try_from(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_generations_invalid_states() {
+        let rule = Generations::<Dim2D>::try_from("B2/S/C3").unwrap();
+        for &(center, expected) in &[(2, 0), (3, 0), (200, 0), (255, 0)] {
+            let mut cells = vec![0; 9];
+            cells[4] = center;
+            let napkin = NdArray::from_flat_vec(UVec::repeat(3_usize), cells);
+            let napkin = napkin.offset_slice(-IVec::repeat(1_isize));
+            assert_eq!(expected, rule.transition(&napkin), "{}", center);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use super::Rule;
+use crate::automaton::space::*;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The letters used for each neighbor count in Hensel notation, in canonical
+/// order.
+const HENSEL_LETTERS: [&str; 9] = [
+    "",
+    "ce",
+    "cekain",
+    "cekainyqjr",
+    "cekainyqjrtwz",
+    "cekainyqjr",
+    "cekain",
+    "ce",
+    "",
+];
+
+/// A representative neighborhood for each Hensel letter with at most four
+/// neighbors, with one bit per neighbor in the order N, NE, E, SE, S, SW, W, NW
+/// (starting from the least significant bit). Neighborhoods with more than
+/// four neighbors are the complements of these.
+const HENSEL_REPRESENTATIVES: &[(usize, char, [u8; 8])] = &[
+    (1, 'c', [0, 1, 0, 0, 0, 0, 0, 0]),
+    (1, 'e', [1, 0, 0, 0, 0, 0, 0, 0]),
+    (2, 'c', [0, 1, 0, 1, 0, 0, 0, 0]),
+    (2, 'e', [1, 0, 1, 0, 0, 0, 0, 0]),
+    (2, 'k', [1, 0, 0, 1, 0, 0, 0, 0]),
+    (2, 'a', [1, 1, 0, 0, 0, 0, 0, 0]),
+    (2, 'i', [1, 0, 0, 0, 1, 0, 0, 0]),
+    (2, 'n', [0, 1, 0, 0, 0, 1, 0, 0]),
+    (3, 'c', [0, 1, 0, 1, 0, 1, 0, 0]),
+    (3, 'e', [1, 0, 1, 0, 1, 0, 0, 0]),
+    (3, 'k', [1, 0, 1, 0, 0, 1, 0, 0]),
+    (3, 'a', [1, 1, 1, 0, 0, 0, 0, 0]),
+    (3, 'i', [1, 1, 0, 0, 0, 0, 0, 1]),
+    (3, 'n', [1, 1, 0, 1, 0, 0, 0, 0]),
+    (3, 'y', [1, 0, 0, 1, 0, 1, 0, 0]),
+    (3, 'q', [1, 1, 0, 0, 0, 1, 0, 0]),
+    (3, 'j', [1, 1, 0, 0, 0, 0, 1, 0]),
+    (3, 'r', [1, 1, 0, 0, 1, 0, 0, 0]),
+    (4, 'c', [0, 1, 0, 1, 0, 1, 0, 1]),
+    (4, 'e', [1, 0, 1, 0, 1, 0, 1, 0]),
+    (4, 'k', [1, 1, 0, 1, 0, 0, 1, 0]),
+    (4, 'a', [1, 1, 1, 1, 0, 0, 0, 0]),
+    (4, 'i', [1, 1, 0, 1, 1, 0, 0, 0]),
+    (4, 'n', [1, 1, 0, 1, 0, 0, 0, 1]),
+    (4, 'y', [1, 1, 0, 1, 0, 1, 0, 0]),
+    (4, 'q', [1, 1, 1, 0, 0, 1, 0, 0]),
+    (4, 'j', [1, 1, 0, 0, 1, 0, 1, 0]),
+    (4, 'r', [1, 1, 1, 0, 1, 0, 0, 0]),
+    (4, 't', [1, 1, 0, 0, 1, 0, 0, 1]),
+    (4, 'w', [1, 1, 0, 0, 0, 1, 1, 0]),
+    (4, 'z', [1, 1, 0, 0, 1, 1, 0, 0]),
+];
+
+/// The offsets of the neighbors, in the order N, NE, E, SE, S, SW, W, NW. (Y
+/// increases upwards in NDCell, so north is +Y.)
+const NEIGHBOR_OFFSETS: [[isize; 2]; 8] = [
+    [0, 1],
+    [1, 1],
+    [1, 0],
+    [1, -1],
+    [0, -1],
+    [-1, -1],
+    [-1, 0],
+    [-1, 1],
+];
+
+/// Returns a representative neighborhood (in the same format as
+/// HENSEL_REPRESENTATIVES, but packed into a u8) for the given neighbor count
+/// and Hensel letter, or None if that letter is not valid for that count.
+fn hensel_representative(count: usize, letter: Option<char>) -> Option<u8> {
+    match (count, letter) {
+        (0, None) => Some(0),
+        (8, None) => Some(0xFF),
+        (5..=7, Some(_)) => hensel_representative(8 - count, letter).map(|n| !n),
+        (_, Some(letter)) => HENSEL_REPRESENTATIVES
+            .iter()
+            .find(|&&(c, l, _)| c == count && l == letter)
+            .map(|(_, _, neighbors)| {
+                neighbors
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &neighbor| (acc << 1) | neighbor)
+            }),
+        _ => None,
+    }
+}
+
+/// Returns the smallest neighborhood equivalent to the given one under rotation
+/// and reflection.
+fn symmetry_class(neighbors: u8) -> u8 {
+    let reflected = (0..8).fold(0, |acc, i| acc | (((neighbors >> i) & 1) << ((8 - i) % 8)));
+    (0..4)
+        .flat_map(|r| {
+            // Rotating by 90 degrees moves each neighbor two places along the
+            // ring.
+            vec![neighbors.rotate_left(2 * r), reflected.rotate_left(2 * r)]
+        })
+        .min()
+        .unwrap()
+}
+
+/// A 2-state isotropic non-totalistic 2D range-1 Moore-neighborhood rule,
+/// described using Hensel notation (e.g. `B2n3/S23-q`).
+#[derive(Clone)]
+pub struct Isotropic2D {
+    /// The next state of the center cell for each 3x3 neighborhood, indexed
+    /// using the state of the center cell as the most significant bit followed
+    /// by the neighbors in the order N, NE, E, SE, S, SW, W, NW.
+    table: Box<[u8; 512]>,
+}
+impl fmt::Debug for Isotropic2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Isotropic2D({})", self)
+    }
+}
+
+impl TryFrom<&str> for Isotropic2D {
+    type Error = ();
+    /// Parses a rule string in Hensel notation (e.g. `B2n3/S23-q`).
+    fn try_from(s: &str) -> Result<Self, ()> {
+        let regex =
+            Regex::new(r"^[Bb](?P<b>[0-8cekainyqjrtwz\-]*)/?[Ss](?P<s>[0-8cekainyqjrtwz\-]*)$")
+                .unwrap();
+        let captures = regex.captures(s).ok_or(())?;
+        let mut table = Box::new([0; 512]);
+        for &(center, conditions) in &[(0, &captures["b"]), (1, &captures["s"])] {
+            for neighbors in parse_conditions(conditions)? {
+                let class = symmetry_class(neighbors);
+                for i in 0..=255 {
+                    if symmetry_class(i) == class {
+                        table[center << 8 | i as usize] = 1;
+                    }
+                }
+            }
+        }
+        Ok(Self { table })
+    }
+}
+
+/// Parses the conditions for birth or survival (e.g. `23-q`), returning a
+/// representative neighborhood for each included Hensel class.
+fn parse_conditions(s: &str) -> Result<Vec<u8>, ()> {
+    let mut ret = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let count = ch.to_digit(10).ok_or(())? as usize;
+        let negate = chars.peek() == Some(&'-');
+        if negate {
+            chars.next();
+        }
+        let mut letters = String::new();
+        while let Some(&letter) = chars.peek() {
+            if letter.is_ascii_digit() {
+                break;
+            }
+            if !HENSEL_LETTERS[count].contains(letter) {
+                return Err(());
+            }
+            letters.push(letter);
+            chars.next();
+        }
+        if negate && letters.is_empty() {
+            return Err(());
+        }
+        if HENSEL_LETTERS[count].is_empty() {
+            ret.push(hensel_representative(count, None).ok_or(())?);
+            continue;
+        }
+        for letter in HENSEL_LETTERS[count].chars() {
+            // With no letters, every neighborhood with this count is included.
+            if letters.is_empty() || letters.contains(letter) != negate {
+                ret.push(hensel_representative(count, Some(letter)).ok_or(())?);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+impl fmt::Display for Isotropic2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &(center, prefix) in &[(0, "B"), (1, "/S")] {
+            write!(f, "{}", prefix)?;
+            for (count, &all_letters) in HENSEL_LETTERS.iter().enumerate() {
+                let is_included = |neighbors: u8| self.table[center << 8 | neighbors as usize] != 0;
+                if all_letters.is_empty() {
+                    if is_included(hensel_representative(count, None).unwrap()) {
+                        write!(f, "{}", count)?;
+                    }
+                    continue;
+                }
+                let (included, excluded): (String, String) =
+                    all_letters.chars().partition(|&letter| {
+                        is_included(hensel_representative(count, Some(letter)).unwrap())
+                    });
+                // Write whichever form is shorter, preferring to list included
+                // letters.
+                if included.is_empty() {
+                    continue;
+                } else if excluded.is_empty() {
+                    write!(f, "{}", count)?;
+                } else if included.len() <= excluded.len() {
+                    write!(f, "{}{}", count, included)?;
+                } else {
+                    write!(f, "{}-{}", count, excluded)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Rule<u8, Dim2D> for Isotropic2D {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn transition(&self, napkin: &ArraySlice2D<u8>) -> u8 {
+        let mut idx = (napkin[&NdVec::origin()] != 0) as usize;
+        for &offset in NEIGHBOR_OFFSETS.iter().rev() {
+            idx = (idx << 1) | (napkin[&NdVec(offset)] != 0) as usize;
+        }
+        self.table[idx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Tests that the Hensel letters describe every neighborhood exactly once.
+    #[test]
+    fn test_hensel_classes() {
+        let mut classes = HashSet::new();
+        for (count, &letters) in HENSEL_LETTERS.iter().enumerate() {
+            let representatives: Vec<u8> = if letters.is_empty() {
+                vec![hensel_representative(count, None).unwrap()]
+            } else {
+                letters
+                    .chars()
+                    .map(|letter| hensel_representative(count, Some(letter)).unwrap())
+                    .collect()
+            };
+            for neighbors in representatives {
+                assert_eq!(count, neighbors.count_ones() as usize);
+                assert!(classes.insert(symmetry_class(neighbors)));
+            }
+        }
+        assert_eq!(51, classes.len());
+        let all_classes: HashSet<u8> = (0..=255).map(symmetry_class).collect();
+        assert_eq!(all_classes, classes);
+    }
+
+    #[test]
+    fn test_isotropic_2d_parse() {
+        for &(input, canonical) in &[
+            ("B2n3/S23-q", "B2n3/S23-q"),
+            ("b2n3s23-q", "B2n3/S23-q"),
+            ("B3/S23", "B3/S23"),
+            ("B3cekainyqjr/S2cekain3", "B3/S23"),
+            ("B2-cekai/S", "B2n/S"),
+            ("B2e3-ae/S2-i34cqk", "B2e3-ea/S2-i34ckq"),
+            ("B0/S8", "B0/S8"),
+            ("B/S", "B/S"),
+        ] {
+            let rule = Isotropic2D::try_from(input).unwrap();
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule.table, Isotropic2D::try_from(canonical).unwrap().table);
+        }
+
+        for &s in &[
+            "",
+            "B3/S23V",
+            "B1a/S",
+            "B0c/S",
+            "B2-/S",
+            "B9/S",
+            "B2n3/S23-q/C3",
+        ] {
+            assert!(Isotropic2D::try_from(s).is_err(), "{:?}", s);
+        }
+    }
+}
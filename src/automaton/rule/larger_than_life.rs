@@ -0,0 +1,244 @@
+use super::{max_neighborhood_radius, NdNeighborhood, Rule};
+use crate::automaton::space::*;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// A neighborhood shape that can be used by a Larger-than-Life rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LtlNeighborhood {
+    /// Every cell within the hypercube of the given radius (`NM`).
+    Moore,
+    /// Every cell within the given Manhattan (L1) distance (`NN`).
+    VonNeumann,
+    /// Every cell whose squared Euclidean distance is at most `r^2 + r`
+    /// (`NC`).
+    Circular,
+}
+impl LtlNeighborhood {
+    /// Returns the offsets of all the cells in the neighborhood of the given
+    /// radius, not including the center cell.
+    fn offsets<D: Dim>(self, radius: usize) -> Vec<IVec<D>> {
+        match self {
+            Self::Moore => NdNeighborhood::Moore.offsets(radius),
+            Self::VonNeumann => NdNeighborhood::VonNeumann.offsets(radius),
+            Self::Circular => {
+                let radius = radius as isize;
+                IRect::<D>::centered(NdVec::origin(), radius)
+                    .iter()
+                    .filter(|offset| {
+                        *offset != NdVec::origin()
+                            && D::axes().iter().map(|&ax| offset[ax].pow(2)).sum::<isize>()
+                                <= radius * radius + radius
+                    })
+                    .collect()
+            }
+        }
+    }
+    /// Returns the letter used for this neighborhood in rule strings.
+    fn letter(self) -> char {
+        match self {
+            Self::Moore => 'M',
+            Self::VonNeumann => 'N',
+            Self::Circular => 'C',
+        }
+    }
+}
+
+/// A Larger-than-Life rule (e.g. Bosco's Rule, `R5,C0,M1,S34..58,B34..45,NM`).
+///
+/// This is an outer-totalistic rule with a large radius, where birth and
+/// survival each happen for a single range of neighbor counts. Like
+/// Generations rules, rules with more than two states have dying cells that
+/// decay through the remaining states, and only live cells (state 1) are
+/// counted as neighbors.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LargerThanLife<D: Dim> {
+    radius: usize,
+    /// The `C` value from the rule string (0 and 1 both mean 2 states).
+    states: u8,
+    /// Whether the center cell is counted in its own neighborhood.
+    include_middle: bool,
+    survival: RangeInclusive<usize>,
+    birth: RangeInclusive<usize>,
+    neighborhood: LtlNeighborhood,
+    neighbor_offsets: Vec<IVec<D>>,
+}
+impl<D: Dim> fmt::Debug for LargerThanLife<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LargerThanLife<Dim{}D>({})", D::NDIM, self)
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for LargerThanLife<D> {
+    type Error = ();
+    /// Parses a rule string in Golly's Larger-than-Life syntax (e.g.
+    /// `R5,C0,M1,S34..58,B34..45,NM`).
+    fn try_from(s: &str) -> Result<Self, ()> {
+        let regex = Regex::new(
+            r"^R(?P<r>\d+),C(?P<c>\d+),M(?P<m>[01]),S(?P<s1>\d+)\.\.(?P<s2>\d+),B(?P<b1>\d+)\.\.(?P<b2>\d+),N(?P<n>[MNC])$",
+        )
+        .unwrap();
+        let captures = regex.captures(s).ok_or(())?;
+        let number = |name: &str| captures[name].parse::<usize>().map_err(|_| ());
+
+        let radius = number("r")?;
+        // In 2D this is 500, the same as in Golly.
+        if !(1..=max_neighborhood_radius::<D>()).contains(&radius) {
+            return Err(());
+        }
+        let states = number("c")?;
+        if states > 255 {
+            return Err(());
+        }
+        let neighborhood = match &captures["n"] {
+            "M" => LtlNeighborhood::Moore,
+            "N" => LtlNeighborhood::VonNeumann,
+            _ => LtlNeighborhood::Circular,
+        };
+        Ok(Self {
+            radius,
+            states: states as u8,
+            include_middle: &captures["m"] == "1",
+            survival: number("s1")?..=number("s2")?,
+            birth: number("b1")?..=number("b2")?,
+            neighborhood,
+            neighbor_offsets: neighborhood.offsets(radius),
+        })
+    }
+}
+
+impl<D: Dim> fmt::Display for LargerThanLife<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.radius,
+            self.states,
+            self.include_middle as u8,
+            self.survival.start(),
+            self.survival.end(),
+            self.birth.start(),
+            self.birth.end(),
+            self.neighborhood.letter(),
+        )
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for LargerThanLife<D> {
+    fn radius(&self) -> usize {
+        self.radius
+    }
+    fn num_states(&self) -> usize {
+        std::cmp::max(self.states as usize, 2)
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        let center = napkin[&NdVec::origin()];
+        if center as usize >= self.num_states() {
+            // This state isn't part of the rule, so treat it as dead.
+            return 0;
+        }
+        if center > 1 {
+            // Dying cells decay regardless of their neighbors.
+            return (center as usize + 1) as u8 % self.num_states() as u8;
+        }
+        let mut live_neighbors = self
+            .neighbor_offsets
+            .iter()
+            .filter(|offset| napkin[offset] == 1)
+            .count();
+        if self.include_middle && center == 1 {
+            live_neighbors += 1;
+        }
+        if center == 0 {
+            self.birth.contains(&live_neighbors) as u8
+        } else if self.survival.contains(&live_neighbors) {
+            1
+        } else {
+            2 % self.num_states() as u8
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ltl_neighborhood_sizes() {
+        use LtlNeighborhood::*;
+        for &(neighborhood, radius, size) in &[
+            (Moore, 5, 120),
+            (VonNeumann, 5, 60),
+            (Circular, 1, 8),
+            (Circular, 2, 20),
+            (Circular, 5, 96),
+        ] {
+            assert_eq!(size, neighborhood.offsets::<Dim2D>(radius).len());
+        }
+    }
+
+    #[test]
+    fn test_ltl_parse() {
+        for &s in &[
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R1,C0,M0,S2..3,B3..3,NM",
+            "R10,C4,M1,S123..212,B123..170,NN",
+            "R7,C2,M0,S1..4,B2..3,NC",
+        ] {
+            let rule = LargerThanLife::<Dim2D>::try_from(s).unwrap();
+            assert_eq!(s, rule.to_string());
+        }
+
+        assert_eq!(
+            2,
+            LargerThanLife::<Dim2D>::try_from("R5,C0,M1,S34..58,B34..45,NM")
+                .unwrap()
+                .num_states()
+        );
+        assert_eq!(
+            4,
+            LargerThanLife::<Dim2D>::try_from("R10,C4,M1,S123..212,B123..170,NN")
+                .unwrap()
+                .num_states()
+        );
+
+        for &s in &[
+            "R0,C0,M1,S34..58,B34..45,NM",
+            "R501,C0,M1,S34..58,B34..45,NM",
+            "R5,C0,M2,S34..58,B34..45,NM",
+            "R5,C0,M1,S34..58,B34..45,NX",
+            "R5,C256,M1,S34..58,B34..45,NM",
+            "R5,C0,M1,S34,B34..45,NM",
+            "R5,M1,S34..58,B34..45,NM",
+        ] {
+            assert!(LargerThanLife::<Dim2D>::try_from(s).is_err(), "{:?}", s);
+        }
+
+        // The largest radius is smaller in higher dimensions.
+        assert!(LargerThanLife::<Dim3D>::try_from("R5,C0,M1,S34..58,B34..45,NM").is_ok());
+        assert!(LargerThanLife::<Dim3D>::try_from("R50,C0,M1,S34..58,B34..45,NM").is_err());
+        assert!(LargerThanLife::<Dim3D>::try_from("R500,C0,M1,S34..58,B34..45,NM").is_err());
+    }
+
+    #[test]
+    fn test_ltl_invalid_states() {
+        for &(rule, center, expected) in &[
+            ("R1,C0,M0,S2..3,B3..3,NM", 2, 0),
+            ("R1,C0,M0,S2..3,B3..3,NM", 3, 0),
+            ("R1,C0,M0,S2..3,B3..3,NM", 255, 0),
+            ("R1,C4,M0,S2..3,B3..3,NM", 2, 3),
+            ("R1,C4,M0,S2..3,B3..3,NM", 3, 0),
+            ("R1,C4,M0,S2..3,B3..3,NM", 4, 0),
+            ("R1,C4,M0,S2..3,B3..3,NM", 200, 0),
+        ] {
+            let rule = LargerThanLife::<Dim2D>::try_from(rule).unwrap();
+            let mut cells = vec![1; 9];
+            cells[4] = center;
+            let napkin = NdArray::from_flat_vec(UVec::repeat(3_usize), cells);
+            let napkin = napkin.offset_slice(-IVec::repeat(1_isize));
+            assert_eq!(expected, rule.transition(&napkin), "{} {}", rule, center);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use super::Rule;
+use crate::automaton::space::*;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::Arc;
+
+/// The largest number of entries allowed in a lookup table (1 MiB).
+const MAX_TABLE_SIZE: usize = 1 << 20;
+
+/// A wrapper around another rule that precomputes the result of the transition
+/// function for every possible napkin and stores them in a lookup table.
+///
+/// This is only possible for rules with few states and a small radius (e.g.
+/// 2-state rules with the range-1 Moore neighborhood in 2D, which need a table
+/// of 512 entries), but for those rules a single table lookup is usually faster
+/// than evaluating the original transition function.
+#[derive(Clone)]
+pub struct LutRule<D: Dim> {
+    rule: Arc<dyn Rule<u8, D>>,
+    /// The offsets of the cells in the napkin, with the first being the least
+    /// significant digit of the index into the table.
+    offsets: Vec<IVec<D>>,
+    table: Vec<u8>,
+}
+impl<D: Dim> fmt::Debug for LutRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LutRule({:?})", self.rule)
+    }
+}
+impl<D: Dim> fmt::Display for LutRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rule)
+    }
+}
+
+impl<D: Dim> LutRule<D> {
+    /// Computes the lookup table for the given rule, or returns None if the
+    /// table would be too large or the rule is a block rule.
+    pub fn new(rule: Arc<dyn Rule<u8, D>>) -> Option<Self> {
+        if rule.as_block_rule().is_some() {
+            return None;
+        }
+        let num_states = rule.num_states();
+        // Check the size of the table before listing the napkin's cells, since
+        // rules with a large radius have far too many of them.
+        let napkin_len = rule
+            .radius()
+            .checked_mul(2)
+            .and_then(|diameter| (diameter + 1).checked_pow(D::NDIM as u32))?;
+        let table_size = num_states
+            .checked_pow(u32::try_from(napkin_len).ok()?)
+            .filter(|&size| size <= MAX_TABLE_SIZE)?;
+        let radius = rule.radius() as isize;
+        let offsets: Vec<IVec<D>> = IRect::<D>::centered(NdVec::origin(), radius)
+            .iter()
+            .collect();
+
+        // Try every napkin, counting up in base `num_states` so that only the
+        // cells whose states change need to be updated each time.
+        let mut napkin = NdArray::from_flat_vec(
+            UVec::repeat(2 * radius as usize + 1),
+            vec![0; offsets.len()],
+        );
+        let corner = IVec::<D>::repeat(radius);
+        let positions: Vec<IVec<D>> = offsets
+            .iter()
+            .map(|offset| offset.clone() + corner.clone())
+            .collect();
+        let mut table = Vec::with_capacity(table_size);
+        loop {
+            table.push(rule.transition(&napkin.offset_slice(-&corner)));
+            let mut carry = true;
+            for pos in &positions {
+                let cell = &mut napkin[pos];
+                *cell += 1;
+                if *cell as usize == num_states {
+                    *cell = 0;
+                } else {
+                    carry = false;
+                    break;
+                }
+            }
+            if carry {
+                break;
+            }
+        }
+        assert_eq!(table_size, table.len());
+
+        Some(Self {
+            rule,
+            offsets,
+            table,
+        })
+    }
+
+    /// Returns the lookup table version of the given rule, or the rule itself
+    /// if the table would be too large.
+    pub fn compile(rule: Arc<dyn Rule<u8, D>>) -> Arc<dyn Rule<u8, D>> {
+        match Self::new(rule.clone()) {
+            Some(lut_rule) => Arc::new(lut_rule),
+            None => rule,
+        }
+    }
+
+    /// Returns the rule that this lookup table was computed from.
+    pub fn inner(&self) -> &Arc<dyn Rule<u8, D>> {
+        &self.rule
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for LutRule<D> {
+    fn radius(&self) -> usize {
+        self.rule.radius()
+    }
+    fn num_states(&self) -> usize {
+        self.rule.num_states()
+    }
+    fn colors(&self) -> Option<&'static [[u8; 3]]> {
+        self.rule.colors()
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        let num_states = self.num_states();
+        let mut idx = 0;
+        for offset in self.offsets.iter().rev() {
+            let cell_state = napkin[offset] as usize;
+            if cell_state >= num_states {
+                // This napkin is not in the table.
+                return self.rule.transition(napkin);
+            }
+            idx = idx * num_states + cell_state;
+        }
+        self.table[idx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton::rule;
+
+    #[test]
+    fn test_lut_size() {
+        let lut_rule = LutRule::<Dim2D>::new(Arc::new(rule::LIFE)).unwrap();
+        assert_eq!(512, lut_rule.table.len());
+        assert_eq!("B3/S23", lut_rule.to_string());
+
+        // A 4-state rule with the range-1 Moore neighborhood in 2D needs 4^9
+        // entries.
+        let wireworld = rule::from_str_uncompiled::<Dim2D>("WireWorld").unwrap();
+        let lut_rule = LutRule::new(wireworld).unwrap();
+        assert_eq!(1 << 18, lut_rule.table.len());
+        assert!(lut_rule.colors().is_some());
+
+        // Rules with larger napkins fall back to the original rule.
+        let rule = rule::from_str::<Dim3D>("B5/S45").unwrap();
+        assert!(LutRule::new(rule.clone()).is_none());
+        assert_eq!("B5/S45", LutRule::compile(rule).to_string());
+        let rule = rule::from_str::<Dim2D>("B3/S23/N2").unwrap();
+        assert!(LutRule::new(rule).is_none());
+
+        // The napkin is never listed for rules with huge radii.
+        for &radius in &[1 << 20, 1 << 40, usize::MAX] {
+            assert!(LutRule::<Dim3D>::new(Arc::new(HugeRule(radius))).is_none());
+        }
+    }
+
+    #[derive(Debug)]
+    struct HugeRule(usize);
+    impl fmt::Display for HugeRule {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Huge")
+        }
+    }
+    impl<D: Dim> Rule<u8, D> for HugeRule {
+        fn radius(&self) -> usize {
+            self.0
+        }
+        fn transition(&self, _napkin: &NdArraySlice<u8, D>) -> u8 {
+            0
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use super::Rule;
+use crate::automaton::space::*;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A block cellular automaton rule, which divides the grid into blocks of 2^D
+/// cells (e.g. 2x2 in 2D) and computes the next state of each block from only
+/// the cells in that block.
+///
+/// The partition alternates each generation, as in the Margolus neighborhood:
+/// on even generations the lowest corner of each block has even coordinates on
+/// every axis, and on odd generations it has odd coordinates.
+///
+/// A block whose cells are all in the same state must become another such
+/// block, so that the infinite background stays uniform. It may still change
+/// state, though (e.g. Critters and Tron complement empty blocks, so the
+/// background "flashes" every other generation).
+pub trait BlockRule<C: CellType, D: Dim> {
+    /// Computes the next states of the cells in a block, on a generation with
+    /// the given parity. The cell at `(x, y, z, ...)` relative to the lowest
+    /// corner of the block has the index `x + 2y + 4z + ...`.
+    fn transition_block(&self, block: &mut [C], odd_generation: bool);
+}
+
+/// Returns whether simulating the given block rule on blocks that are all the
+/// same state always gives blocks that are all the same state, starting from
+/// empty blocks on an even generation. Block rules that don't can't be
+/// simulated on an infinite grid, since empty space would not stay uniform.
+pub fn keeps_background_uniform<C: CellType, D: Dim>(rule: &dyn BlockRule<C, D>) -> bool {
+    let mut seen = vec![];
+    let mut phase = (C::default(), false);
+    while !seen.contains(&phase) {
+        seen.push(phase);
+        let (background, odd_generation) = phase;
+        let mut block = vec![background; D::TREE_BRANCHES];
+        rule.transition_block(&mut block, odd_generation);
+        if block.iter().any(|&cell| cell != block[0]) {
+            return false;
+        }
+        phase = (block[0], !odd_generation);
+    }
+    true
+}
+
+/// Returns the index of a cell within a block, given its position relative
+/// to the lowest corner of the block.
+pub fn block_index<D: Dim>(pos: &IVec<D>) -> usize {
+    D::axes()
+        .iter()
+        .enumerate()
+        .map(|(i, &ax)| (pos[ax] as usize & 1) << i)
+        .sum()
+}
+
+/// Returns the position of a cell relative to the lowest corner of its block,
+/// given its index within the block.
+pub fn block_offset<D: Dim>(idx: usize) -> IVec<D> {
+    let mut ret = IVec::origin();
+    for (i, &ax) in D::axes().iter().enumerate() {
+        ret[ax] = (idx >> i & 1) as isize;
+    }
+    ret
+}
+
+/// A 2-state Margolus rule given by a lookup table, in MCell's notation (e.g.
+/// Critters, `MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0`).
+///
+/// Each block of 2^D cells is numbered by adding together the bits of its live
+/// cells, and the table gives the new number of each block. In 2D the upper
+/// left cell of the block has the bit 1, the upper right has 2, the lower left
+/// has 4, and the lower right has 8, the same as MCell. (Y increases upwards
+/// in NDCell, so the "upper" cells are the ones with larger Y coordinates.)
+/// The same ordering is used in 1D and 3D, with the bits for Z being 16
+/// through 128.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MargolusRule<D: Dim> {
+    table: Vec<u8>,
+    _phantom: PhantomData<D>,
+}
+impl<D: Dim> fmt::Debug for MargolusRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MargolusRule<Dim{}D>({})", D::NDIM, self)
+    }
+}
+
+impl<D: Dim> MargolusRule<D> {
+    /// Returns the bit for the cell with the given index within a block.
+    fn cell_bit(idx: usize) -> usize {
+        // Flip Y so that the upper cells come first, as in MCell.
+        if D::NDIM >= 2 {
+            1 << (idx ^ 2)
+        } else {
+            1 << idx
+        }
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for MargolusRule<D> {
+    type Error = ();
+    /// Parses a rule string in MCell's Margolus notation (e.g.
+    /// `MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0`), which must contain a table
+    /// entry for every possible block of 2^D cells. Only 1D, 2D and 3D are
+    /// supported. Empty space must stay uniform: starting from empty blocks,
+    /// each uniform block that the background can become must become another
+    /// uniform block (see `keeps_background_uniform()`).
+    fn try_from(s: &str) -> Result<Self, ()> {
+        if D::NDIM > 3 {
+            return Err(());
+        }
+        let table_size = 1 << (1 << D::NDIM);
+        let entries = s.strip_prefix("MS,D").ok_or(())?;
+        let table = entries
+            .split(';')
+            .map(|entry| match entry.trim().parse::<usize>() {
+                Ok(n) if n < table_size => Ok(n as u8),
+                _ => Err(()),
+            })
+            .collect::<Result<Vec<u8>, ()>>()?;
+        if table.len() != table_size {
+            return Err(());
+        }
+        let ret = Self {
+            table,
+            _phantom: PhantomData,
+        };
+        if !keeps_background_uniform(&ret) {
+            return Err(());
+        }
+        Ok(ret)
+    }
+}
+
+impl<D: Dim> fmt::Display for MargolusRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries: Vec<String> = self.table.iter().map(u8::to_string).collect();
+        write!(f, "MS,D{}", entries.join(";"))
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for MargolusRule<D> {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn as_block_rule(&self) -> Option<&dyn BlockRule<u8, D>> {
+        Some(self)
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        // Block rules are never simulated one cell at a time.
+        napkin[&NdVec::origin()]
+    }
+}
+
+impl<D: Dim> BlockRule<u8, D> for MargolusRule<D> {
+    fn transition_block(&self, block: &mut [u8], _odd_generation: bool) {
+        let old_number: usize = (0..block.len())
+            .filter(|&idx| block[idx] != 0)
+            .map(Self::cell_bit)
+            .sum();
+        let new_number = self.table[old_number] as usize;
+        for (idx, cell) in block.iter_mut().enumerate() {
+            *cell = (new_number & Self::cell_bit(idx) != 0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_margolus_parse() {
+        let s = "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15";
+        let rule = MargolusRule::<Dim2D>::try_from(s).unwrap();
+        assert_eq!(s, rule.to_string());
+        assert!(MargolusRule::<Dim1D>::try_from(s).is_err());
+        assert!(MargolusRule::<Dim3D>::try_from(s).is_err());
+        assert!(MargolusRule::<Dim1D>::try_from("MS,D0;2;1;3").is_ok());
+        assert!(MargolusRule::<Dim1D>::try_from("MS,D0;2;1;4").is_err());
+        assert!(MargolusRule::<Dim1D>::try_from("MS,D0;2;1").is_err());
+        assert!(MargolusRule::<Dim1D>::try_from("B3/S23").is_err());
+        // Empty blocks can't become partly full.
+        assert!(MargolusRule::<Dim1D>::try_from("MS,D1;2;1;3").is_err());
+        assert!(MargolusRule::<Dim1D>::try_from("MS,D3;2;1;0").is_ok());
+        // If empty blocks become full, then full blocks can't become partly
+        // full either.
+        let s = "MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;7";
+        assert!(MargolusRule::<Dim2D>::try_from(s).is_err());
+        assert!(crate::automaton::rule::from_str::<Dim2D>(s).is_err());
+        // Otherwise the background is never full, so it doesn't matter.
+        let s = "MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;7";
+        assert!(MargolusRule::<Dim2D>::try_from(s).is_ok());
+
+        // In the billiard ball model, a lone ball moves diagonally across the
+        // block, so a ball in the upper left (bit 1) moves to the lower right
+        // (bit 8).
+        let mut block = [0, 0, 1, 0];
+        rule.transition_block(&mut block, false);
+        assert_eq!([0, 1, 0, 0], block);
+    }
+
+    #[test]
+    fn test_block_index() {
+        for idx in 0..8 {
+            assert_eq!(idx, block_index(&block_offset::<Dim3D>(idx)));
+        }
+        assert_eq!(2, block_index::<Dim2D>(&NdVec([0, 1])));
+        assert_eq!(NdVec([1, 0, 1]), block_offset::<Dim3D>(5));
+    }
+}
@@ -4,16 +4,66 @@
 use crate::automaton::space::*;
 use core::fmt;
 
+mod counts;
+mod generations;
+mod isotropic;
+mod larger_than_life;
+mod lut;
+mod margolus;
+mod outer_totalistic;
+mod registry;
+mod rule_file;
+mod script;
+mod table;
 mod totalistic;
+mod tree;
+mod turmite;
+mod wireworld;
+mod wolfram;
 
+pub use generations::*;
+pub use isotropic::*;
+pub use larger_than_life::*;
+pub use lut::*;
+pub use margolus::*;
+pub use outer_totalistic::*;
+pub use registry::*;
+pub use rule_file::*;
+pub use script::*;
+pub use table::*;
 pub use totalistic::*;
+pub use tree::*;
+pub use turmite::*;
+pub use wireworld::*;
+pub use wolfram::*;
 
 /// A cellular automaton rule.
-pub trait Rule<C: CellType, D: Dim>: fmt::Debug {
+///
+/// The Display implementation of a rule should produce its rule string (e.g.
+/// `B3/S23`), which is written to the header of saved patterns.
+pub trait Rule<C: CellType, D: Dim>: fmt::Debug + fmt::Display + Send + Sync {
     /// Returns the maximum distance away that a cell may need to see in order
     /// to compute its next state.
     fn radius(&self) -> usize;
 
+    /// Returns the number of cell states used by this rule.
+    fn num_states(&self) -> usize {
+        2
+    }
+
+    /// Returns the RGB color to draw each cell state with, starting at state
+    /// 0, or None to shade live and decaying states automatically.
+    fn colors(&self) -> Option<&'static [[u8; 3]]> {
+        None
+    }
+
+    /// Returns this rule as a block rule if it is one, in which case the
+    /// simulation updates whole blocks of cells using
+    /// BlockRule::transition_block() instead of calling transition().
+    fn as_block_rule(&self) -> Option<&dyn BlockRule<C, D>> {
+        None
+    }
+
     /// Computes the next state for the cell at the center of the napkin.
     fn transition(&self, napkin: &NdArraySlice<C, D>) -> C;
 }
@@ -21,6 +71,11 @@ pub trait Rule<C: CellType, D: Dim>: fmt::Debug {
 /// A basic rule that never changes any cell states.
 #[derive(Debug)]
 pub struct DummyRule;
+impl fmt::Display for DummyRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dummy")
+    }
+}
 impl<C: CellType, D: Dim> Rule<C, D> for DummyRule {
     fn radius(&self) -> usize {
         0
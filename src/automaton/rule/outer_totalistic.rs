@@ -0,0 +1,273 @@
+use super::counts::{parse_neighbor_counts, write_neighbor_counts};
+use super::Rule;
+use crate::automaton::space::*;
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The largest number of cells allowed in the hypercube containing a
+/// neighborhood, which is the size of the range-500 neighborhood that Golly
+/// allows for 2D rules.
+const MAX_NEIGHBORHOOD_CELLS: usize = 1001 * 1001;
+
+/// Returns the largest neighborhood radius allowed in rule strings for the
+/// given number of dimensions, which keeps the number of cells in the
+/// neighborhood below `MAX_NEIGHBORHOOD_CELLS`.
+pub fn max_neighborhood_radius<D: Dim>() -> usize {
+    let fits = |radius: usize| {
+        (2 * radius + 1)
+            .checked_pow(D::NDIM as u32)
+            .is_some_and(|cells| cells <= MAX_NEIGHBORHOOD_CELLS)
+    };
+    // Start from the floating-point estimate and correct any rounding error.
+    let mut radius = ((MAX_NEIGHBORHOOD_CELLS as f64).powf(1.0 / D::NDIM as f64) as usize) / 2;
+    while !fits(radius) {
+        radius -= 1;
+    }
+    while fits(radius + 1) {
+        radius += 1;
+    }
+    radius
+}
+
+/// A neighborhood shape that can be used by an N-dimensional outer-totalistic
+/// rule, along with the letter used for it in rule strings (the same letters
+/// as Golly's HROT rules).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NdNeighborhood {
+    /// Every cell within the hypercube of the given radius (`M`).
+    Moore,
+    /// Every cell within the given Manhattan (L1) distance (`N`).
+    VonNeumann,
+    /// Every cell within the given distance along a single axis (`+`).
+    Cross,
+    /// Every cell within the hypercube of the given radius whose coordinates
+    /// have an odd sum (`B`).
+    Checkerboard,
+}
+impl NdNeighborhood {
+    /// Returns true if the given offset from the center cell is within the
+    /// neighborhood of the given radius. The center cell itself is never
+    /// included.
+    pub fn contains<D: Dim>(self, offset: &IVec<D>, radius: usize) -> bool {
+        let radius = radius as isize;
+        let abs = |ax: &Axis| offset[*ax].abs();
+        if *offset == NdVec::origin() || D::axes().iter().any(|ax| abs(ax) > radius) {
+            return false;
+        }
+        match self {
+            Self::Moore => true,
+            Self::VonNeumann => D::axes().iter().map(abs).sum::<isize>() <= radius,
+            Self::Cross => D::axes().iter().filter(|ax| abs(ax) != 0).count() == 1,
+            Self::Checkerboard => D::axes().iter().map(abs).sum::<isize>() % 2 == 1,
+        }
+    }
+    /// Returns the offsets of all the cells in the neighborhood of the given
+    /// radius.
+    pub fn offsets<D: Dim>(self, radius: usize) -> Vec<IVec<D>> {
+        IRect::<D>::centered(NdVec::origin(), radius as isize)
+            .iter()
+            .filter(|offset| self.contains(offset, radius))
+            .collect()
+    }
+    /// Returns the letter used for this neighborhood in rule strings.
+    fn letter(self) -> char {
+        match self {
+            Self::Moore => 'M',
+            Self::VonNeumann => 'N',
+            Self::Cross => '+',
+            Self::Checkerboard => 'B',
+        }
+    }
+    /// Returns the neighborhood with the given letter, or None if the letter
+    /// is not recognized.
+    fn from_letter(letter: &str) -> Option<Self> {
+        match letter {
+            "M" | "m" => Some(Self::Moore),
+            "N" | "n" => Some(Self::VonNeumann),
+            "+" => Some(Self::Cross),
+            "B" | "b" => Some(Self::Checkerboard),
+            _ => None,
+        }
+    }
+}
+
+/// A 2-state outer-totalistic rule of any dimensionality and radius (e.g. the
+/// 3D rule `B5/S45`).
+#[derive(Clone, PartialEq, Eq)]
+pub struct OuterTotalistic<D: Dim> {
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+    neighborhood: NdNeighborhood,
+    radius: usize,
+    neighbor_offsets: Vec<IVec<D>>,
+}
+impl<D: Dim> fmt::Debug for OuterTotalistic<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OuterTotalistic<Dim{}D>({})", D::NDIM, self)
+    }
+}
+
+impl<D: Dim> OuterTotalistic<D> {
+    /// Constructs a new outer-totalistic rule with the given neighborhood.
+    /// `birth` and `survival` list the neighbor counts for which a cell is born
+    /// or survives.
+    pub fn new(
+        birth: &[usize],
+        survival: &[usize],
+        neighborhood: NdNeighborhood,
+        radius: usize,
+    ) -> Self {
+        let neighbor_offsets = neighborhood.offsets(radius);
+        let conditions = |counts: &[usize]| {
+            let mut ret = vec![false; neighbor_offsets.len() + 1];
+            for &n in counts {
+                if let Some(condition) = ret.get_mut(n) {
+                    *condition = true;
+                }
+            }
+            ret
+        };
+        Self {
+            birth: conditions(birth),
+            survival: conditions(survival),
+            neighborhood,
+            radius,
+            neighbor_offsets,
+        }
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for OuterTotalistic<D> {
+    type Error = ();
+    /// Parses a rule string in B/S notation (e.g. `B5/S45`), optionally
+    /// followed by a neighborhood letter and radius (e.g. `B3/S23/N2`). The
+    /// default neighborhood is the range-1 Moore neighborhood.
+    fn try_from(s: &str) -> Result<Self, ()> {
+        let regex =
+            Regex::new(r"^[Bb](?P<b>[\d,\-]*)/?[Ss](?P<s>[\d,\-]*)(/(?P<n>[A-Za-z+])(?P<r>\d+))?$")
+                .unwrap();
+        let captures = regex.captures(s).ok_or(())?;
+        let (neighborhood, radius) = match (captures.name("n"), captures.name("r")) {
+            (Some(n), Some(r)) => (
+                NdNeighborhood::from_letter(n.as_str()).ok_or(())?,
+                r.as_str().parse().map_err(|_| ())?,
+            ),
+            _ => (NdNeighborhood::Moore, 1),
+        };
+        if !(1..=max_neighborhood_radius::<D>()).contains(&radius) {
+            return Err(());
+        }
+        let neighbor_offsets = neighborhood.offsets(radius);
+        let max_neighbors = neighbor_offsets.len();
+        Ok(Self {
+            birth: parse_neighbor_counts(&captures["b"], max_neighbors)?,
+            survival: parse_neighbor_counts(&captures["s"], max_neighbors)?,
+            neighborhood,
+            radius,
+            neighbor_offsets,
+        })
+    }
+}
+
+impl<D: Dim> fmt::Display for OuterTotalistic<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        write_neighbor_counts(f, &self.birth)?;
+        write!(f, "/S")?;
+        write_neighbor_counts(f, &self.survival)?;
+        if (self.neighborhood, self.radius) != (NdNeighborhood::Moore, 1) {
+            write!(f, "/{}{}", self.neighborhood.letter(), self.radius)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for OuterTotalistic<D> {
+    fn radius(&self) -> usize {
+        self.radius
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        let live_neighbors = self
+            .neighbor_offsets
+            .iter()
+            .filter(|offset| napkin[offset] != 0)
+            .count();
+        if napkin[&NdVec::origin()] != 0 {
+            self.survival[live_neighbors] as u8
+        } else {
+            self.birth[live_neighbors] as u8
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nd_neighborhood_sizes() {
+        use NdNeighborhood::*;
+        for &(neighborhood, radius, size_2d, size_3d) in &[
+            (Moore, 1, 8, 26),
+            (Moore, 2, 24, 124),
+            (VonNeumann, 1, 4, 6),
+            (VonNeumann, 2, 12, 24),
+            (Cross, 1, 4, 6),
+            (Cross, 3, 12, 18),
+            (Checkerboard, 1, 4, 14),
+            (Checkerboard, 2, 12, 62),
+        ] {
+            assert_eq!(size_2d, neighborhood.offsets::<Dim2D>(radius).len());
+            assert_eq!(size_3d, neighborhood.offsets::<Dim3D>(radius).len());
+        }
+        assert_eq!(80, Moore.offsets::<Dim4D>(1).len());
+    }
+
+    #[test]
+    fn test_outer_totalistic_parse() {
+        for &(input, canonical) in &[
+            ("B5/S45", "B5/S45"),
+            ("b5s45", "B5/S45"),
+            ("B5/S45/M1", "B5/S45"),
+            ("B4-6/S5-7,9/N2", "B456/S5679/N2"),
+            ("B10-12/S3/M2", "B10-12/S3/M2"),
+            ("B2/S/+3", "B2/S/+3"),
+            ("B1/S1/b2", "B1/S1/B2"),
+        ] {
+            let rule = OuterTotalistic::<Dim3D>::try_from(input).unwrap();
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule, OuterTotalistic::<Dim3D>::try_from(canonical).unwrap());
+        }
+        assert_eq!(
+            OuterTotalistic::<Dim3D>::new(&[5], &[4, 5], NdNeighborhood::Moore, 1),
+            OuterTotalistic::<Dim3D>::try_from("B5/S45").unwrap()
+        );
+
+        // 26 neighbors in 3D, but only 8 in 2D.
+        assert!(OuterTotalistic::<Dim3D>::try_from("B26-26/S").is_ok());
+        assert!(OuterTotalistic::<Dim2D>::try_from("B9/S").is_err());
+        for &s in &["B3/S23/N0", "B3/S23/X1", "B3/S23/N", "B3/S23/C3", "B3/S23V"] {
+            assert!(OuterTotalistic::<Dim2D>::try_from(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_outer_totalistic_max_radius() {
+        assert_eq!(501_000, max_neighborhood_radius::<Dim1D>());
+        assert_eq!(500, max_neighborhood_radius::<Dim2D>());
+        assert_eq!(49, max_neighborhood_radius::<Dim3D>());
+        assert_eq!(15, max_neighborhood_radius::<Dim4D>());
+
+        assert!(OuterTotalistic::<Dim2D>::try_from("B3/S23/+500").is_ok());
+        assert!(OuterTotalistic::<Dim3D>::try_from("B3/S23/+49").is_ok());
+        for &s in &[
+            "B3/S23/M501",
+            "B3/S23/M1000000",
+            "B3/S23/M99999999999999999999999",
+        ] {
+            assert!(OuterTotalistic::<Dim2D>::try_from(s).is_err(), "{:?}", s);
+        }
+        assert!(OuterTotalistic::<Dim3D>::try_from("B3/S23/M50").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Construction of rules from rule strings.
+
+use std::convert::TryFrom;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::*;
+
+/// Names of well-known rules, along with the rule strings they stand for.
+const ALIASES: &[(&str, &str)] = &[
+    ("Life", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Seeds", "B2/S"),
+    ("DayAndNight", "B3678/S34678"),
+    ("LangtonsAnt", "{{{1,2,0},{0,8,0}}}"),
+    ("BBM", "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15"),
+    ("Critters", "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0"),
+    ("Tron", "MS,D15;1;2;3;4;5;6;7;8;9;10;11;12;13;14;0"),
+];
+
+/// The directory that is searched for rule scripts (e.g.
+/// `rules/BriansBrain.ndrule` for the rule `BriansBrain`) and Golly `.rule`
+/// files (e.g. `rules/Langtons-Loops.rule` for the rule `Langtons-Loops`).
+pub const RULE_DIR: &str = "rules";
+
+/// Constructs a rule from a rule string (e.g. `B3/S23`), the name of a
+/// well-known rule (e.g. `Life`), or the name of a rule script or rule file in
+/// RULE_DIR.
+///
+/// Rules with few enough states and a small enough radius are compiled into a
+/// lookup table (see LutRule).
+///
+/// Returns an error if the rule string is not recognized, or if it describes a
+/// rule that cannot be simulated in D dimensions.
+pub fn from_str<D: Dim>(s: &str) -> Result<Arc<dyn Rule<u8, D>>, String> {
+    from_str_uncompiled(s).map(LutRule::compile)
+}
+
+/// Constructs a rule from a rule string, the same as from_str(), but without
+/// compiling it into a lookup table.
+pub fn from_str_uncompiled<D: Dim>(s: &str) -> Result<Arc<dyn Rule<u8, D>>, String> {
+    let s = s.trim();
+    let rule_str = ALIASES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        .map(|&(_, rule_str)| rule_str)
+        .unwrap_or(s);
+    let rule = match D::NDIM {
+        1 => from_str_1d(rule_str).map(transmute_rule),
+        2 => from_str_2d(rule_str).map(transmute_rule),
+        _ => None,
+    };
+    if let Some(rule) = rule.or_else(|| from_str_nd(rule_str)) {
+        return Ok(rule);
+    }
+    // Look for a rule script or Golly rule file with the same name.
+    let is_valid_name = rule_str
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+    if is_valid_name {
+        let path = Path::new(RULE_DIR).join(format!("{}.ndrule", rule_str));
+        if path.is_file() {
+            return load_script_file(&path);
+        }
+        let path = Path::new(RULE_DIR).join(format!("{}.rule", rule_str));
+        if D::NDIM == 2 && path.is_file() {
+            return load_rule_file(&path).map(transmute_rule);
+        }
+    }
+    Err(format!("Unknown rule: {:?}", s))
+}
+
+/// Constructs a rule that can be simulated in any number of dimensions from a
+/// rule string, or returns None if the rule string is not recognized.
+fn from_str_nd<D: Dim>(s: &str) -> Option<Arc<dyn Rule<u8, D>>> {
+    if let Ok(rule) = OuterTotalistic::<D>::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = Generations::<D>::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = LargerThanLife::<D>::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = Wireworld::<D>::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = MargolusRule::<D>::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = Turmite::<D>::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    None
+}
+
+/// Constructs a 1D rule from a rule string, or returns None if the rule string
+/// is not recognized.
+fn from_str_1d(s: &str) -> Option<Arc<dyn Rule<u8, Dim1D>>> {
+    if let Ok(rule) = Elementary1D::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = Totalistic1D::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    None
+}
+
+/// Constructs a 2D rule from a rule string, or returns None if the rule string
+/// is not recognized.
+fn from_str_2d(s: &str) -> Option<Arc<dyn Rule<u8, Dim2D>>> {
+    if let Ok(rule) = Totalistic2D::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    if let Ok(rule) = Isotropic2D::try_from(s) {
+        return Some(Arc::new(rule));
+    }
+    None
+}
+
+/// Converts an `Arc<dyn Rule<u8, D1>>` into an `Arc<dyn Rule<u8, D2>>`.
+///
+/// This function is not marked as unsafe because it performs "runtime" checking
+/// that the initial and final dimensionalities are the same, even though that
+/// "runtime" checking is almost certianly compile-time-optimized away.
+///
+/// If the dimensionalities do not match, panics.
+fn transmute_rule<D1: Dim, D2: Dim>(rule: Arc<dyn Rule<u8, D1>>) -> Arc<dyn Rule<u8, D2>> {
+    if D1::NDIM == D2::NDIM {
+        unsafe { std::mem::transmute::<Arc<dyn Rule<u8, D1>>, Arc<dyn Rule<u8, D2>>>(rule) }
+    } else {
+        panic!(
+            "Cannot convert Rule<_, Dim{}D> into Rule<_, Dim{}D>",
+            D1::NDIM,
+            D2::NDIM
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_from_str() {
+        let rule = from_str::<Dim2D>("B3/S23").unwrap();
+        assert_eq!("B3/S23", rule.to_string());
+        assert_eq!(1, rule.radius());
+
+        // Aliases are case-insensitive.
+        assert_eq!("B3/S23", from_str::<Dim2D>("life").unwrap().to_string());
+        assert_eq!(
+            "B36/S23",
+            from_str::<Dim2D>("HighLife").unwrap().to_string()
+        );
+
+        assert_eq!(
+            "B2n3/S23-q",
+            from_str::<Dim2D>("B2n3/S23-q").unwrap().to_string()
+        );
+
+        assert!(from_str::<Dim2D>("NotARule").is_err());
+        assert!(from_str::<Dim2D>("B9/S23").is_err());
+        assert!(from_str::<Dim3D>("B2n3/S23-q").is_err());
+
+        let rule = from_str::<Dim3D>("B5/S45").unwrap();
+        assert_eq!("B5/S45", rule.to_string());
+        let rule = from_str::<Dim2D>("B3/S23/N2").unwrap();
+        assert_eq!(2, rule.radius());
+
+        let rule = from_str::<Dim3D>("B5/S45/C5").unwrap();
+        assert_eq!("B5/S45/C5", rule.to_string());
+        assert_eq!(5, rule.num_states());
+
+        let rule = from_str::<Dim2D>("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!("R5,C0,M1,S34..58,B34..45,NM", rule.to_string());
+        assert_eq!(5, rule.radius());
+
+        assert_eq!("W110", from_str::<Dim1D>("W110").unwrap().to_string());
+        let rule = from_str::<Dim1D>("T777,K3").unwrap();
+        assert_eq!("T777,K3,R1", rule.to_string());
+        assert_eq!(3, rule.num_states());
+        assert!(from_str::<Dim2D>("W110").is_err());
+
+        let rule = from_str::<Dim3D>("WireWorld").unwrap();
+        assert_eq!(4, rule.num_states());
+        assert!(rule.colors().is_some());
+
+        let rule = from_str::<Dim2D>("LangtonsAnt").unwrap();
+        assert_eq!("{{{1,2,0},{0,8,0}}}", rule.to_string());
+        assert_eq!(10, rule.num_states());
+        let rule = from_str::<Dim3D>("LangtonsAnt").unwrap();
+        assert_eq!(50, rule.num_states());
+
+        let rule = from_str::<Dim2D>("Critters").unwrap();
+        assert_eq!(
+            "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0",
+            rule.to_string()
+        );
+        assert!(rule.as_block_rule().is_some());
+        assert!(from_str::<Dim3D>("Critters").is_err());
+
+        // Rule scripts in RULE_DIR work in any number of dimensions.
+        let rule = from_str::<Dim3D>("BriansBrain").unwrap();
+        assert_eq!("BriansBrain", rule.to_string());
+        assert_eq!(3, rule.num_states());
+    }
+}
@@ -0,0 +1,110 @@
+//! Loading of rules from Golly `.rule` files, described here:
+//! http://golly.sourceforge.net/Help/formats.html#rule
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::*;
+
+/// Parses the contents of a Golly `.rule` file, using its `@TABLE` section if
+/// it has one and its `@TREE` section otherwise. Other sections (such as
+/// `@COLORS` and `@ICONS`) are ignored.
+pub fn parse_rule_file(s: &str) -> Result<Arc<dyn Rule<u8, Dim2D>>, String> {
+    let mut name = None;
+    let mut table: Option<String> = None;
+    let mut tree: Option<String> = None;
+    // Which section the current line is in: `Some(true)` for @TABLE,
+    // `Some(false)` for @TREE, and None for any other section.
+    let mut in_table = None;
+    for line in s.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('@') {
+            let mut words = trimmed.split_whitespace();
+            in_table = match words.next() {
+                Some("@RULE") => {
+                    name = Some(
+                        words
+                            .next()
+                            .ok_or("Missing rule name after @RULE")?
+                            .to_owned(),
+                    );
+                    None
+                }
+                Some("@TABLE") => {
+                    table = Some(String::new());
+                    Some(true)
+                }
+                Some("@TREE") => {
+                    tree = Some(String::new());
+                    Some(false)
+                }
+                _ => None,
+            };
+            continue;
+        }
+        let section = match in_table {
+            Some(true) => &mut table,
+            Some(false) => &mut tree,
+            None => continue,
+        };
+        if let Some(section) = section {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    let name = name.ok_or("Missing @RULE section")?;
+    if let Some(table) = table {
+        Ok(Arc::new(RuleTable::parse(&name, &table)?))
+    } else if let Some(tree) = tree {
+        Ok(Arc::new(RuleTree::parse(&name, &tree)?))
+    } else {
+        Err("Missing @TABLE or @TREE section".to_owned())
+    }
+}
+
+/// Loads a Golly `.rule` file.
+pub fn load_rule_file(path: &Path) -> Result<Arc<dyn Rule<u8, Dim2D>>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    parse_rule_file(&contents).map_err(|e| format!("Invalid rule file {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule_file() {
+        let rule_file = "
+@RULE Parity
+
+A comment before the table.
+
+@TABLE
+n_states:2
+neighborhood:vonNeumann
+symmetries:permute
+var a={0,1}
+var b={0,1}
+0,1,0,0,0,1
+1,1,0,0,0,0
+
+@COLORS
+1 255 0 0
+";
+        let rule = parse_rule_file(rule_file).unwrap();
+        assert_eq!("Parity", rule.to_string());
+        assert_eq!(2, rule.num_states());
+
+        assert!(parse_rule_file("@TABLE\nn_states:2")
+            .unwrap_err()
+            .contains("@RULE"));
+        assert!(parse_rule_file("@RULE Test\n@COLORS\n")
+            .unwrap_err()
+            .contains("@TABLE or @TREE"));
+        assert!(parse_rule_file("@RULE Test\n@TREE\nnum_states=2\n")
+            .unwrap_err()
+            .contains("num_neighbors"));
+    }
+}
@@ -0,0 +1,643 @@
+//! A small language for describing rules with arbitrary transition functions,
+//! so that new rules can be loaded at runtime without recompiling NDCell.
+//!
+//! A rule script consists of some optional directives followed by an
+//! expression that computes the next state of a cell. For example, here is
+//! Brian's Brain:
+//!
+//! ```text
+//! @states dead, alive, dying
+//! @neighborhood moore(1)
+//!
+//! if this == alive {
+//!     dying
+//! } else if this == dead && count(alive) == 2 {
+//!     alive
+//! } else {
+//!     dead
+//! }
+//! ```
+//!
+//! The directives are:
+//!
+//! - `@states` followed by the number of states (e.g. `@states 3`) or a
+//!   comma-separated list of names for them, starting at state 0. Defaults to
+//!   2 states.
+//! - `@neighborhood` followed by `moore`, `vonneumann`, `cross` or
+//!   `checkerboard`, and optionally a radius in parentheses. This is the
+//!   neighborhood used by `count()` and `sum()`, and defaults to `moore(1)`.
+//!
+//! Every value is an integer. Expressions may use:
+//!
+//! - integer literals and state names;
+//! - `this`, the state of the cell itself;
+//! - `let name = value;` before an expression, at the beginning of the rule or
+//!   of any block;
+//! - `if condition { ... } else { ... }`, where any nonzero condition counts
+//!   as true (`else if` may be used to chain conditions);
+//! - the arithmetic operators `+`, `-`, `*`, `/` and `%` (division by zero
+//!   produces 0);
+//! - the comparison operators `==`, `!=`, `<`, `<=`, `>` and `>=`, and the
+//!   logical operators `&&`, `||` and `!`, which all produce 0 or 1;
+//! - `count(s1, s2, ...)`, the number of cells in the neighborhood whose state
+//!   is any of the given values;
+//! - `sum()`, the sum of the states of the cells in the neighborhood;
+//! - `cell(dx, dy, ...)`, the state of the cell at a constant offset, with one
+//!   coordinate per dimension (Y increases upwards);
+//! - `min(a, b, ...)`, `max(a, b, ...)` and `abs(a)`.
+//!
+//! The result is wrapped into the range of valid states, so that (for example)
+//! `this + 1` cycles through every state. Comments start with `#`.
+
+use pest::Parser;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{NdNeighborhood, Rule};
+use crate::automaton::space::*;
+
+/// The largest neighborhood radius allowed in a rule script.
+const MAX_RADIUS: usize = 100;
+
+// The grammar is in its own module so that the `Rule` enum generated by pest
+// does not conflict with the `Rule` trait.
+mod grammar {
+    #[derive(Parser)]
+    #[grammar = "automaton/rule/script.pest"]
+    pub struct ScriptParser;
+}
+use grammar::{Rule as Token, ScriptParser};
+
+type TokenPair<'a> = pest::iterators::Pair<'a, Token>;
+
+/// A unary operator in a rule script.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum UnaryOp {
+    Neg,
+    Not,
+}
+
+/// A binary operator in a rule script.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+impl BinaryOp {
+    fn from_symbol(symbol: &str) -> Self {
+        match symbol {
+            "+" => Self::Add,
+            "-" => Self::Sub,
+            "*" => Self::Mul,
+            "/" => Self::Div,
+            "%" => Self::Rem,
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            "&&" => Self::And,
+            "||" => Self::Or,
+            _ => unreachable!("Unknown operator {:?}", symbol),
+        }
+    }
+    fn apply(self, a: i64, b: i64) -> i64 {
+        match self {
+            Self::Add => a.wrapping_add(b),
+            Self::Sub => a.wrapping_sub(b),
+            Self::Mul => a.wrapping_mul(b),
+            Self::Div => a.checked_div_euclid(b).unwrap_or(0),
+            Self::Rem => a.checked_rem_euclid(b).unwrap_or(0),
+            Self::Eq => (a == b) as i64,
+            Self::Ne => (a != b) as i64,
+            Self::Lt => (a < b) as i64,
+            Self::Le => (a <= b) as i64,
+            Self::Gt => (a > b) as i64,
+            Self::Ge => (a >= b) as i64,
+            Self::And => (a != 0 && b != 0) as i64,
+            Self::Or => (a != 0 || b != 0) as i64,
+        }
+    }
+}
+
+/// A compiled expression in a rule script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    /// An integer constant (including state names).
+    Const(i64),
+    /// The state of the center cell.
+    This,
+    /// The value of the variable in the given slot.
+    Var(usize),
+    /// The state of the cell at the offset with the given index in
+    /// `ScriptRule::cell_offsets`.
+    Cell(usize),
+    /// The number of cells in the neighborhood with any of the given states.
+    Count(Vec<Expr>),
+    /// The sum of the states of the cells in the neighborhood.
+    Sum,
+    Min(Vec<Expr>),
+    Max(Vec<Expr>),
+    Abs(Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A condition, followed by the value if it is nonzero and the value if it
+    /// is zero.
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Stores a value in the given slot, then evaluates an expression that may
+    /// use it.
+    Let(usize, Box<Expr>, Box<Expr>),
+}
+
+/// A rule described by a rule script (see the module documentation for the
+/// syntax), which can be simulated in any number of dimensions.
+#[derive(Clone)]
+pub struct ScriptRule<D: Dim> {
+    name: String,
+    num_states: usize,
+    radius: usize,
+    /// The offsets of the cells in the neighborhood, not including the center
+    /// cell.
+    neighbor_offsets: Vec<IVec<D>>,
+    /// The offsets used by `cell()`.
+    cell_offsets: Vec<IVec<D>>,
+    /// The number of variable slots needed to evaluate the expression.
+    num_vars: usize,
+    expr: Expr,
+}
+impl<D: Dim> fmt::Debug for ScriptRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ScriptRule<Dim{}D>({})", D::NDIM, self.name)
+    }
+}
+impl<D: Dim> fmt::Display for ScriptRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl<D: Dim> ScriptRule<D> {
+    /// Parses a rule script, giving the resulting rule the given name.
+    pub fn parse(name: &str, source: &str) -> Result<Self, String> {
+        let main = ScriptParser::parse(Token::main, source)
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or("No main token pair")?;
+
+        let mut compiler = Compiler::<D>::default();
+        let mut num_states = 2;
+        let mut neighborhood = NdNeighborhood::Moore;
+        let mut neighborhood_radius = 1;
+        let mut expr = None;
+        for pair in main.into_inner() {
+            match pair.as_rule() {
+                Token::states => {
+                    let inner: Vec<TokenPair> = pair.into_inner().collect();
+                    if inner[0].as_rule() == Token::int {
+                        num_states = parse_int(&inner[0])?;
+                    } else {
+                        num_states = inner.len();
+                        for ident in &inner {
+                            let state_name = ident.as_str().to_owned();
+                            if compiler.state_names.contains(&state_name) {
+                                return Err(format!("Duplicate state name {:?}", state_name));
+                            }
+                            compiler.state_names.push(state_name);
+                        }
+                    }
+                    if !(2..=256).contains(&num_states) {
+                        return Err("Number of states must be between 2 and 256".to_owned());
+                    }
+                }
+                Token::neighborhood => {
+                    let mut inner = pair.into_inner();
+                    let kind = inner.next().unwrap().as_str().to_ascii_lowercase();
+                    neighborhood = match kind.as_str() {
+                        "moore" => NdNeighborhood::Moore,
+                        "vonneumann" => NdNeighborhood::VonNeumann,
+                        "cross" => NdNeighborhood::Cross,
+                        _ => NdNeighborhood::Checkerboard,
+                    };
+                    if let Some(radius) = inner.next() {
+                        neighborhood_radius = parse_int(&radius)?;
+                    }
+                    if !(1..=MAX_RADIUS).contains(&neighborhood_radius) {
+                        return Err(format!(
+                            "Neighborhood radius must be between 1 and {}",
+                            MAX_RADIUS
+                        ));
+                    }
+                }
+                Token::body => expr = Some(compiler.compile_body(pair)?),
+                Token::EOI => (),
+                _ => unreachable!("Unexpected token {:?}", pair.as_rule()),
+            }
+        }
+        let expr = expr.ok_or("Missing transition function")?;
+
+        let mut radius = compiler
+            .cell_offsets
+            .iter()
+            .flat_map(|offset| D::axes().iter().map(move |&ax| offset[ax].abs()))
+            .max()
+            .unwrap_or(0) as usize;
+        let neighbor_offsets = if compiler.uses_neighborhood {
+            radius = std::cmp::max(radius, neighborhood_radius);
+            neighborhood.offsets(neighborhood_radius)
+        } else {
+            vec![]
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            num_states,
+            radius,
+            neighbor_offsets,
+            cell_offsets: compiler.cell_offsets,
+            num_vars: compiler.max_vars,
+            expr,
+        })
+    }
+
+    /// Evaluates an expression for the cell at the center of the napkin.
+    fn eval(&self, expr: &Expr, napkin: &NdArraySlice<u8, D>, vars: &mut [i64]) -> i64 {
+        let neighbors = || {
+            self.neighbor_offsets
+                .iter()
+                .map(move |offset| napkin[offset] as i64)
+        };
+        match expr {
+            Expr::Const(value) => *value,
+            Expr::This => napkin[&NdVec::origin()] as i64,
+            Expr::Var(slot) => vars[*slot],
+            Expr::Cell(idx) => napkin[&self.cell_offsets[*idx]] as i64,
+            Expr::Count(states) => {
+                let states: Vec<i64> = states
+                    .iter()
+                    .map(|state| self.eval(state, napkin, vars))
+                    .collect();
+                neighbors().filter(|state| states.contains(state)).count() as i64
+            }
+            Expr::Sum => neighbors().sum(),
+            Expr::Min(args) => args
+                .iter()
+                .map(|arg| self.eval(arg, napkin, vars))
+                .min()
+                .unwrap(),
+            Expr::Max(args) => args
+                .iter()
+                .map(|arg| self.eval(arg, napkin, vars))
+                .max()
+                .unwrap(),
+            Expr::Abs(arg) => self.eval(arg, napkin, vars).wrapping_abs(),
+            Expr::Unary(op, arg) => {
+                let value = self.eval(arg, napkin, vars);
+                match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Not => (value == 0) as i64,
+                }
+            }
+            // Logical operators short-circuit.
+            Expr::Binary(BinaryOp::And, a, b) => {
+                (self.eval(a, napkin, vars) != 0 && self.eval(b, napkin, vars) != 0) as i64
+            }
+            Expr::Binary(BinaryOp::Or, a, b) => {
+                (self.eval(a, napkin, vars) != 0 || self.eval(b, napkin, vars) != 0) as i64
+            }
+            Expr::Binary(op, a, b) => {
+                let a = self.eval(a, napkin, vars);
+                let b = self.eval(b, napkin, vars);
+                op.apply(a, b)
+            }
+            Expr::If(condition, if_true, if_false) => {
+                if self.eval(condition, napkin, vars) != 0 {
+                    self.eval(if_true, napkin, vars)
+                } else {
+                    self.eval(if_false, napkin, vars)
+                }
+            }
+            Expr::Let(slot, value, body) => {
+                vars[*slot] = self.eval(value, napkin, vars);
+                self.eval(body, napkin, vars)
+            }
+        }
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for ScriptRule<D> {
+    fn radius(&self) -> usize {
+        self.radius
+    }
+    fn num_states(&self) -> usize {
+        self.num_states
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        let mut vars = vec![0; self.num_vars];
+        let result = self.eval(&self.expr, napkin, &mut vars);
+        result.rem_euclid(self.num_states as i64) as u8
+    }
+}
+
+/// Converts a parsed rule script into an expression.
+struct Compiler<D: Dim> {
+    state_names: Vec<String>,
+    /// The names of the variables currently in scope; each variable is stored
+    /// in the slot with the same index.
+    vars: Vec<String>,
+    /// The largest number of variables that are ever in scope at once.
+    max_vars: usize,
+    cell_offsets: Vec<IVec<D>>,
+    /// Whether `count()` or `sum()` is used anywhere.
+    uses_neighborhood: bool,
+}
+impl<D: Dim> Default for Compiler<D> {
+    fn default() -> Self {
+        Self {
+            state_names: vec![],
+            vars: vec![],
+            max_vars: 0,
+            cell_offsets: vec![],
+            uses_neighborhood: false,
+        }
+    }
+}
+impl<D: Dim> Compiler<D> {
+    fn compile_body(&mut self, pair: TokenPair) -> Result<Expr, String> {
+        let mut bindings = vec![];
+        let mut ret = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Token::let_binding => {
+                    let mut inner = inner.into_inner();
+                    let name = inner.next().unwrap().as_str().to_owned();
+                    // The variable is not in scope in its own value.
+                    let value = self.compile_expr(inner.next().unwrap())?;
+                    bindings.push((self.vars.len(), value));
+                    self.vars.push(name);
+                    self.max_vars = std::cmp::max(self.max_vars, self.vars.len());
+                }
+                _ => ret = Some(self.compile_expr(inner)?),
+            }
+        }
+        self.vars.truncate(self.vars.len() - bindings.len());
+        Ok(bindings
+            .into_iter()
+            .rev()
+            .fold(ret.unwrap(), |body, (slot, value)| {
+                Expr::Let(slot, Box::new(value), Box::new(body))
+            }))
+    }
+
+    fn compile_expr(&mut self, pair: TokenPair) -> Result<Expr, String> {
+        match pair.as_rule() {
+            Token::expr | Token::and_expr | Token::cmp_expr | Token::sum | Token::product => {
+                // These all consist of operands separated by left-associative
+                // binary operators.
+                let mut inner = pair.into_inner();
+                let mut ret = self.compile_expr(inner.next().unwrap())?;
+                while let Some(op) = inner.next() {
+                    let rhs = self.compile_expr(inner.next().unwrap())?;
+                    ret = Expr::Binary(
+                        BinaryOp::from_symbol(op.as_str()),
+                        Box::new(ret),
+                        Box::new(rhs),
+                    );
+                }
+                Ok(ret)
+            }
+            Token::unary => {
+                let inner: Vec<TokenPair> = pair.into_inner().collect();
+                let (primary, ops) = inner.split_last().unwrap();
+                let mut ret = self.compile_expr(primary.clone())?;
+                for op in ops.iter().rev() {
+                    ret = match (op.as_str(), ret) {
+                        // Fold negative constants so that they can be used as
+                        // cell offsets.
+                        ("-", Expr::Const(value)) => Expr::Const(-value),
+                        ("-", ret) => Expr::Unary(UnaryOp::Neg, Box::new(ret)),
+                        (_, ret) => Expr::Unary(UnaryOp::Not, Box::new(ret)),
+                    };
+                }
+                Ok(ret)
+            }
+            Token::if_expr => {
+                let mut inner = pair.into_inner();
+                let mut next = || self.compile_expr(inner.next().unwrap());
+                let condition = next()?;
+                let if_true = next()?;
+                let if_false = next()?;
+                Ok(Expr::If(
+                    Box::new(condition),
+                    Box::new(if_true),
+                    Box::new(if_false),
+                ))
+            }
+            Token::call => self.compile_call(pair),
+            Token::body => self.compile_body(pair),
+            Token::int => Ok(Expr::Const(parse_int(&pair)?)),
+            Token::ident => {
+                let name = pair.as_str();
+                if let Some(slot) = self.vars.iter().rposition(|var| var == name) {
+                    Ok(Expr::Var(slot))
+                } else if let Some(state) = self.state_names.iter().position(|s| s == name) {
+                    Ok(Expr::Const(state as i64))
+                } else if name == "this" {
+                    Ok(Expr::This)
+                } else {
+                    Err(format!("Unknown name {:?}", name))
+                }
+            }
+            _ => unreachable!("Unexpected token {:?}", pair.as_rule()),
+        }
+    }
+
+    fn compile_call(&mut self, pair: TokenPair) -> Result<Expr, String> {
+        let mut inner = pair.into_inner();
+        let name = inner.next().unwrap().as_str();
+        let args = inner
+            .map(|arg| self.compile_expr(arg))
+            .collect::<Result<Vec<Expr>, String>>()?;
+        let expect_args = |valid: bool, expected: &str| {
+            if valid {
+                Ok(())
+            } else {
+                Err(format!("{}() takes {}", name, expected))
+            }
+        };
+        match name {
+            "count" => {
+                expect_args(!args.is_empty(), "at least 1 argument")?;
+                self.uses_neighborhood = true;
+                Ok(Expr::Count(args))
+            }
+            "sum" => {
+                expect_args(args.is_empty(), "no arguments")?;
+                self.uses_neighborhood = true;
+                Ok(Expr::Sum)
+            }
+            "cell" => {
+                expect_args(
+                    args.len() == D::NDIM,
+                    &format!("{} coordinates in {}D", D::NDIM, D::NDIM),
+                )?;
+                let mut offset = IVec::<D>::origin();
+                for (&ax, arg) in D::axes().iter().zip(&args) {
+                    offset[ax] = match arg {
+                        Expr::Const(value) if value.unsigned_abs() as usize <= MAX_RADIUS => {
+                            *value as isize
+                        }
+                        Expr::Const(_) => {
+                            return Err(format!(
+                                "cell() coordinates must be between -{} and {}",
+                                MAX_RADIUS, MAX_RADIUS
+                            ))
+                        }
+                        _ => return Err("cell() coordinates must be constants".to_owned()),
+                    };
+                }
+                let idx = match self.cell_offsets.iter().position(|o| *o == offset) {
+                    Some(idx) => idx,
+                    None => {
+                        self.cell_offsets.push(offset);
+                        self.cell_offsets.len() - 1
+                    }
+                };
+                Ok(Expr::Cell(idx))
+            }
+            "min" => {
+                expect_args(!args.is_empty(), "at least 1 argument")?;
+                Ok(Expr::Min(args))
+            }
+            "max" => {
+                expect_args(!args.is_empty(), "at least 1 argument")?;
+                Ok(Expr::Max(args))
+            }
+            "abs" => {
+                expect_args(args.len() == 1, "1 argument")?;
+                Ok(Expr::Abs(Box::new(args.into_iter().next().unwrap())))
+            }
+            _ => Err(format!("Unknown function {:?}", name)),
+        }
+    }
+}
+
+fn parse_int<T: std::str::FromStr>(pair: &TokenPair) -> Result<T, String> {
+    pair.as_str()
+        .parse()
+        .map_err(|_| format!("Number too large: {}", pair.as_str()))
+}
+
+/// Loads a rule script from a file, naming the rule after the file (e.g.
+/// `BriansBrain` for `rules/BriansBrain.ndrule`).
+pub fn load_script_file<D: Dim>(path: &Path) -> Result<Arc<dyn Rule<u8, D>>, String> {
+    let name = path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("Invalid file name: {}", path.display()))?;
+    let source = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    let rule = ScriptRule::parse(name, &source)
+        .map_err(|e| format!("Invalid rule script {}: {}", path.display(), e))?;
+    Ok(Arc::new(rule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the result of a rule script for a 1D napkin of radius 1.
+    fn eval_1d(source: &str, cells: [u8; 3]) -> u8 {
+        let rule = ScriptRule::<Dim1D>::parse("Test", source).unwrap();
+        let napkin = NdArray::from_flat_vec(NdVec([3]), cells.to_vec());
+        rule.transition(&napkin.offset_slice(NdVec([-1])))
+    }
+
+    #[test]
+    fn test_script_expressions() {
+        assert_eq!(7, eval_1d("@states 10 1 + 2 * 3", [0, 0, 0]));
+        assert_eq!(9, eval_1d("@states 10 (1 + 2) * 3", [0, 0, 0]));
+        assert_eq!(3, eval_1d("@states 10 10 - 4 - 3", [0, 0, 0]));
+        // Division rounds towards negative infinity, and dividing by zero
+        // produces zero.
+        assert_eq!(8, eval_1d("@states 10 -7 / 2 + 12", [0, 0, 0]));
+        assert_eq!(1, eval_1d("@states 10 -7 % 2", [0, 0, 0]));
+        assert_eq!(0, eval_1d("@states 10 5 / 0 + 5 % 0", [0, 0, 0]));
+        // The result wraps around.
+        assert_eq!(2, eval_1d("@states 3 this + 1", [0, 1, 0]));
+        assert_eq!(0, eval_1d("@states 3 this + 1", [0, 2, 0]));
+        assert_eq!(2, eval_1d("@states 3 -1", [0, 0, 0]));
+
+        assert_eq!(1, eval_1d("1 < 2 && !(2 < 1) || 0", [0, 0, 0]));
+        assert_eq!(
+            5,
+            eval_1d("@states 10 min(7, 5, 9) + max(-3, -4) + abs(-3)", [0, 0, 0])
+        );
+        assert_eq!(
+            1,
+            eval_1d("if 0 { 0 } else if 2 { 1 } else { 0 }", [0, 0, 0])
+        );
+
+        // Variables, including shadowing.
+        let source = "
+            @states 10
+            let x = 2;
+            let y = x * 3;
+            let z = { let x = 1; x + y };
+            x + z # 2 + (1 + 6)
+        ";
+        assert_eq!(9, eval_1d(source, [0, 0, 0]));
+
+        // Neighborhood functions.
+        let source = "@states a, b, c\n@neighborhood moore(1)\n10 * count(b, c) + sum()";
+        assert_eq!((10 * 2 + 3) % 3, eval_1d(source, [1, 2, 2]) as usize);
+        assert_eq!(2, eval_1d("@states 3 cell(-1) * 2 + cell(1)", [1, 0, 0]));
+        assert_eq!(1, eval_1d("@states 3 cell(-1) * 2 + cell(1)", [0, 2, 1]));
+    }
+
+    #[test]
+    fn test_script_radius() {
+        let radius = |source| ScriptRule::<Dim2D>::parse("Test", source).unwrap().radius();
+        assert_eq!(0, radius("this"));
+        assert_eq!(1, radius("count(1)"));
+        assert_eq!(3, radius("@neighborhood vonNeumann(3) sum()"));
+        assert_eq!(2, radius("cell(0, -2)"));
+        assert_eq!(4, radius("@neighborhood cross(2) sum() + cell(4, 1)"));
+    }
+
+    #[test]
+    fn test_script_errors() {
+        for (source, msg) in &[
+            ("", "expected"),
+            ("@states 1 0", "between 2 and 256"),
+            ("@states 300 0", "between 2 and 256"),
+            ("@states a, b, a a", "Duplicate state name"),
+            ("@neighborhood moore(0) 0", "radius"),
+            ("x", "Unknown name \"x\""),
+            ("let x = x; x", "Unknown name \"x\""),
+            ("{ let x = 1; x } + x", "Unknown name \"x\""),
+            ("foo(1)", "Unknown function \"foo\""),
+            ("count()", "count() takes at least 1 argument"),
+            ("cell(1)", "cell() takes 2 coordinates in 2D"),
+            ("cell(this, 1)", "constants"),
+            ("cell(1000, 1)", "between"),
+            ("if 1 { 1 }", "expected"),
+            ("let if = 1; 0", "expected"),
+        ] {
+            let err = ScriptRule::<Dim2D>::parse("Test", source).unwrap_err();
+            assert!(err.contains(msg), "{:?} does not contain {:?}", err, msg);
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use super::{Neighborhood2D, Rule};
+use crate::automaton::space::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+/// The offsets of the neighbors in a von Neumann rule table, in the order N,
+/// E, S, W. (Y increases upwards in NDCell, so north is +Y.)
+const VON_NEUMANN_OFFSETS: &[[isize; 2]] = &[[0, 1], [1, 0], [0, -1], [-1, 0]];
+/// The offsets of the neighbors in a Moore rule table, in the order N, NE, E,
+/// SE, S, SW, W, NW.
+const MOORE_OFFSETS: &[[isize; 2]] = &[
+    [0, 1],
+    [1, 1],
+    [1, 0],
+    [1, -1],
+    [0, -1],
+    [-1, -1],
+    [-1, 0],
+    [-1, 1],
+];
+
+/// The maximum number of inputs to a transition (the center cell followed by
+/// the neighbors).
+const MAX_INPUTS: usize = 1 + 8;
+
+/// A symmetry that can be applied to the transitions in a rule table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Symmetry {
+    None,
+    Rotate4,
+    Rotate4Reflect,
+    Rotate8,
+    Rotate8Reflect,
+    ReflectHorizontal,
+    Permute,
+}
+impl Symmetry {
+    fn from_str(s: &str, neighborhood: Neighborhood2D) -> Option<Self> {
+        let is_moore = neighborhood == Neighborhood2D::Moore;
+        match s {
+            "none" => Some(Self::None),
+            "rotate4" => Some(Self::Rotate4),
+            "rotate4reflect" => Some(Self::Rotate4Reflect),
+            "rotate8" if is_moore => Some(Self::Rotate8),
+            "rotate8reflect" if is_moore => Some(Self::Rotate8Reflect),
+            "reflect_horizontal" => Some(Self::ReflectHorizontal),
+            "permute" => Some(Self::Permute),
+            _ => None,
+        }
+    }
+    /// Returns every arrangement of the given neighbors (listed clockwise from
+    /// north) that is equivalent under this symmetry, without duplicates.
+    fn apply<T: Clone + Ord + Hash>(self, neighbors: &[T]) -> Vec<Vec<T>> {
+        let n = neighbors.len();
+        let rotate =
+            |by: usize| -> Vec<T> { (0..n).map(|i| neighbors[(i + by) % n].clone()).collect() };
+        let reflect = |v: Vec<T>| -> Vec<T> { (0..n).map(|i| v[(n - i) % n].clone()).collect() };
+        // Rotations by a quarter turn move each neighbor by a quarter of the
+        // way around the ring.
+        let quarter = n / 4;
+        let arrangements: Vec<Vec<T>> = match self {
+            Self::None => vec![neighbors.to_vec()],
+            Self::Rotate4 => (0..4).map(|r| rotate(r * quarter)).collect(),
+            Self::Rotate4Reflect => (0..4)
+                .flat_map(|r| vec![rotate(r * quarter), reflect(rotate(r * quarter))])
+                .collect(),
+            Self::Rotate8 => (0..8).map(rotate).collect(),
+            Self::Rotate8Reflect => (0..8)
+                .flat_map(|r| vec![rotate(r), reflect(rotate(r))])
+                .collect(),
+            Self::ReflectHorizontal => vec![neighbors.to_vec(), reflect(neighbors.to_vec())],
+            Self::Permute => {
+                let mut current = neighbors.to_vec();
+                current.sort();
+                let mut ret = vec![current.clone()];
+                while next_permutation(&mut current) {
+                    ret.push(current.clone());
+                }
+                ret
+            }
+        };
+        let mut seen = HashSet::new();
+        arrangements
+            .into_iter()
+            .filter(|arrangement| seen.insert(arrangement.clone()))
+            .collect()
+    }
+}
+
+/// Rearranges the slice into the next lexicographically greater permutation,
+/// or returns false if it is already the greatest one.
+fn next_permutation<T: Ord>(v: &mut [T]) -> bool {
+    let i = match (1..v.len()).rev().find(|&i| v[i - 1] < v[i]) {
+        Some(i) => i,
+        None => return false,
+    };
+    let j = (i..v.len()).rev().find(|&j| v[i - 1] < v[j]).unwrap();
+    v.swap(i - 1, j);
+    v[i..].reverse();
+    true
+}
+
+/// A 2D rule described by a Golly rule table (the `@TABLE` section of a `.rule`
+/// file), using the Moore or von Neumann neighborhood.
+///
+/// Cells that do not match any transition in the table stay the same.
+#[derive(Clone)]
+pub struct RuleTable {
+    name: String,
+    num_states: usize,
+    offsets: &'static [[isize; 2]],
+    /// For each input (the center cell followed by the neighbors) and each
+    /// state, a bitset of the transitions that allow that input to have that
+    /// state.
+    masks: Vec<Vec<Vec<u64>>>,
+    /// The output state of each transition.
+    outputs: Vec<u8>,
+}
+impl fmt::Debug for RuleTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RuleTable({})", self.name)
+    }
+}
+impl fmt::Display for RuleTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl RuleTable {
+    /// Parses the contents of an `@TABLE` section, giving the resulting rule
+    /// the given name.
+    ///
+    /// Variables that appear more than once in the same transition are bound,
+    /// so they must take the same value everywhere in it.
+    pub fn parse(name: &str, table: &str) -> Result<Self, String> {
+        let mut num_states = None;
+        let mut neighborhood = None;
+        let mut symmetry = None;
+        let mut vars: HashMap<String, Vec<u8>> = HashMap::new();
+        // The set of allowed states for each input, followed by the output
+        // state.
+        let mut transitions: Vec<Transition> = vec![];
+
+        for (i, line) in table.lines().enumerate() {
+            let line_err = |msg: String| format!("Line {} of @TABLE: {}", i + 1, msg);
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("n_states:") {
+                let n: usize = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| line_err("Invalid n_states".to_owned()))?;
+                if !(2..=256).contains(&n) {
+                    return Err(line_err("n_states must be between 2 and 256".to_owned()));
+                }
+                num_states = Some(n);
+            } else if let Some(value) = line.strip_prefix("neighborhood:") {
+                neighborhood = Some(match value.trim() {
+                    "vonNeumann" => Neighborhood2D::VonNeumann,
+                    "Moore" => Neighborhood2D::Moore,
+                    other => return Err(line_err(format!("Unsupported neighborhood {:?}", other))),
+                });
+            } else if let Some(value) = line.strip_prefix("symmetries:") {
+                let n = neighborhood
+                    .ok_or_else(|| line_err("Symmetries given before neighborhood".to_owned()))?;
+                symmetry = Some(Symmetry::from_str(value.trim(), n).ok_or_else(|| {
+                    line_err(format!("Unsupported symmetries {:?}", value.trim()))
+                })?);
+            } else if let Some(definition) = line.strip_prefix("var ") {
+                let num_states = num_states
+                    .ok_or_else(|| line_err("Variable defined before n_states".to_owned()))?;
+                let mut parts = definition.splitn(2, '=');
+                let var_name = parts.next().unwrap().trim().to_owned();
+                let values = parts.next().map(str::trim).unwrap_or("");
+                if !values.starts_with('{') || !values.ends_with('}') {
+                    return Err(line_err(format!("Invalid variable definition {:?}", line)));
+                }
+                let mut states = vec![];
+                for item in values[1..values.len() - 1].split(',').map(str::trim) {
+                    states.extend(parse_token(item, &vars, num_states).map_err(line_err)?);
+                }
+                vars.insert(var_name, states);
+            } else {
+                let (num_states, neighborhood, symmetry) =
+                    match (num_states, neighborhood, symmetry) {
+                        (Some(n), Some(neighborhood), Some(symmetry)) => {
+                            (n, neighborhood, symmetry)
+                        }
+                        _ => {
+                            return Err(line_err(
+                                "Transition given before n_states, neighborhood, and symmetries"
+                                    .to_owned(),
+                            ))
+                        }
+                    };
+                let tokens: Vec<&str> = if line.contains(',') {
+                    line.split(',').map(str::trim).collect()
+                } else {
+                    // With ten or fewer states, the commas may be omitted.
+                    line.char_indices()
+                        .map(|(i, c)| &line[i..i + c.len_utf8()])
+                        .collect()
+                };
+                let num_neighbors = neighborhood_offsets(neighborhood).len();
+                if tokens.len() != num_neighbors + 2 {
+                    return Err(line_err(format!(
+                        "Expected {} values in transition",
+                        num_neighbors + 2
+                    )));
+                }
+                transitions.extend(
+                    expand_transition(&tokens, &vars, num_states, symmetry).map_err(line_err)?,
+                );
+            }
+        }
+
+        let num_states = num_states.ok_or("Missing n_states in @TABLE")?;
+        let neighborhood = neighborhood.ok_or("Missing neighborhood in @TABLE")?;
+        let offsets = neighborhood_offsets(neighborhood);
+        let words = transitions.len().div_ceil(64);
+        let mut masks = vec![vec![vec![0u64; words]; num_states]; offsets.len() + 1];
+        let mut outputs = vec![];
+        for (idx, (inputs, output)) in transitions.into_iter().enumerate() {
+            for (input_masks, allowed_states) in masks.iter_mut().zip(inputs) {
+                for state in allowed_states {
+                    input_masks[state as usize][idx / 64] |= 1 << (idx % 64);
+                }
+            }
+            outputs.push(output);
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            num_states,
+            offsets,
+            masks,
+            outputs,
+        })
+    }
+}
+
+/// Returns the offsets of the neighbors in the given neighborhood in the order
+/// used by rule tables.
+fn neighborhood_offsets(neighborhood: Neighborhood2D) -> &'static [[isize; 2]] {
+    match neighborhood {
+        Neighborhood2D::VonNeumann => VON_NEUMANN_OFFSETS,
+        _ => MOORE_OFFSETS,
+    }
+}
+
+/// Returns the states described by a single value in a rule table, which may
+/// be a state number or the name of a variable.
+fn parse_token(
+    token: &str,
+    vars: &HashMap<String, Vec<u8>>,
+    num_states: usize,
+) -> Result<Vec<u8>, String> {
+    if let Some(states) = vars.get(token) {
+        Ok(states.clone())
+    } else {
+        match token.parse::<usize>() {
+            Ok(state) if state < num_states => Ok(vec![state as u8]),
+            Ok(_) => Err(format!("State {} out of range", token)),
+            Err(_) => Err(format!("Unknown variable {:?}", token)),
+        }
+    }
+}
+
+/// A transition after expanding variables: the set of allowed states for each
+/// input (the center cell followed by the neighbors) and the output state.
+type Transition = (Vec<Vec<u8>>, u8);
+
+/// Expands a single transition from a rule table (the center cell, the
+/// neighbors, and the output) into the set of allowed states for each input
+/// and the output state, for every value of each bound variable and every
+/// symmetric arrangement of the neighbors.
+fn expand_transition(
+    tokens: &[&str],
+    vars: &HashMap<String, Vec<u8>>,
+    num_states: usize,
+    symmetry: Symmetry,
+) -> Result<Vec<Transition>, String> {
+    let mut bound_vars: Vec<&str> = vec![];
+    for &token in tokens {
+        if vars.contains_key(token)
+            && tokens.iter().filter(|&&t| t == token).count() > 1
+            && !bound_vars.contains(&token)
+        {
+            bound_vars.push(token);
+        }
+    }
+    let output_token = tokens[tokens.len() - 1];
+    if vars.contains_key(output_token) && !bound_vars.contains(&output_token) {
+        return Err(format!(
+            "Output variable {:?} is not bound to an input",
+            output_token
+        ));
+    }
+
+    // Enumerate every combination of values for the bound variables.
+    let mut assignments: Vec<HashMap<&str, u8>> = vec![HashMap::new()];
+    for &var_name in &bound_vars {
+        assignments = assignments
+            .into_iter()
+            .flat_map(|assignment| {
+                vars[var_name].iter().map(move |&value| {
+                    let mut assignment = assignment.clone();
+                    assignment.insert(var_name, value);
+                    assignment
+                })
+            })
+            .collect();
+    }
+
+    let mut ret = vec![];
+    for assignment in assignments {
+        let mut inputs = vec![];
+        for &token in &tokens[..tokens.len() - 1] {
+            inputs.push(match assignment.get(token) {
+                Some(&value) => vec![value],
+                None => parse_token(token, vars, num_states)?,
+            });
+        }
+        let output = match assignment.get(output_token) {
+            Some(&value) => value,
+            None => parse_token(output_token, vars, num_states)?[0],
+        };
+        let center = inputs.remove(0);
+        for neighbors in symmetry.apply(&inputs) {
+            let mut inputs = vec![center.clone()];
+            inputs.extend(neighbors);
+            ret.push((inputs, output));
+        }
+    }
+    Ok(ret)
+}
+
+impl Rule<u8, Dim2D> for RuleTable {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn num_states(&self) -> usize {
+        self.num_states
+    }
+    fn transition(&self, napkin: &ArraySlice2D<u8>) -> u8 {
+        let center = napkin[&NdVec::origin()];
+        let inputs = std::iter::once(center)
+            .chain(self.offsets.iter().map(|&offset| napkin[&NdVec(offset)]));
+        // This is called for every cell when the rule isn't compiled, so avoid
+        // allocating.
+        let mut input_masks: [&[u64]; MAX_INPUTS] = [&[]; MAX_INPUTS];
+        for (input_mask, (masks, state)) in
+            input_masks.iter_mut().zip(self.masks.iter().zip(inputs))
+        {
+            *input_mask = match masks.get(state as usize) {
+                Some(masks) => masks,
+                // Cells with states outside the table do not match any
+                // transition.
+                None => return center,
+            };
+        }
+        let input_masks = &input_masks[..self.masks.len()];
+        for word in 0..self.outputs.len().div_ceil(64) {
+            let matches = input_masks.iter().fold(!0, |acc, masks| acc & masks[word]);
+            if matches != 0 {
+                return self.outputs[word * 64 + matches.trailing_zeros() as usize];
+            }
+        }
+        center
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetries() {
+        let neighbors = [1, 2, 3, 4];
+        assert_eq!(1, Symmetry::None.apply(&neighbors).len());
+        assert_eq!(
+            vec![
+                vec![1, 2, 3, 4],
+                vec![2, 3, 4, 1],
+                vec![3, 4, 1, 2],
+                vec![4, 1, 2, 3]
+            ],
+            Symmetry::Rotate4.apply(&neighbors)
+        );
+        assert_eq!(
+            vec![vec![1, 2, 3, 4], vec![1, 4, 3, 2]],
+            Symmetry::ReflectHorizontal.apply(&neighbors)
+        );
+        assert_eq!(8, Symmetry::Rotate4Reflect.apply(&neighbors).len());
+        assert_eq!(24, Symmetry::Permute.apply(&neighbors).len());
+        // Duplicate arrangements are removed.
+        assert_eq!(2, Symmetry::Rotate4.apply(&[1, 2, 1, 2]).len());
+        assert_eq!(28, Symmetry::Permute.apply(&[0, 0, 0, 0, 0, 0, 1, 1]).len());
+
+        let moore = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(
+            vec![3, 4, 5, 6, 7, 8, 1, 2],
+            Symmetry::Rotate4.apply(&moore)[1]
+        );
+        assert_eq!(16, Symmetry::Rotate8Reflect.apply(&moore).len());
+    }
+
+    #[test]
+    fn test_rule_table_parse() {
+        let table = "
+            n_states:3
+            neighborhood:vonNeumann
+            symmetries:rotate4
+            var a={0,1,2}
+            var b={a}
+            # A cell in state 1 with a neighbor in state 2 to its north becomes
+            # state 2.
+            1,2,a,b,a,2
+            # Compact form, with bound variables.
+            0,1,0,0,0,1
+            2a0a01
+        ";
+        let rule = RuleTable::parse("Test", table).unwrap();
+        assert_eq!("Test", rule.to_string());
+        assert_eq!(3, rule.num_states());
+        // The first transition has 3 values for its bound variable, each
+        // rotated 4 ways. The last transition has only 1 distinct rotation when
+        // `a` is 0, and 2 otherwise.
+        assert_eq!(3 * 4 + 4 + (1 + 2 + 2), rule.outputs.len());
+
+        for (table, msg) in &[
+            (
+                "neighborhood:Moore\nsymmetries:none\n0,0,0,0,0,0,0,0,0,1",
+                "before",
+            ),
+            (
+                "n_states:2\nneighborhood:hexagonal",
+                "Unsupported neighborhood",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:rotate8",
+                "Unsupported symmetries",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:none\n0,0,0,0,2",
+                "Expected 6",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:none\n0,0,0,0,0,2",
+                "out of range",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:none\n0,0,0,0,x,1",
+                "Unknown variable",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:none\nvar a={0,1}\n0,0,0,0,0,a",
+                "not bound",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:none\n0000é1",
+                "Unknown variable",
+            ),
+            (
+                "n_states:2\nneighborhood:vonNeumann\nsymmetries:none\n00000→",
+                "Unknown variable",
+            ),
+        ] {
+            let err = RuleTable::parse("Test", table).unwrap_err();
+            assert!(err.contains(msg), "{:?} does not contain {:?}", err, msg);
+        }
+    }
+}
@@ -2,48 +2,135 @@ use super::Rule;
 use crate::automaton::space::*;
 use regex::Regex;
 use std::convert::TryFrom;
+use std::fmt;
 
-/// A 2-state totalistic 2D range-1 Moore-neighborhood algorithm.
-#[derive(Debug)]
-pub struct MooreTotalistic2D {
+/// A range-1 neighborhood that can be used by a Life-like rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighborhood2D {
+    /// The eight cells surrounding the center cell.
+    Moore,
+    /// The four cells orthogonally adjacent to the center cell (suffix `V`).
+    VonNeumann,
+    /// A hexagonal grid emulated on a square one, in the same way as Golly
+    /// (suffix `H`): the Moore neighborhood without the northeast and
+    /// southwest cells.
+    Hexagonal,
+}
+impl Neighborhood2D {
+    /// Returns the offsets of the neighbors (not including the center cell).
+    fn offsets(self) -> &'static [[isize; 2]] {
+        match self {
+            Self::Moore => &[
+                [-1, -1],
+                [0, -1],
+                [1, -1],
+                [-1, 0],
+                [1, 0],
+                [-1, 1],
+                [0, 1],
+                [1, 1],
+            ],
+            Self::VonNeumann => &[[0, -1], [-1, 0], [1, 0], [0, 1]],
+            // Y increases upwards in NDCell, so northeast is (1, 1).
+            Self::Hexagonal => &[[0, -1], [1, -1], [-1, 0], [1, 0], [-1, 1], [0, 1]],
+        }
+    }
+    /// Returns the suffix used for this neighborhood in rule strings.
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Moore => "",
+            Self::VonNeumann => "V",
+            Self::Hexagonal => "H",
+        }
+    }
+    /// Returns the neighborhood with the given rule string suffix, or None if
+    /// the suffix is not recognized.
+    fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "" => Some(Self::Moore),
+            "V" | "v" => Some(Self::VonNeumann),
+            "H" | "h" => Some(Self::Hexagonal),
+            _ => None,
+        }
+    }
+}
+
+/// A 2-state outer-totalistic 2D range-1 rule (i.e. a "Life-like" rule), using
+/// a Moore, von Neumann, or hexagonal neighborhood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Totalistic2D {
     birth: [u8; 9],
     survival: [u8; 9],
+    neighborhood: Neighborhood2D,
 }
 
-impl TryFrom<&str> for MooreTotalistic2D {
+impl TryFrom<&str> for Totalistic2D {
     type Error = ();
+    /// Parses a rule string in B/S notation (e.g. `B36/S23`) or S/B notation
+    /// (e.g. `23/36`), optionally followed by a neighborhood suffix (`V` or
+    /// `H`).
     fn try_from(s: &str) -> Result<Self, ()> {
-        let regex = Regex::new(r"^[Bb](\d*)/?[Ss](\d*)$").unwrap();
-        let captures = regex.captures(s).ok_or(())?;
-        let mut conditions = [[0; 9]; 2];
-        for i in 0..2 {
-            for ch in captures[i].chars() {
-                conditions[i][ch.to_string().parse::<usize>().unwrap()] = 1;
+        let captures = [
+            r"^[Bb](?P<b>\d*)/?[Ss](?P<s>\d*)(?P<n>[A-Za-z]?)$",
+            r"^[Ss](?P<s>\d*)/?[Bb](?P<b>\d*)(?P<n>[A-Za-z]?)$",
+            r"^(?P<s>\d*)/(?P<b>\d*)(?P<n>[A-Za-z]?)$",
+        ]
+        .iter()
+        .find_map(|regex| Regex::new(regex).unwrap().captures(s))
+        .ok_or(())?;
+        let neighborhood = Neighborhood2D::from_suffix(&captures["n"]).ok_or(())?;
+        let max_neighbors = neighborhood.offsets().len();
+        let parse_conditions = |digits: &str| {
+            let mut conditions = [0; 9];
+            for ch in digits.chars() {
+                let n = ch.to_digit(10).ok_or(())? as usize;
+                if n > max_neighbors {
+                    return Err(());
+                }
+                conditions[n] = 1;
             }
-        }
+            Ok(conditions)
+        };
         Ok(Self {
-            birth: conditions[0],
-            survival: conditions[0],
+            birth: parse_conditions(&captures["b"])?,
+            survival: parse_conditions(&captures["s"])?,
+            neighborhood,
         })
     }
 }
 
-impl Rule<u8, Dim2D> for MooreTotalistic2D {
+impl fmt::Display for Totalistic2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        for (n, &cell_state) in self.birth.iter().enumerate() {
+            if cell_state != 0 {
+                write!(f, "{}", n)?;
+            }
+        }
+        write!(f, "/S")?;
+        for (n, &cell_state) in self.survival.iter().enumerate() {
+            if cell_state != 0 {
+                write!(f, "{}", n)?;
+            }
+        }
+        write!(f, "{}", self.neighborhood.suffix())
+    }
+}
+
+impl Rule<u8, Dim2D> for Totalistic2D {
     fn radius(&self) -> usize {
         1
     }
     fn transition(&self, napkin: &ArraySlice2D<u8>) -> u8 {
         // Count live neighbors.
-        let nbhood = Rect2D::moore(self.radius() as isize);
         let mut live_neighbors = 0;
-        for cell_coords in nbhood.iter() {
-            if napkin[&cell_coords] != 0 {
+        for &offset in self.neighborhood.offsets() {
+            if napkin[&NdVec(offset)] != 0 {
                 live_neighbors += 1;
             }
         }
         // Index LUT to get next cell state.
         if napkin[&NdVec::origin()] != 0 {
-            live_neighbors -= 1;
             self.survival[live_neighbors]
         } else {
             self.birth[live_neighbors]
@@ -51,9 +138,60 @@ impl Rule<u8, Dim2D> for MooreTotalistic2D {
     }
 }
 
-/// Conway's Game of Life, simulated using a general 2-state totalistic
-/// 2D range-1 Moore-neighborhood algorithm.
-pub const LIFE: MooreTotalistic2D = MooreTotalistic2D {
+/// Conway's Game of Life, simulated using a general 2-state outer-totalistic
+/// 2D range-1 algorithm.
+pub const LIFE: Totalistic2D = Totalistic2D {
     birth: [0, 0, 0, 1, 0, 0, 0, 0, 0],
     survival: [0, 0, 1, 1, 0, 0, 0, 0, 0],
+    neighborhood: Neighborhood2D::Moore,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Totalistic2D {
+        Totalistic2D::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn test_totalistic_2d_parse() {
+        assert_eq!(LIFE, parse("B3/S23"));
+        assert_eq!(LIFE, parse("b3s23"));
+        assert_eq!(LIFE, parse("S23/B3"));
+        assert_eq!(LIFE, parse("23/3"));
+        assert_eq!(LIFE, parse("B33/S32"));
+
+        // Canonical rule strings round-trip.
+        for &(input, canonical) in &[
+            ("B3/S23", "B3/S23"),
+            ("23/36", "B36/S23"),
+            ("B2/S", "B2/S"),
+            ("/2", "B2/S"),
+            ("B3678/S34678", "B3678/S34678"),
+            ("34678/3678", "B3678/S34678"),
+            ("B2/S013V", "B2/S013V"),
+            ("s013b2v", "B2/S013V"),
+            ("B245/S3H", "B245/S3H"),
+            ("3/245h", "B245/S3H"),
+            ("B/S", "B/S"),
+        ] {
+            let rule = parse(input);
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule, parse(canonical));
+        }
+
+        for &s in &[
+            "",
+            "B3S23/",
+            "B9/S23",
+            "B3/S23X",
+            "B5/S23V",
+            "B3/S7H",
+            "B3/S23/C3",
+            "23",
+        ] {
+            assert_eq!(Err(()), Totalistic2D::try_from(s), "{:?}", s);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use super::Rule;
+use crate::automaton::space::*;
+use std::fmt;
+
+/// The offsets of the cells in a von Neumann rule tree, in the order they are
+/// looked up: N, W, E, S, then the center cell. (Y increases upwards in NDCell,
+/// so north is +Y.)
+const VON_NEUMANN_ORDER: &[[isize; 2]] = &[[0, 1], [-1, 0], [1, 0], [0, -1], [0, 0]];
+/// The offsets of the cells in a Moore rule tree, in the order they are looked
+/// up: NW, NE, SW, SE, N, W, E, S, then the center cell.
+const MOORE_ORDER: &[[isize; 2]] = &[
+    [-1, 1],
+    [1, 1],
+    [-1, -1],
+    [1, -1],
+    [0, 1],
+    [-1, 0],
+    [1, 0],
+    [0, -1],
+    [0, 0],
+];
+
+/// A 2D rule described by a Golly rule tree (the `@TREE` section of a `.rule`
+/// file), using the Moore or von Neumann neighborhood.
+///
+/// A rule tree is a decision tree that looks up the state of one cell at each
+/// level, so the next state of a cell is found in a fixed number of steps
+/// regardless of how many transitions the rule has.
+#[derive(Clone)]
+pub struct RuleTree {
+    name: String,
+    num_states: usize,
+    order: &'static [[isize; 2]],
+    /// The nodes of the tree. Each entry of a node on the lowest level is an
+    /// output state, and each entry of a higher node is the index of a node on
+    /// the level below it.
+    nodes: Vec<Vec<usize>>,
+}
+impl fmt::Debug for RuleTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RuleTree({})", self.name)
+    }
+}
+impl fmt::Display for RuleTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl RuleTree {
+    /// Parses the contents of an `@TREE` section, giving the resulting rule the
+    /// given name.
+    pub fn parse(name: &str, tree: &str) -> Result<Self, String> {
+        let mut num_states = None;
+        let mut order = None;
+        let mut num_nodes = None;
+        // The level of each node, followed by its entries.
+        let mut nodes: Vec<(usize, Vec<usize>)> = vec![];
+
+        for (i, line) in tree.lines().enumerate() {
+            let line_err = |msg: &str| format!("Line {} of @TREE: {}", i + 1, msg);
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("num_states=") {
+                let n: usize = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| line_err("Invalid num_states"))?;
+                if !(2..=256).contains(&n) {
+                    return Err(line_err("num_states must be between 2 and 256"));
+                }
+                num_states = Some(n);
+            } else if let Some(value) = line.strip_prefix("num_neighbors=") {
+                order = Some(match value.trim() {
+                    "4" => VON_NEUMANN_ORDER,
+                    "8" => MOORE_ORDER,
+                    _ => return Err(line_err("num_neighbors must be 4 or 8")),
+                });
+            } else if let Some(value) = line.strip_prefix("num_nodes=") {
+                num_nodes = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| line_err("Invalid num_nodes"))?,
+                );
+            } else {
+                let (num_states, order) = match (num_states, order) {
+                    (Some(n), Some(order)) => (n, order),
+                    _ => return Err(line_err("Node given before num_states and num_neighbors")),
+                };
+                let numbers = line
+                    .split_whitespace()
+                    .map(|n| n.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| line_err("Invalid node"))?;
+                if numbers.len() != num_states + 1 {
+                    return Err(line_err("Wrong number of entries in node"));
+                }
+                let level = numbers[0];
+                let entries = numbers[1..].to_vec();
+                let valid = match level {
+                    1 => entries.iter().all(|&state| state < num_states),
+                    _ if level <= order.len() => entries
+                        .iter()
+                        .all(|&idx| nodes.get(idx).map(|(l, _)| l + 1) == Some(level)),
+                    _ => false,
+                };
+                if !valid {
+                    return Err(line_err("Invalid node"));
+                }
+                nodes.push((level, entries));
+            }
+        }
+
+        let num_states = num_states.ok_or("Missing num_states in @TREE")?;
+        let order = order.ok_or("Missing num_neighbors in @TREE")?;
+        if num_nodes != Some(nodes.len()) {
+            return Err("Wrong number of nodes in @TREE".to_owned());
+        }
+        // The root node is the last one, and it must be on the top level.
+        if nodes.last().map(|&(level, _)| level) != Some(order.len()) {
+            return Err("Root node of @TREE is not on the top level".to_owned());
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            num_states,
+            order,
+            nodes: nodes.into_iter().map(|(_, entries)| entries).collect(),
+        })
+    }
+}
+
+impl Rule<u8, Dim2D> for RuleTree {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn num_states(&self) -> usize {
+        self.num_states
+    }
+    fn transition(&self, napkin: &ArraySlice2D<u8>) -> u8 {
+        let mut idx = self.nodes.len() - 1;
+        for &offset in self.order {
+            match self.nodes[idx].get(napkin[&NdVec(offset)] as usize) {
+                Some(&next) => idx = next,
+                // Cells with states outside the tree stay the same.
+                None => return napkin[&NdVec::origin()],
+            }
+        }
+        idx as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A von Neumann rule where each cell becomes the XOR of its four
+    /// neighbors.
+    const XOR_TREE: &str = "
+        num_states=2
+        num_neighbors=4
+        num_nodes=9
+        1 0 0
+        1 1 1
+        2 0 1
+        2 1 0
+        3 2 3
+        3 3 2
+        4 4 5
+        4 5 4
+        5 6 7
+    ";
+
+    #[test]
+    fn test_rule_tree_parse() {
+        let rule = RuleTree::parse("Xor", XOR_TREE).unwrap();
+        assert_eq!("Xor", rule.to_string());
+        assert_eq!(2, rule.num_states());
+        assert_eq!(9, rule.nodes.len());
+
+        for (tree, msg) in &[
+            (
+                XOR_TREE.replace("num_nodes=9", "num_nodes=8"),
+                "Wrong number of nodes",
+            ),
+            (
+                XOR_TREE.replace("5 6 7", "5 6 7 1"),
+                "Wrong number of entries",
+            ),
+            (XOR_TREE.replace("5 6 7", "5 6 3"), "Invalid node"),
+            (XOR_TREE.replace("1 1 1", "1 1 2"), "Invalid node"),
+            (
+                XOR_TREE.replace("num_neighbors=4", "num_neighbors=6"),
+                "4 or 8",
+            ),
+            (XOR_TREE.replace("5 6 7", "4 6 7"), "Invalid node"),
+            (XOR_TREE.replace("5 6 7", ""), "Wrong number of nodes"),
+        ] {
+            let err = RuleTree::parse("Xor", tree).unwrap_err();
+            assert!(err.contains(msg), "{:?} does not contain {:?}", err, msg);
+        }
+    }
+}
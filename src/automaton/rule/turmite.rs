@@ -0,0 +1,456 @@
+use super::Rule;
+use crate::automaton::space::*;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Unit vectors along each axis, in the order +Y, +X, -Y, -X, +Z, -Z. In 2D,
+/// the first four are north, east, south, and west (Y increases upwards in
+/// NDCell, so north is +Y), and the rest are not used.
+const DIRECTIONS: [[isize; 3]; 6] = [
+    [0, 1, 0],
+    [1, 0, 0],
+    [0, -1, 0],
+    [-1, 0, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// Returns the opposite of a direction.
+fn negate([x, y, z]: [isize; 3]) -> [isize; 3] {
+    [-x, -y, -z]
+}
+
+/// Returns the cross product of two directions.
+fn cross([x1, y1, z1]: [isize; 3], [x2, y2, z2]: [isize; 3]) -> [isize; 3] {
+    [y1 * z2 - z1 * y2, z1 * x2 - x1 * z2, x1 * y2 - y1 * x2]
+}
+
+/// The direction that an ant is facing, along with the direction of its "up"
+/// (which only matters in 3D; in 2D it is always +Z).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Orientation {
+    forward: [isize; 3],
+    up: [isize; 3],
+}
+
+/// A turn made by a turmite, encoded in specification strings the same way as
+/// in Golly, with two extra turns for 3D.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Turn {
+    /// Keep facing the same direction (`1`).
+    NoTurn,
+    /// Turn 90 degrees clockwise, as seen from above (`2`).
+    Right,
+    /// Turn 180 degrees (`4`).
+    UTurn,
+    /// Turn 90 degrees counterclockwise, as seen from above (`8`).
+    Left,
+    /// Pitch 90 degrees upwards, so that the ant faces the way that was up and
+    /// its back faces the way it came from (`16`, 3D only).
+    Up,
+    /// Pitch 90 degrees downwards (`32`, 3D only).
+    Down,
+}
+impl Turn {
+    /// Every turn, in the same order as their codes.
+    const ALL: [Self; 6] = [
+        Self::NoTurn,
+        Self::Right,
+        Self::UTurn,
+        Self::Left,
+        Self::Up,
+        Self::Down,
+    ];
+
+    /// Returns the number used for this turn in specification strings.
+    fn code(self) -> u8 {
+        1 << self as u8
+    }
+    /// Returns the turn with the given number from a specification string, or
+    /// None if the number is not recognized.
+    fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|turn| turn.code() == code)
+    }
+    /// Returns whether this turn can leave the plane that the ant is in.
+    fn is_3d(self) -> bool {
+        self == Self::Up || self == Self::Down
+    }
+    /// Returns the orientation of an ant after it makes this turn.
+    fn apply(self, orientation: Orientation) -> Orientation {
+        let Orientation { forward, up } = orientation;
+        let right = cross(forward, up);
+        let (forward, up) = match self {
+            Self::NoTurn => (forward, up),
+            Self::Right => (right, up),
+            Self::UTurn => (negate(forward), up),
+            Self::Left => (negate(right), up),
+            Self::Up => (up, negate(forward)),
+            Self::Down => (negate(up), forward),
+        };
+        Orientation { forward, up }
+    }
+}
+
+/// What a turmite does when it finds itself on a cell of a particular color
+/// in a particular state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct TurmiteAction {
+    /// The color to paint the current cell.
+    color: u8,
+    /// The direction to turn before moving forward.
+    turn: Turn,
+    /// The state to enter.
+    state: u8,
+}
+
+/// A 2D or 3D turmite, of which Langton's Ant (`{{{1,2,0},{0,8,0}}}`) is the
+/// most famous.
+///
+/// Each cell state packs together the color of the cell and the state and
+/// orientation of the ant on it (if any): the cell state is `color + colors *
+/// ant`, where `ant` is 0 for a cell with no ant or `1 + ant_state *
+/// orientations + orientation` for a cell with an ant. In 2D, there are 4
+/// orientations, numbered clockwise from north. In 3D, there are 24
+/// orientations (one for each direction the ant can face and each direction
+/// its "up" can be), and the first 4 are the same as in 2D with up being +Z,
+/// so a 3D turmite that never turns up or down behaves just like a 2D one. If
+/// several ants try to move into the same cell at once, only one of them
+/// survives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turmite<D: Dim> {
+    colors: u8,
+    ant_states: u8,
+    /// The action for each ant state and cell color, indexed by `ant_state *
+    /// colors + color`.
+    table: Vec<TurmiteAction>,
+    /// Every orientation that an ant can have.
+    orientations: Vec<Orientation>,
+    /// The orientation after each turn (in the same order as `Turn::ALL`)
+    /// from each orientation.
+    turns: Vec<[u8; 6]>,
+    _phantom: PhantomData<D>,
+}
+
+impl<D: Dim> Turmite<D> {
+    /// Returns the cell state for a cell of the given color with an ant on it
+    /// in the given state and orientation. (See `Turmite` for how orientations
+    /// are numbered.)
+    pub fn ant_cell_state(&self, color: u8, ant_state: u8, orientation: u8) -> u8 {
+        color + self.colors * (1 + ant_state * self.orientations.len() as u8 + orientation)
+    }
+    /// Returns the color of the cell with the given state, and the state and
+    /// orientation of the ant on it (if any).
+    ///
+    /// States that this rule doesn't have (e.g. from an RLE) are treated as
+    /// empty cells.
+    fn decode(&self, cell_state: u8) -> (u8, Option<(u8, u8)>) {
+        if cell_state as usize >= self.num_states() {
+            return (0, None);
+        }
+        let color = cell_state % self.colors;
+        let orientation_count = self.orientations.len() as u8;
+        match cell_state / self.colors {
+            0 => (color, None),
+            ant => (
+                color,
+                Some(((ant - 1) / orientation_count, (ant - 1) % orientation_count)),
+            ),
+        }
+    }
+    fn action(&self, ant_state: u8, color: u8) -> TurmiteAction {
+        self.table[(ant_state * self.colors + color) as usize]
+    }
+}
+
+/// Returns every orientation that an ant can have in the given number of
+/// dimensions (2 or 3), in the order described for `Turmite`.
+fn orientations(ndim: usize) -> Vec<Orientation> {
+    let directions = &DIRECTIONS[..2 * ndim];
+    let ups: &[[isize; 3]] = if ndim == 3 {
+        // +Z, -Z, +Y, -Y, +X, -X
+        &[
+            DIRECTIONS[4],
+            DIRECTIONS[5],
+            DIRECTIONS[0],
+            DIRECTIONS[2],
+            DIRECTIONS[1],
+            DIRECTIONS[3],
+        ]
+    } else {
+        &DIRECTIONS[4..5]
+    };
+    ups.iter()
+        .flat_map(|&up| {
+            directions
+                .iter()
+                .filter(move |&&forward| cross(forward, up) != [0, 0, 0])
+                .map(move |&forward| Orientation { forward, up })
+        })
+        .collect()
+}
+
+/// Splits a string of the form `{a,b,c}` into its top-level comma-separated
+/// items, or returns None if it is not surrounded by braces or the braces are
+/// unbalanced.
+fn split_braced(s: &str) -> Option<Vec<&str>> {
+    if !s.starts_with('{') || !s.ends_with('}') || s.len() < 2 {
+        return None;
+    }
+    let inner = &s[1..s.len() - 1];
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, ch) in inner.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' if depth == 0 => return None,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    items.push(&inner[start..]);
+    Some(items)
+}
+
+impl<D: Dim> TryFrom<&str> for Turmite<D> {
+    type Error = ();
+    /// Parses a turmite specification string in the same format as Golly
+    /// (e.g. `{{{1,2,0},{0,8,0}}}`), which lists a `{color,turn,state}` action
+    /// for each cell color within each ant state.
+    ///
+    /// Turmites only exist in 2D and 3D, since turning left or right is not
+    /// well-defined in other numbers of dimensions.
+    fn try_from(s: &str) -> Result<Self, ()> {
+        if D::NDIM != 2 && D::NDIM != 3 {
+            return Err(());
+        }
+        let s: String = s.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let states = split_braced(&s).ok_or(())?;
+        let ant_states = states.len();
+        let mut actions = vec![];
+        let mut colors = None;
+        for state in states {
+            let state_actions = split_braced(state).ok_or(())?;
+            // Every ant state must handle the same number of colors.
+            if *colors.get_or_insert(state_actions.len()) != state_actions.len() {
+                return Err(());
+            }
+            for action in state_actions {
+                let numbers = split_braced(action)
+                    .ok_or(())?
+                    .into_iter()
+                    .map(|n| n.parse::<u8>().map_err(|_| ()))
+                    .collect::<Result<Vec<u8>, ()>>()?;
+                match numbers[..] {
+                    [color, turn, state] => actions.push(TurmiteAction {
+                        color,
+                        turn: Turn::from_code(turn).ok_or(())?,
+                        state,
+                    }),
+                    _ => return Err(()),
+                }
+            }
+        }
+        let colors = colors.ok_or(())?;
+        let orientations = orientations(D::NDIM);
+        if colors < 2 || colors * (1 + orientations.len() * ant_states) > 256 {
+            return Err(());
+        }
+        if actions.iter().any(|action| {
+            action.color as usize >= colors
+                || action.state as usize >= ant_states
+                || (action.turn.is_3d() && D::NDIM != 3)
+        }) {
+            return Err(());
+        }
+        let turns = orientations
+            .iter()
+            .map(|&orientation| {
+                let mut ret = [0; 6];
+                for (i, turn) in Turn::ALL.iter().enumerate() {
+                    if turn.is_3d() && D::NDIM != 3 {
+                        continue;
+                    }
+                    let new_orientation = turn.apply(orientation);
+                    ret[i] = orientations
+                        .iter()
+                        .position(|&o| o == new_orientation)
+                        .unwrap() as u8;
+                }
+                ret
+            })
+            .collect();
+        Ok(Self {
+            colors: colors as u8,
+            ant_states: ant_states as u8,
+            table: actions,
+            orientations,
+            turns,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<D: Dim> fmt::Display for Turmite<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, state_actions) in self.table.chunks(self.colors as usize).enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{{")?;
+            for (j, action) in state_actions.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ",")?;
+                }
+                write!(
+                    f,
+                    "{{{},{},{}}}",
+                    action.color,
+                    action.turn.code(),
+                    action.state
+                )?;
+            }
+            write!(f, "}}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for Turmite<D> {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn num_states(&self) -> usize {
+        self.colors as usize * (1 + self.orientations.len() * self.ant_states as usize)
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        let (mut color, ant) = self.decode(napkin[&NdVec::origin()]);
+        // If there is an ant here, it paints this cell and leaves.
+        if let Some((ant_state, _)) = ant {
+            color = self.action(ant_state, color).color;
+        }
+        // Look for an ant that will move onto this cell.
+        for &offset in &DIRECTIONS[..2 * D::NDIM] {
+            let neighbor = napkin[&NdVec::from_fn(|ax| offset[ax as usize])];
+            let (neighbor_color, neighbor_ant) = self.decode(neighbor);
+            if let Some((ant_state, orientation)) = neighbor_ant {
+                let action = self.action(ant_state, neighbor_color);
+                let new_orientation = self.turns[orientation as usize][action.turn as usize];
+                if self.orientations[new_orientation as usize].forward == negate(offset) {
+                    return self.ant_cell_state(color, action.state, new_orientation);
+                }
+            }
+        }
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turmite_parse() {
+        for &(input, canonical) in &[
+            ("{{{1,2,0},{0,8,0}}}", "{{{1,2,0},{0,8,0}}}"),
+            ("{{{1, 2, 0}, {0, 8, 0}}}", "{{{1,2,0},{0,8,0}}}"),
+            (
+                "{{{1,8,1},{1,8,1}},{{1,2,1},{0,1,0}}}",
+                "{{{1,8,1},{1,8,1}},{{1,2,1},{0,1,0}}}",
+            ),
+            ("{{{1,2,0},{2,8,0},{0,4,0}}}", "{{{1,2,0},{2,8,0},{0,4,0}}}"),
+        ] {
+            let rule = Turmite::<Dim2D>::try_from(input).unwrap();
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule, Turmite::<Dim2D>::try_from(canonical).unwrap());
+        }
+
+        let ant = Turmite::<Dim2D>::try_from("{{{1,2,0},{0,8,0}}}").unwrap();
+        assert_eq!(10, ant.num_states());
+        assert_eq!(2, ant.ant_cell_state(0, 0, 0));
+        assert_eq!(9, ant.ant_cell_state(1, 0, 3));
+        assert_eq!((1, Some((0, 3))), ant.decode(9));
+        assert_eq!((1, None), ant.decode(1));
+        assert_eq!((0, None), ant.decode(10));
+        assert_eq!((0, None), ant.decode(255));
+
+        for &s in &[
+            "",
+            "{}",
+            "{{{1,2,0}}}",
+            "{{{1,2,0},{0,3,0}}}",
+            "{{{1,2,0},{0,8,1}}}",
+            "{{{2,2,0},{0,8,0}}}",
+            "{{{1,2,0},{0,8,0}},{{1,2,0}}}",
+            "{{{1,2},{0,8,0}}}",
+            "{{{1,2,0},{0,8,0}}",
+            // Turning up or down only works in 3D.
+            "{{{1,2,0},{0,16,0}}}",
+        ] {
+            assert!(Turmite::<Dim2D>::try_from(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_turmite_parse_3d() {
+        let s = "{{{1,2,0},{0,16,0}}}";
+        let ant = Turmite::<Dim3D>::try_from(s).unwrap();
+        assert_eq!(s, ant.to_string());
+        assert_eq!(50, ant.num_states());
+        assert_eq!(2, ant.ant_cell_state(0, 0, 0));
+        assert_eq!((1, Some((0, 23))), ant.decode(49));
+
+        // There's only room for 5 ant states with 2 colors in 3D.
+        let five_states = format!("{{{}}}", ["{{1,2,0},{0,32,0}}"; 5].join(","));
+        assert!(Turmite::<Dim3D>::try_from(&*five_states).is_ok());
+        let six_states = format!("{{{}}}", ["{{1,2,0},{0,32,0}}"; 6].join(","));
+        assert!(Turmite::<Dim3D>::try_from(&*six_states).is_err());
+
+        assert!(Turmite::<Dim1D>::try_from("{{{1,2,0},{0,8,0}}}").is_err());
+        assert!(Turmite::<Dim4D>::try_from("{{{1,2,0},{0,8,0}}}").is_err());
+    }
+
+    #[test]
+    fn test_turmite_orientations() {
+        let orientations_2d = orientations(2);
+        let orientations_3d = orientations(3);
+        assert_eq!(4, orientations_2d.len());
+        assert_eq!(24, orientations_3d.len());
+        // The 2D orientations are the first 3D ones.
+        assert_eq!(orientations_2d[..], orientations_3d[..4]);
+
+        for &orientation in &orientations_3d {
+            let turn = |turns: &[Turn]| {
+                turns
+                    .iter()
+                    .fold(orientation, |orientation, turn| turn.apply(orientation))
+            };
+            assert!(orientations_3d.contains(&turn(&[Turn::Right])));
+            assert!(orientations_3d.contains(&turn(&[Turn::Up])));
+            assert_eq!(turn(&[Turn::Left]), turn(&[Turn::Right; 3]));
+            assert_eq!(turn(&[Turn::UTurn]), turn(&[Turn::Right; 2]));
+            assert_eq!(turn(&[Turn::Down]), turn(&[Turn::Up; 3]));
+            assert_eq!(orientation, turn(&[Turn::Up, Turn::Down]));
+            assert_eq!(orientation, turn(&[Turn::Up; 4]));
+        }
+    }
+
+    #[test]
+    fn test_turmite_invalid_states() {
+        let ant = Turmite::<Dim2D>::try_from("{{{1,2,0},{0,8,0}}}").unwrap();
+        for &cell_state in &[10, 11, 200, 255] {
+            let napkin = NdArray::from_flat_vec(UVec::repeat(3_usize), vec![cell_state; 9]);
+            let napkin = napkin.offset_slice(-IVec::repeat(1_isize));
+            assert_eq!(0, ant.transition(&napkin), "{}", cell_state);
+        }
+    }
+}
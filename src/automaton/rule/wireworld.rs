@@ -0,0 +1,97 @@
+use super::Rule;
+use crate::automaton::space::*;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The color of each Wireworld cell state, the same as in Golly.
+const WIREWORLD_COLORS: &[[u8; 3]] = &[[0, 0, 0], [0, 128, 255], [255, 255, 255], [255, 128, 0]];
+
+/// The state of an empty cell in Wireworld.
+pub const WIREWORLD_EMPTY: u8 = 0;
+/// The state of an electron head in Wireworld.
+pub const WIREWORLD_HEAD: u8 = 1;
+/// The state of an electron tail in Wireworld.
+pub const WIREWORLD_TAIL: u8 = 2;
+/// The state of a conductor in Wireworld.
+pub const WIREWORLD_CONDUCTOR: u8 = 3;
+
+/// Wireworld, using the range-1 Moore neighborhood in any number of
+/// dimensions.
+///
+/// Cell states are numbered the same as in Golly, so `A` in an RLE file is an
+/// electron head, `B` is an electron tail, and `C` is a conductor. Electron
+/// heads become tails, tails become conductors, and conductors become heads if
+/// exactly one or two of their neighbors are heads.
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+pub struct Wireworld<D: Dim> {
+    _phantom: PhantomData<D>,
+}
+impl<D: Dim> fmt::Debug for Wireworld<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Wireworld<Dim{}D>", D::NDIM)
+    }
+}
+
+impl<D: Dim> TryFrom<&str> for Wireworld<D> {
+    type Error = ();
+    /// Parses the rule string `WireWorld` (case-insensitive).
+    fn try_from(s: &str) -> Result<Self, ()> {
+        if s.eq_ignore_ascii_case("WireWorld") {
+            Ok(Self::default())
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl<D: Dim> fmt::Display for Wireworld<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WireWorld")
+    }
+}
+
+impl<D: Dim> Rule<u8, D> for Wireworld<D> {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn num_states(&self) -> usize {
+        4
+    }
+    fn colors(&self) -> Option<&'static [[u8; 3]]> {
+        Some(WIREWORLD_COLORS)
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        match napkin[&NdVec::origin()] {
+            WIREWORLD_HEAD => WIREWORLD_TAIL,
+            WIREWORLD_TAIL => WIREWORLD_CONDUCTOR,
+            WIREWORLD_CONDUCTOR => {
+                let heads = IRect::<D>::centered(NdVec::origin(), 1)
+                    .iter()
+                    .filter(|offset| napkin[offset] == WIREWORLD_HEAD)
+                    .count();
+                if heads == 1 || heads == 2 {
+                    WIREWORLD_HEAD
+                } else {
+                    WIREWORLD_CONDUCTOR
+                }
+            }
+            _ => WIREWORLD_EMPTY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wireworld_parse() {
+        for &s in &["WireWorld", "Wireworld", "wireworld"] {
+            let rule = Wireworld::<Dim3D>::try_from(s).unwrap();
+            assert_eq!("WireWorld", rule.to_string());
+        }
+        assert!(Wireworld::<Dim2D>::try_from("WireWorld2").is_err());
+        assert_eq!(4, WIREWORLD_COLORS.len());
+    }
+}
@@ -0,0 +1,187 @@
+use super::Rule;
+use crate::automaton::space::*;
+use num::{BigInt, One, ToPrimitive, Zero};
+use regex::Regex;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// One of Wolfram's 256 elementary 1D rules (e.g. Rule 110, `W110`).
+///
+/// Each bit of the rule number gives the next state of the center cell for one
+/// 3-cell neighborhood, where the neighborhood is read as a binary number with
+/// the leftmost (most negative) cell as the most significant bit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Elementary1D {
+    number: u8,
+}
+
+impl TryFrom<&str> for Elementary1D {
+    type Error = ();
+    /// Parses a rule string consisting of `W` followed by the rule number (e.g.
+    /// `W110`).
+    fn try_from(s: &str) -> Result<Self, ()> {
+        let captures = Regex::new(r"^[Ww](?P<n>\d+)$")
+            .unwrap()
+            .captures(s)
+            .ok_or(())?;
+        Ok(Self {
+            number: captures["n"].parse().map_err(|_| ())?,
+        })
+    }
+}
+
+impl fmt::Display for Elementary1D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "W{}", self.number)
+    }
+}
+
+impl Rule<u8, Dim1D> for Elementary1D {
+    fn radius(&self) -> usize {
+        1
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, Dim1D>) -> u8 {
+        let idx = [-1, 0, 1]
+            .iter()
+            .fold(0, |acc, &x| (acc << 1) | (napkin[&NdVec([x])] != 0) as u8);
+        (self.number >> idx) & 1
+    }
+}
+
+/// A k-state, range-r totalistic 1D rule described by its Wolfram code (e.g.
+/// `T777,K3,R1`).
+///
+/// The next state of each cell depends only on the sum of the states of the
+/// 2r+1 cells in its neighborhood (including itself). Digit `n` of the code,
+/// written in base k, gives the next state for a sum of `n`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Totalistic1D {
+    states: u8,
+    radius: usize,
+    /// The next state of the center cell for each possible sum.
+    table: Vec<u8>,
+}
+
+impl TryFrom<&str> for Totalistic1D {
+    type Error = ();
+    /// Parses a rule string consisting of `T` followed by the Wolfram code,
+    /// then optionally the number of states and the radius (e.g.
+    /// `T777,K3,R1`). The default is two states and a radius of 1.
+    fn try_from(s: &str) -> Result<Self, ()> {
+        let captures = Regex::new(r"^[Tt](?P<code>\d+)(,[Kk](?P<k>\d+))?(,[Rr](?P<r>\d+))?$")
+            .unwrap()
+            .captures(s)
+            .ok_or(())?;
+        let states: u8 = match captures.name("k") {
+            Some(k) => k.as_str().parse().map_err(|_| ())?,
+            None => 2,
+        };
+        let radius: usize = match captures.name("r") {
+            Some(r) => r.as_str().parse().map_err(|_| ())?,
+            None => 1,
+        };
+        if states < 2 || radius == 0 {
+            return Err(());
+        }
+        let max_sum = (2 * radius + 1) * (states as usize - 1);
+        let mut code: BigInt = captures["code"].parse().map_err(|_| ())?;
+        let mut table = Vec::with_capacity(max_sum + 1);
+        for _ in 0..=max_sum {
+            table.push((&code % states).to_u8().unwrap());
+            code /= states;
+        }
+        // Reject codes with more digits than there are possible sums.
+        if !code.is_zero() {
+            return Err(());
+        }
+        Ok(Self {
+            states,
+            radius,
+            table,
+        })
+    }
+}
+
+impl fmt::Display for Totalistic1D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut code = BigInt::zero();
+        let mut place_value = BigInt::one();
+        for &digit in &self.table {
+            code += &place_value * digit;
+            place_value *= self.states;
+        }
+        write!(f, "T{},K{},R{}", code, self.states, self.radius)
+    }
+}
+
+impl Rule<u8, Dim1D> for Totalistic1D {
+    fn radius(&self) -> usize {
+        self.radius
+    }
+    fn num_states(&self) -> usize {
+        self.states as usize
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, Dim1D>) -> u8 {
+        let radius = self.radius as isize;
+        // States that this rule doesn't have (e.g. from an RLE) count as 0.
+        let sum: usize = (-radius..=radius)
+            .map(|x| napkin[&NdVec([x])])
+            .filter(|&cell_state| cell_state < self.states)
+            .map(usize::from)
+            .sum();
+        self.table[sum]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elementary_1d_parse() {
+        assert_eq!("W110", Elementary1D::try_from("W110").unwrap().to_string());
+        assert_eq!("W30", Elementary1D::try_from("w30").unwrap().to_string());
+        for &s in &["W256", "W", "110", "W110,K2"] {
+            assert!(Elementary1D::try_from(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_totalistic_1d_parse() {
+        for &(input, canonical) in &[
+            ("T777,K3,R1", "T777,K3,R1"),
+            ("t777,k3", "T777,K3,R1"),
+            ("T10", "T10,K2,R1"),
+            ("T52,K2,R2", "T52,K2,R2"),
+            ("T0,K5,R3", "T0,K5,R3"),
+        ] {
+            let rule = Totalistic1D::try_from(input).unwrap();
+            assert_eq!(canonical, rule.to_string());
+            assert_eq!(rule, Totalistic1D::try_from(canonical).unwrap());
+        }
+        // 2 states, radius 1: sums 0 to 3 give a 4-digit binary code.
+        assert_eq!(
+            vec![0, 0, 1, 0],
+            Totalistic1D::try_from("T4").unwrap().table
+        );
+        // Codes with large numbers of digits need not fit in a u64.
+        let big = "T340282366920938463463374607431768211455,K4,R10";
+        assert_eq!(big, Totalistic1D::try_from(big).unwrap().to_string());
+
+        for &s in &["T16", "T2187,K3", "T1,K1", "T1,R0", "T1,K256", "T1,R1,K2"] {
+            assert!(Totalistic1D::try_from(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_totalistic_1d_invalid_states() {
+        let rule = Totalistic1D::try_from("T777,K3,R1").unwrap();
+        let transition = |cells: Vec<u8>| {
+            let napkin = NdArray::from_flat_vec(NdVec([3_usize]), cells);
+            rule.transition(&napkin.offset_slice(NdVec([-1_isize])))
+        };
+        assert_eq!(rule.table[2], transition(vec![1, 0, 1]));
+        assert_eq!(rule.table[2], transition(vec![1, 3, 1]));
+        assert_eq!(rule.table[4], transition(vec![2, 255, 2]));
+    }
+}
@@ -0,0 +1,765 @@
+//! HashLife, which caches the results of simulating each node.
+
+use num::{BigInt, One, Signed, ToPrimitive, Zero};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::super::rule::{block_index, block_offset};
+use super::*;
+use crate::math::{ceil_log_base_2, ceil_log_base_2_bigint};
+
+/// Nodes less than this many layers above the minimum layer are always
+/// simulated on a single thread, because spawning threads would take longer
+/// than simulating them.
+const MIN_PARALLEL_LAYERS: usize = 4;
+
+/// Number of independently locked shards that the results cache is split into,
+/// so that many threads can use it at once.
+const RESULTS_CACHE_SHARDS: usize = 64;
+
+/// Number of results added to the results cache between checks of the memory
+/// budget during a step.
+const GC_CHECK_INTERVAL: usize = 1 << 16;
+
+/// A HashLife simulation of a given automaton that caches simulation results.
+///
+/// Clones of a simulation share the same results cache, the same way that
+/// clones of an NdTree share the same node cache. Large nodes are simulated
+/// using multiple threads, which produces exactly the same nodes as simulating
+/// them on a single thread.
+#[derive(Debug, Clone)]
+pub struct HashLifeSimulator<C: CellType, D: Dim> {
+    rule: Arc<dyn Rule<C, D>>,
+    step_size: BigInt,
+    min_layer: usize,
+    /// Every phase that the simulation can be in.
+    phases: Phases<C>,
+    /// The index of the phase of the next generation to be simulated.
+    phase: usize,
+    topology: Topology<D>,
+    /// The base-2 logarithm of the step size, if hyperspeed is enabled.
+    hyperspeed_exponent: Option<usize>,
+    /// The maximum number of bytes that the node cache and results cache
+    /// should use before cached results are evicted.
+    memory_budget: usize,
+    results: Arc<ResultsCache<C, D>>,
+    /// The roots of other NdTrees (such as those in the undo history) whose
+    /// nodes keep their cached results when garbage is collected.
+    gc_roots: Vec<NdCachedNode<C, D>>,
+    /// The maximum number of threads to use for simulation.
+    thread_count: usize,
+    /// The number of results that were not already cached during the most
+    /// recent step.
+    cache_misses: usize,
+}
+impl<C: CellType, D: Dim> Default for HashLifeSimulator<C, D> {
+    fn default() -> Self {
+        Self::new(Arc::new(DummyRule), BigInt::one())
+    }
+}
+
+impl<C: CellType, D: Dim> HashLifeSimulator<C, D> {
+    /// Constructs a new HashLifeSimulator with the given rule and step size.
+    pub fn new(rule: Arc<dyn Rule<C, D>>, step_size: BigInt) -> Self {
+        // Determine the minimum layer at which we can simulate one generation
+        // of the automaton, using `n / 4 >= r`. (See the documentation for
+        // HashLifeSimulator::advance_inner_node() for an explanation.) Even at r=0 or
+        // r=1, the minimum layer is 2 because we need to return the inner node
+        // (which is at a lower layer) and the minimum layer is 1.
+        let mut min_layer = 2;
+        while (1 << min_layer) / 4 < rule.radius() {
+            min_layer += 1;
+        }
+
+        let phases = Phases::new(&*rule);
+
+        Self {
+            rule,
+            step_size,
+            min_layer,
+            phases,
+            phase: 0,
+            topology: Topology::default(),
+            hyperspeed_exponent: None,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            results: Arc::new(ResultsCache::default()),
+            gc_roots: vec![],
+            thread_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            cache_misses: 0,
+        }
+    }
+}
+impl<C: CellType, D: Dim> Simulator<C, D> for HashLifeSimulator<C, D> {
+    fn get_algorithm(&self) -> Algorithm {
+        Algorithm::HashLife
+    }
+    fn clone_box(&self) -> Box<dyn Simulator<C, D>> {
+        Box::new(self.clone())
+    }
+
+    fn get_rule(&self) -> &Arc<dyn Rule<C, D>> {
+        &self.rule
+    }
+
+    fn get_step_size(&self) -> &BigInt {
+        &self.step_size
+    }
+    fn set_step_size(&mut self, new_step_size: BigInt) {
+        self.hyperspeed_exponent = None;
+        self.step_size = self.topology.clamp_step_size(new_step_size);
+    }
+
+    fn get_hyperspeed_exponent(&self) -> Option<usize> {
+        self.hyperspeed_exponent
+    }
+    /// Enables or disables hyperspeed.
+    ///
+    /// In hyperspeed mode the step size is always a power of two, starting at
+    /// 1, and it doubles after each step where the cache keeps up (i.e. no
+    /// more new results had to be computed than on the previous step).
+    /// This lets HashLife run regular patterns exponentially fast, as in
+    /// Golly. Bounded grids are simulated one generation at a time, so the
+    /// step size never increases on them.
+    fn set_hyperspeed(&mut self, hyperspeed: bool) {
+        if hyperspeed {
+            self.hyperspeed_exponent = Some(0);
+            self.reset_hyperspeed();
+        } else {
+            self.hyperspeed_exponent = None;
+        }
+    }
+    fn reset_hyperspeed(&mut self) {
+        if self.hyperspeed_exponent.is_some() {
+            self.hyperspeed_exponent = Some(0);
+            // Keep cached results for the old step size; they will be needed
+            // again once the step size has doubled back up to it.
+            self.step_size = BigInt::one();
+        }
+    }
+
+    fn get_background(&self) -> C {
+        self.phases[self.phase].background
+    }
+    fn set_generation_count(&mut self, generations: &BigInt) {
+        self.phase = if generations.is_positive() {
+            self.phases.after(0, generations)
+        } else {
+            0
+        };
+    }
+
+    fn get_topology(&self) -> &Topology<D> {
+        &self.topology
+    }
+    fn set_topology(&mut self, topology: Topology<D>) -> Result<(), String> {
+        self.phases.check_topology(&*self.rule, &topology)?;
+        self.step_size = topology.clamp_step_size(self.step_size.clone());
+        self.topology = topology;
+        Ok(())
+    }
+
+    fn get_memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+    fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.memory_budget = memory_budget;
+    }
+    fn get_cache_stats(&self) -> CacheStats {
+        *self.results.stats.lock().unwrap()
+    }
+    /// Evicts every cached result except those for nodes reachable from the
+    /// root of the given NdTree or one of the roots passed to
+    /// `set_gc_roots()`.
+    ///
+    /// This happens automatically whenever the caches use more memory than the
+    /// budget allows, which is checked after each step and from time to time
+    /// during a step. As in Golly, a node is reachable if it is part of one of
+    /// those patterns or part of a cached result for a reachable node (so that
+    /// future generations of the patterns stay cached). If that still uses
+    /// more memory than the budget allows, then results are only kept for
+    /// nodes in the patterns themselves. Any node that is not kept (and is not
+    /// held onto by another NdTree, or by a step in progress) is then freed.
+    fn collect_garbage(&mut self, tree: &NdTree<C, D>) {
+        self.results.collect_garbage(
+            &self.gc_roots_with(tree.get_root()),
+            &tree.cache,
+            self.memory_budget,
+        );
+    }
+    fn set_gc_roots(&mut self, roots: &[GcRoot]) {
+        self.gc_roots = roots.iter().filter_map(GcRoot::get).collect();
+    }
+
+    fn get_thread_count(&self) -> usize {
+        self.thread_count
+    }
+    fn set_thread_count(&mut self, thread_count: usize) {
+        self.thread_count = thread_count.max(1);
+    }
+
+    fn try_step_single(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.advance(tree, &BigInt::one(), cancellation, progress)
+    }
+    /// Advances the given NdTree by a number of generations equal to this
+    /// simulation's step size, unless the step is cancelled using the given
+    /// token.
+    ///
+    /// Each time one of the top-level sub-nodes of the step is finished,
+    /// `progress` is called with the fraction of the step that has been
+    /// completed. Results that were finished before the step was cancelled
+    /// stay in the cache, so they don't need to be computed again.
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        let step_size = self.step_size.clone();
+        let previous_cache_misses = self.cache_misses;
+        self.advance(tree, &step_size, cancellation, progress)?;
+        if let Some(exponent) = self.hyperspeed_exponent {
+            if self.topology.is_unbounded() && self.cache_misses <= previous_cache_misses {
+                self.hyperspeed_exponent = Some(exponent + 1);
+                self.step_size <<= 1;
+            }
+        }
+        Ok(())
+    }
+}
+impl<C: CellType, D: Dim> HashLifeSimulator<C, D> {
+    /// Returns the given node along with the roots passed to `set_gc_roots()`.
+    fn gc_roots_with(&self, root: &NdCachedNode<C, D>) -> Vec<NdCachedNode<C, D>> {
+        std::iter::once(root.clone())
+            .chain(self.gc_roots.iter().cloned())
+            .collect()
+    }
+
+    /// Updates the cache statistics and collects garbage if the caches are
+    /// using more memory than the budget allows, keeping the results for
+    /// nodes reachable from the given roots.
+    fn enforce_memory_budget(&self, cache: &NdTreeCache<C, D>, roots: &[NdCachedNode<C, D>]) {
+        let stats = self.results.update_stats(cache.node_count());
+        if stats.memory_usage > self.memory_budget {
+            self.results
+                .collect_garbage(roots, cache, self.memory_budget);
+        }
+    }
+
+    /// Advances the given NdTree by the given number of generations on this
+    /// simulation's topology, or restores it if the step is cancelled.
+    fn advance(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        generations: &BigInt,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        // Cloning an NdTree is cheap, because it only holds onto the root.
+        let original_tree = tree.clone();
+        let original_phase = self.phase;
+        let insertions_before = self.results.insertions.load(Ordering::Relaxed);
+        let result = if self.topology.is_unbounded() {
+            self.advance_unbounded(tree, generations, cancellation, progress)
+        } else {
+            self.advance_bounded(tree, generations, cancellation, progress)
+        };
+        match result {
+            Ok(()) => {
+                self.cache_misses =
+                    self.results.insertions.load(Ordering::Relaxed) - insertions_before;
+            }
+            Err(StepCancelled) => {
+                *tree = original_tree;
+                self.phase = original_phase;
+            }
+        }
+        self.enforce_memory_budget(&tree.cache, &self.gc_roots_with(tree.get_root()));
+        result
+    }
+
+    /// Advances the given NdTree by the given number of generations on this
+    /// simulation's topology, which must be bounded along some axis.
+    fn advance_bounded(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        generations: &BigInt,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        // HashLife can only simulate an unbounded grid, so simulate one
+        // generation at a time, copying cells across the edges of the grid
+        // before each generation and removing the cells that end up outside
+        // the grid afterwards. (Dead cells are stored as the background
+        // state.)
+        let total = generations.to_f64().unwrap_or(f64::INFINITY);
+        for generation in num::range(BigInt::zero(), generations.clone()) {
+            let completed = generation.to_f64().unwrap_or(f64::INFINITY);
+            self.topology.clear_outside(tree);
+            self.topology
+                .add_border_cells(tree, self.rule.radius(), self.get_background());
+            self.advance_unbounded(tree, &BigInt::one(), cancellation, &|fraction| {
+                progress((completed + fraction) / total)
+            })?;
+        }
+        self.topology.clear_outside(tree);
+        tree.shrink();
+        Ok(())
+    }
+
+    /// Advances the given NdTree by the given number of generations, as if the
+    /// grid were unbounded.
+    fn advance_unbounded(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        generations: &BigInt,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        // Expand out to the sphere of influence of the existing pattern,
+        // following `expansion_distance >= r * t`.
+        let min_expansion_distance = BigInt::from(1)
+            << (ceil_log_base_2(self.rule.radius()) + ceil_log_base_2_bigint(generations));
+        let mut expansion_distance = BigInt::from(0);
+        while expansion_distance < min_expansion_distance {
+            tree.expand();
+            expansion_distance += tree.get_root().len() / 4;
+        }
+        // Now expand one more layer to guarantee that the sphere of influence
+        // is within the inner node, because HashLifeSimulator::advance_inner_node()
+        // must always returns a node one layer lower than its input. (This also
+        // ensures that we aren't somehow still at layer 1; we need to be at at
+        // least layer 2 so that the result can be at layer 1, which is the
+        // minimum layer for a node.)
+        tree.expand();
+        // Now do the actual simulation.
+        let new_node = self.advance_inner_node(
+            &tree.cache,
+            &tree.slice.root,
+            generations,
+            self.phase,
+            &StepControl {
+                threads: self.thread_count,
+                cancellation,
+                progress: Some(progress),
+                gc_roots: &self.gc_roots_with(&tree.slice.root),
+            },
+        )?;
+        self.phase = self.phases.after(self.phase, generations);
+        tree.set_root_centered(new_node);
+        // Shrink the tree as much as possible to avoid wasted space.
+        tree.shrink();
+        Ok(())
+    }
+
+    /// Computes the inner node for a given node after the given numebr of
+    /// generations.
+    ///
+    /// A node's inner node is the node one layer down, centered on the original
+    /// node. For example, the inner node of a 16x16 node (layer 4) is the 8x8
+    /// node (layer 3) centered on it. HashLife always performs calculations
+    /// like this; a node is progressed some distance into the future, and the
+    /// state of its inner node is the result. This is because without outside
+    /// information, it is impossible to predict the state of the entire node
+    /// (since adjacent cells outside of the node could affect it), but it is
+    /// always possible in theory to predict the inner node of a node with
+    /// length `n` after `t` generations using a rule with max neighborhood
+    /// radius `r` if `n / 4 >= r * t`. (`r` defines the maximum speed that
+    /// information can travel, so `r * t` is the distance that information can
+    /// travel in time `t`, and `n / 4` is the distance from any edge of the
+    /// inner node to the edge of the outer node.) In practice, however, each
+    /// layer must be computed separately, so the `r` and `t` must each be
+    /// replaced with their next lowest power of two.
+    ///
+    /// `phase` is the index of the phase of the first generation to simulate,
+    /// which gives the state of the background that cells are stored relative
+    /// to (see `Simulator::get_background()`) and whether the generation is
+    /// odd. Block rules rely on every node at layer 1 or above starting at even
+    /// coordinates so that the partition into blocks is the same everywhere
+    /// that a node appears. (This is always true, because the root node is
+    /// kept centered on the origin.)
+    ///
+    /// `control` gives the maximum number of threads to use, and allows the
+    /// computation to be cancelled. Progress is only reported for the
+    /// top-level node. The memory budget is checked after each of the
+    /// top-level node's branches is finished and whenever enough new results
+    /// have been cached, and garbage is collected if needed. This is safe in
+    /// the middle of a step, because the nodes being simulated are held onto
+    /// until they are finished.
+    fn advance_inner_node(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        node: &NdCachedNode<C, D>,
+        generations: &BigInt,
+        phase: usize,
+        control: &StepControl<'_, C, D>,
+    ) -> Result<NdCachedNode<C, D>, StepCancelled> {
+        // Handle the simplest case of just not simulating anything. This is one
+        // of the recursive base cases.
+        if generations.is_zero() {
+            return Ok(node.get_inner_node(cache));
+        }
+
+        // If the result is already in the cache, just return that.
+        if let Some(result) = self.results.get_result(node, generations, phase) {
+            return Ok(result);
+        }
+
+        if control.cancellation.is_cancelled() {
+            return Err(StepCancelled);
+        }
+
+        // Otherwise make sure we're above the minimum layer.
+        assert!(
+            node.layer >= self.min_layer,
+            "Cannot advance inner node at layer below minimum simulation layer"
+        );
+
+        let ret;
+
+        // If this is the minimum layer, just compute each cell manually. This
+        // is the other recursive base case.
+        if node.layer == self.min_layer {
+            assert!(
+                generations.is_one(),
+                "Cannot simulate more than 1 generation at minimum layer"
+            );
+            let Phase {
+                background: old_background,
+                odd_generation,
+            } = self.phases[phase];
+            let new_background = self.phases[self.phases.after(phase, &BigInt::one())].background;
+            let mut old_cell_ndarray = NdArray::from(node);
+            if old_background != C::default() {
+                old_cell_ndarray =
+                    old_cell_ndarray.map(|cell| swap_background(cell, old_background));
+            }
+            let base_offset = 1 << (node.layer - 2);
+            ret = match self.rule.as_block_rule() {
+                Some(block_rule) => {
+                    cache.get_small_node_from_cell_fn(node.layer - 1, NdVec::origin(), &|pos| {
+                        // Find the lowest corner of the block containing this
+                        // cell, then compute the whole block.
+                        let pos = pos + base_offset;
+                        let block_corner = IVec::<D>::from_fn(|ax| {
+                            pos[ax] - (pos[ax] - odd_generation as isize).rem_euclid(2)
+                        });
+                        let mut block: Vec<C> = (0..D::TREE_BRANCHES)
+                            .map(|idx| {
+                                old_cell_ndarray[&(block_corner.clone() + block_offset(idx))]
+                            })
+                            .collect();
+                        block_rule.transition_block(&mut block, odd_generation);
+                        swap_background(block[block_index(&(pos - block_corner))], new_background)
+                    })
+                }
+                None => {
+                    cache.get_small_node_from_cell_fn(node.layer - 1, NdVec::origin(), &|pos| {
+                        let slice = old_cell_ndarray.offset_slice(-&pos - base_offset);
+                        swap_background(self.rule.transition(&slice), new_background)
+                    })
+                }
+            };
+        } else {
+            // In the algorithm described below, there are two `t/2`s that must
+            // add up to `t` (where `t` is the number of generations to
+            // simulate). But of course if `t` is odd, then this may not be the
+            // case. It hardly matters whether `t_outer` or `t_inner` is larger,
+            // as long as they differ by no more than `1` and they add up to
+            // `t`.
+            let t_inner: BigInt = generations / 2;
+            let t_outer: BigInt = generations - &t_inner;
+
+            // Let `L` be the layer of the current node, and let `t` be the
+            // number of generations to simulate. Colors refer to Figure 4 in
+            // this article: https://www.drdobbs.com/jvm/_/184406478.
+            let completed_branches = AtomicUsize::new(0);
+            let compute_final_branch = |final_branch_idx: ByteVec<D>, threads: usize| {
+                let branch_control = StepControl {
+                    threads,
+                    cancellation: control.cancellation,
+                    progress: None,
+                    gc_roots: control.gc_roots,
+                };
+                let node_halfway = cache.try_get_node_from_fn(|cache, inner_branch_idx| {
+                    let node_intial = cache.get_node_from_fn(|_cache, outer_branch_idx| {
+                        // 1. Grab sub-branches at layer `L-2` of the original
+                        //    node at time `0`.
+                        node.get_sub_branch(
+                            final_branch_idx.clone() + inner_branch_idx.clone() + outer_branch_idx,
+                        )
+                        .clone()
+                        // 2. Use these branches to make a node at layer `L-1` and
+                        //    time `0`.
+                    });
+                    // 3. Simulate that node to get a new node at layer `L-2`
+                    //    and time `t/2` (red squares).
+                    Ok(NdTreeBranch::Node(self.advance_inner_node(
+                        cache,
+                        &node_intial,
+                        &t_outer,
+                        phase,
+                        &branch_control,
+                    )?))
+                    // 4. Using branches from step #3, create a node at layer
+                    //    `L-1` and time `t/2`.
+                })?;
+                // 5. Simulate that node to get a new node at layer `L-2` and
+                //    time `t` (green squares).
+                let final_branch = NdTreeBranch::Node(self.advance_inner_node(
+                    cache,
+                    &node_halfway,
+                    &t_inner,
+                    self.phases.after(phase, &t_outer),
+                    &branch_control,
+                )?);
+                // 6. Using branches from step #5, create a new node at layer
+                //    `L-1` and time `t` (blue square). This is the final
+                //    result.
+                if let Some(progress) = control.progress {
+                    let completed = completed_branches.fetch_add(1, Ordering::Relaxed) + 1;
+                    progress(completed as f64 / D::TREE_BRANCHES as f64);
+                    self.enforce_memory_budget(cache, control.gc_roots);
+                }
+                Ok(final_branch)
+            };
+            let threads = control.threads;
+            ret = if threads > 1 && node.layer >= self.min_layer + MIN_PARALLEL_LAYERS {
+                // Each branch of the result is independent of the others, so
+                // split them up between threads, and split up the remaining
+                // threads between the branches for the layers below.
+                let branches_per_thread = D::TREE_BRANCHES.div_ceil(threads);
+                let threads_per_branch = threads.div_ceil(D::TREE_BRANCHES);
+                let branch_indices: Vec<usize> = (0..D::TREE_BRANCHES).collect();
+                let branches = std::thread::scope(|scope| {
+                    let handles: Vec<_> = branch_indices
+                        .chunks(branches_per_thread)
+                        .map(|chunk| {
+                            let compute_final_branch = &compute_final_branch;
+                            scope.spawn(move || {
+                                chunk
+                                    .iter()
+                                    .map(|&i| {
+                                        compute_final_branch(
+                                            ByteVec::from_array_idx(i),
+                                            threads_per_branch,
+                                        )
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .flat_map(|handle| handle.join().unwrap())
+                        .collect::<Result<_, _>>()
+                });
+                cache.get_node(branches?)
+            } else {
+                cache.try_get_node_from_fn(|_cache, final_branch_idx| {
+                    compute_final_branch(final_branch_idx, 1)
+                })?
+            };
+        }
+
+        // Add the result to the cache so we don't have to do all that work next
+        // time.
+        let insertions = self
+            .results
+            .set_result(node.clone(), generations, phase, ret.clone());
+        if insertions.is_multiple_of(GC_CHECK_INTERVAL) {
+            self.enforce_memory_budget(cache, control.gc_roots);
+        }
+        Ok(ret)
+    }
+}
+
+/// Options for simulating a node that are the same for all of its sub-nodes.
+struct StepControl<'a, C: CellType, D: Dim> {
+    /// The maximum number of threads to use.
+    threads: usize,
+    /// A token that is checked before simulating each node.
+    cancellation: &'a CancellationToken,
+    /// A function to call with the fraction of the node's branches that have
+    /// been finished.
+    progress: Option<&'a (dyn Fn(f64) + Sync)>,
+    /// The nodes whose results are kept if garbage is collected during the
+    /// step, starting with the node being simulated.
+    gc_roots: &'a [NdCachedNode<C, D>],
+}
+
+/// A cache of simulation results for a variety of step sizes, with a separate
+/// cache for each phase.
+///
+/// The cache is split into shards by node hash code, each with its own lock,
+/// so that it can be used by many threads at once.
+#[derive(Debug)]
+struct ResultsCache<C: CellType, D: Dim> {
+    shards: Vec<Mutex<ResultsCacheShard<C, D>>>,
+    /// The number of results that have been added to the cache.
+    insertions: AtomicUsize,
+    stats: Mutex<CacheStats>,
+    /// Held while collecting garbage, so that only one thread does it at a
+    /// time.
+    gc_lock: Mutex<()>,
+}
+type ResultsCacheShard<C, D> = HashMap<BigInt, Vec<SingleStepResultsCache<C, D>>, NodeHasher>;
+impl<C: CellType, D: Dim> Default for ResultsCache<C, D> {
+    fn default() -> Self {
+        Self {
+            shards: (0..RESULTS_CACHE_SHARDS)
+                .map(|_| Mutex::new(HashMap::default()))
+                .collect(),
+            insertions: AtomicUsize::new(0),
+            stats: Mutex::new(CacheStats::default()),
+            gc_lock: Mutex::new(()),
+        }
+    }
+}
+impl<C: CellType, D: Dim> ResultsCache<C, D> {
+    /// Returns the shard that holds results for the given node.
+    fn shard(&self, node: &NdCachedNode<C, D>) -> &Mutex<ResultsCacheShard<C, D>> {
+        &self.shards[node.hash_code as usize % RESULTS_CACHE_SHARDS]
+    }
+
+    fn get_result(
+        &self,
+        node: &NdCachedNode<C, D>,
+        step_size: &BigInt,
+        phase: usize,
+    ) -> Option<NdCachedNode<C, D>> {
+        self.shard(node)
+            .lock()
+            .unwrap()
+            .get(step_size)
+            .and_then(|phase_caches| phase_caches.get(phase))
+            .and_then(|single_step_cache| single_step_cache.get_result(node))
+            .cloned()
+    }
+    /// Caches a result, returning the number of results that have been added
+    /// to the cache so far.
+    fn set_result(
+        &self,
+        node: NdCachedNode<C, D>,
+        step_size: &BigInt,
+        phase: usize,
+        result: NdCachedNode<C, D>,
+    ) -> usize {
+        let mut shard = self.shard(&node).lock().unwrap();
+        if !shard.contains_key(step_size) {
+            shard.insert(step_size.clone(), vec![]);
+        }
+        let phase_caches = shard.get_mut(step_size).unwrap();
+        if phase_caches.len() <= phase {
+            phase_caches.resize_with(phase + 1, SingleStepResultsCache::default);
+        }
+        // Another thread may have computed the same result in the meantime.
+        if phase_caches[phase].set_result(node, result) {
+            self.insertions.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            self.insertions.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Evicts every result except those for nodes reachable from the given
+    /// roots (see `HashLifeSimulator::collect_garbage()`), unless another
+    /// thread is already doing so.
+    fn collect_garbage(
+        &self,
+        roots: &[NdCachedNode<C, D>],
+        node_cache: &NdTreeCache<C, D>,
+        memory_budget: usize,
+    ) {
+        let _gc_guard = match self.gc_lock.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        self.retain_reachable_from(roots, true);
+        let stats = self.update_stats(node_cache.node_count());
+        if stats.memory_usage > memory_budget {
+            self.retain_reachable_from(roots, false);
+            self.update_stats(node_cache.node_count());
+        }
+        self.stats.lock().unwrap().gc_count += 1;
+    }
+
+    /// Removes every result for a node that is not reachable from one of the
+    /// given roots, optionally following cached results as well as branches.
+    fn retain_reachable_from(&self, roots: &[NdCachedNode<C, D>], follow_results: bool) {
+        let mut reachable = HashSet::new();
+        let mut stack = roots.to_vec();
+        while let Some(node) = stack.pop() {
+            if reachable.insert(Arc::as_ptr(&node)) {
+                for branch in &node.branches {
+                    if let NdTreeBranch::Node(child) = branch {
+                        stack.push(child.clone());
+                    }
+                }
+                if follow_results {
+                    let shard = self.shard(&node).lock().unwrap();
+                    for single_step_cache in shard.values().flatten() {
+                        if let Some(result) = single_step_cache.get_result(&node) {
+                            stack.push(result.clone());
+                        }
+                    }
+                }
+            }
+        }
+        for shard in &self.shards {
+            let mut shard = shard.lock().unwrap();
+            for phase_caches in shard.values_mut() {
+                for single_step_cache in phase_caches {
+                    single_step_cache
+                        .0
+                        .retain(|node, _| reachable.contains(&Arc::as_ptr(node)));
+                }
+            }
+            shard.retain(|_, phase_caches| {
+                phase_caches
+                    .iter()
+                    .any(|single_step_cache| !single_step_cache.0.is_empty())
+            });
+        }
+    }
+
+    /// Updates the statistics given the number of nodes in the node cache, and
+    /// returns the new statistics.
+    fn update_stats(&self, node_count: usize) -> CacheStats {
+        let result_count = self
+            .shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .flatten()
+                    .map(|single_step_cache| single_step_cache.0.len())
+                    .sum::<usize>()
+            })
+            .sum();
+        let mut stats = self.stats.lock().unwrap();
+        stats.set_counts::<C, D>(node_count, result_count);
+        *stats
+    }
+}
+
+/// A cache of simulation results for a given step size and phase.
+#[derive(Debug, Default, Clone)]
+struct SingleStepResultsCache<C: CellType, D: Dim>(
+    HashMap<NdCachedNode<C, D>, NdCachedNode<C, D>, NodeHasher>,
+);
+impl<C: CellType, D: Dim> SingleStepResultsCache<C, D> {
+    fn get_result(&self, node: &NdCachedNode<C, D>) -> Option<&NdCachedNode<C, D>> {
+        self.0.get(node)
+    }
+    /// Caches a result, returning true if there was no result for the node.
+    fn set_result(&mut self, node: NdCachedNode<C, D>, result: NdCachedNode<C, D>) -> bool {
+        self.0.insert(node, result).is_none()
+    }
+}
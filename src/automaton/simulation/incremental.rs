@@ -0,0 +1,518 @@
+//! Simulation one generation at a time, without caching results.
+
+use num::{BigInt, One, Signed, ToPrimitive, Zero};
+use std::sync::Arc;
+
+use super::super::rule::{block_index, block_offset};
+use super::*;
+
+/// The lowest layer of the nodes whose cells are computed directly.
+const MIN_TILE_LAYER: usize = 2;
+
+/// A simulation that computes one generation at a time, only recomputing the
+/// parts of the pattern that have changed recently.
+///
+/// The grid is divided into "tiles" (nodes at a fixed layer), and each tile is
+/// recomputed from the tiles around it. If a node and every node around it are
+/// the same as they were before the last generation with the same phase, then
+/// the node will be the same as it was after that generation, so it isn't
+/// recomputed. Since nodes are hash-consed, this can skip large still or
+/// periodic parts of the pattern at once.
+///
+/// Unlike HashLife, no results are cached apart from the last generation, so
+/// chaotic patterns (such as large soups) that HashLife can't find much
+/// repetition in are often faster this way. Only a single thread is used.
+#[derive(Debug, Clone)]
+pub struct IncrementalSimulator<C: CellType, D: Dim> {
+    rule: Arc<dyn Rule<C, D>>,
+    step_size: BigInt,
+    /// Layer of the tiles whose cells are computed directly.
+    tile_layer: usize,
+    /// Number of cells on each side of a tile that are needed to compute it.
+    margin: usize,
+    /// Every phase that the simulation can be in.
+    phases: Phases<C>,
+    /// The index of the phase of the next generation to be simulated.
+    phase: usize,
+    topology: Topology<D>,
+    /// Whether hyperspeed is enabled.
+    hyperspeed: bool,
+    memory_budget: usize,
+    thread_count: usize,
+    /// The most recent generation simulated in each phase, which parts of the
+    /// next generation with the same phase can be copied from.
+    history: Vec<Generation<C, D>>,
+    stats: CacheStats,
+}
+impl<C: CellType, D: Dim> Default for IncrementalSimulator<C, D> {
+    fn default() -> Self {
+        Self::new(Arc::new(DummyRule), BigInt::one())
+    }
+}
+
+impl<C: CellType, D: Dim> IncrementalSimulator<C, D> {
+    /// Constructs a new IncrementalSimulator with the given rule and step
+    /// size.
+    pub fn new(rule: Arc<dyn Rule<C, D>>, step_size: BigInt) -> Self {
+        // Block rules only need the cells in the same block, but the margin
+        // must be even so that it doesn't change the partition into blocks.
+        let margin = match rule.as_block_rule() {
+            Some(_) => 2,
+            None => rule.radius(),
+        };
+        // Each tile can only depend on the tiles next to it.
+        let mut tile_layer = MIN_TILE_LAYER;
+        while (1 << tile_layer) < margin {
+            tile_layer += 1;
+        }
+
+        let phases = Phases::new(&*rule);
+
+        Self {
+            rule,
+            step_size,
+            tile_layer,
+            margin,
+            phases,
+            phase: 0,
+            topology: Topology::default(),
+            hyperspeed: false,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            thread_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            history: vec![],
+            stats: CacheStats::default(),
+        }
+    }
+}
+impl<C: CellType, D: Dim> Simulator<C, D> for IncrementalSimulator<C, D> {
+    fn get_algorithm(&self) -> Algorithm {
+        Algorithm::Incremental
+    }
+    fn clone_box(&self) -> Box<dyn Simulator<C, D>> {
+        Box::new(self.clone())
+    }
+
+    fn get_rule(&self) -> &Arc<dyn Rule<C, D>> {
+        &self.rule
+    }
+
+    fn get_step_size(&self) -> &BigInt {
+        &self.step_size
+    }
+    fn set_step_size(&mut self, new_step_size: BigInt) {
+        self.hyperspeed = false;
+        self.step_size = self.topology.clamp_step_size(new_step_size);
+    }
+
+    fn get_hyperspeed_exponent(&self) -> Option<usize> {
+        if self.hyperspeed {
+            Some(0)
+        } else {
+            None
+        }
+    }
+    /// Enables or disables hyperspeed.
+    ///
+    /// Every generation takes about as long to simulate as the one before, so
+    /// the step size never increases in hyperspeed mode; it stays at 1.
+    fn set_hyperspeed(&mut self, hyperspeed: bool) {
+        self.hyperspeed = hyperspeed;
+        self.reset_hyperspeed();
+    }
+    fn reset_hyperspeed(&mut self) {
+        if self.hyperspeed {
+            self.step_size = BigInt::one();
+        }
+    }
+
+    fn get_background(&self) -> C {
+        self.phases[self.phase].background
+    }
+    fn set_generation_count(&mut self, generations: &BigInt) {
+        self.phase = if generations.is_positive() {
+            self.phases.after(0, generations)
+        } else {
+            0
+        };
+    }
+
+    fn get_topology(&self) -> &Topology<D> {
+        &self.topology
+    }
+    fn set_topology(&mut self, topology: Topology<D>) -> Result<(), String> {
+        self.phases.check_topology(&*self.rule, &topology)?;
+        self.step_size = topology.clamp_step_size(self.step_size.clone());
+        self.topology = topology;
+        Ok(())
+    }
+
+    fn get_memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+    fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.memory_budget = memory_budget;
+    }
+    fn get_cache_stats(&self) -> CacheStats {
+        self.stats
+    }
+    /// Forgets the previous generations, so that the nodes in them can be
+    /// freed.
+    ///
+    /// This happens automatically after any step that leaves the node cache
+    /// using more memory than the budget allows.
+    fn collect_garbage(&mut self, tree: &NdTree<C, D>) {
+        self.history.clear();
+        self.stats.set_counts::<C, D>(tree.cache.node_count(), 0);
+        self.stats.gc_count += 1;
+    }
+    /// Does nothing, because this algorithm only caches the previous
+    /// generations of the pattern being simulated.
+    fn set_gc_roots(&mut self, _roots: &[GcRoot]) {}
+
+    /// Returns the maximum number of threads to use for simulation.
+    ///
+    /// This is only kept so that it can be passed on when switching to another
+    /// algorithm, since this one only uses a single thread.
+    fn get_thread_count(&self) -> usize {
+        self.thread_count
+    }
+    fn set_thread_count(&mut self, thread_count: usize) {
+        self.thread_count = thread_count.max(1);
+    }
+
+    fn try_step_single(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.advance(tree, &BigInt::one(), cancellation, progress)
+    }
+    /// Advances the given NdTree by a number of generations equal to this
+    /// simulation's step size, unless the step is cancelled using the given
+    /// token.
+    ///
+    /// `progress` is called after each generation, and after each top-level
+    /// sub-node of each generation.
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        let step_size = self.step_size.clone();
+        self.advance(tree, &step_size, cancellation, progress)
+    }
+}
+impl<C: CellType, D: Dim> IncrementalSimulator<C, D> {
+    /// Advances the given NdTree by the given number of generations on this
+    /// simulation's topology, or restores it if the step is cancelled.
+    fn advance(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        generations: &BigInt,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        // Cloning an NdTree is cheap, because it only holds onto the root.
+        let original_tree = tree.clone();
+        let original_phase = self.phase;
+        let total = generations.to_f64().unwrap_or(f64::INFINITY);
+        let mut result = Ok(());
+        for generation in num::range(BigInt::zero(), generations.clone()) {
+            let completed = generation.to_f64().unwrap_or(f64::INFINITY);
+            if !self.topology.is_unbounded() {
+                // Copy cells across the edges of the grid before each
+                // generation, as HashLife does.
+                self.topology.clear_outside(tree);
+                self.topology
+                    .add_border_cells(tree, self.rule.radius(), self.get_background());
+            }
+            result = self.advance_generation(tree, cancellation, &|fraction| {
+                progress((completed + fraction) / total)
+            });
+            if result.is_err() {
+                break;
+            }
+        }
+        match result {
+            Ok(()) => {
+                self.topology.clear_outside(tree);
+                tree.shrink();
+            }
+            Err(StepCancelled) => {
+                *tree = original_tree;
+                self.phase = original_phase;
+            }
+        }
+        self.stats.set_counts::<C, D>(tree.cache.node_count(), 0);
+        if self.stats.memory_usage > self.memory_budget {
+            self.collect_garbage(tree);
+        }
+        result
+    }
+
+    /// Advances the given NdTree by a single generation, as if the grid were
+    /// unbounded.
+    fn advance_generation(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        // Expand the tree so that the pattern is far enough from the edges
+        // that nothing outside the root node can change. This also makes sure
+        // that the root node is above the tile layer.
+        tree.expand();
+        while tree.get_root().layer < self.tile_layer + 2 {
+            tree.expand();
+        }
+        let phase = self.phases[self.phase];
+        // Find the last generation with the same phase, and expand it or the
+        // tree so that their roots cover the same cells. (The root node is
+        // always centered on the origin.)
+        self.history
+            .retain(|generation| Arc::ptr_eq(&generation.before.cache, &tree.cache));
+        let previous = self
+            .history
+            .iter()
+            .find(|generation| generation.phase == phase)
+            .map(|generation| {
+                let mut before = generation.before.clone();
+                let mut after = generation.before.clone();
+                after.set_root(generation.after.clone());
+                while tree.get_root().layer < before.get_root().layer {
+                    tree.expand();
+                }
+                while before.get_root().layer < tree.get_root().layer {
+                    before.expand();
+                    after.expand();
+                }
+                (before, after)
+            });
+
+        let cache = &tree.cache;
+        let neighborhood = Neighborhood::around(cache, tree.get_root());
+        let previous_neighborhood = previous
+            .as_ref()
+            .map(|(before, _)| Neighborhood::around(cache, before.get_root()));
+        let new_root = self.advance_node(
+            cache,
+            &neighborhood,
+            previous_neighborhood
+                .as_ref()
+                .zip(previous.as_ref())
+                .map(|(neighborhood, (_, after))| (neighborhood, after.get_root())),
+            cancellation,
+            Some(progress),
+        )?;
+
+        self.history.retain(|generation| generation.phase != phase);
+        self.history.push(Generation {
+            phase,
+            before: tree.clone(),
+            after: new_root.clone(),
+        });
+        self.phase = self.phases.after(self.phase, &BigInt::one());
+        // The new root covers the same cells as the old one.
+        tree.set_root(new_root);
+        tree.shrink();
+        Ok(())
+    }
+
+    /// Computes the center node of the given neighborhood after one
+    /// generation, given the same neighborhood before the last generation with
+    /// the same phase and the center node after it, if there was one.
+    ///
+    /// `progress` is called with the fraction of the node's branches that have
+    /// been finished, if it is given.
+    fn advance_node(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        neighborhood: &Neighborhood<C, D>,
+        previous: Option<PreviousNode<C, D>>,
+        cancellation: &CancellationToken,
+        progress: Option<&(dyn Fn(f64) + Sync)>,
+    ) -> Result<NdCachedNode<C, D>, StepCancelled> {
+        let layer = neighborhood.center().layer;
+        // Empty space stays empty, since cells are stored relative to the
+        // background.
+        if neighborhood.is_empty() {
+            return Ok(cache.get_empty_node(layer));
+        }
+        // If nothing in the neighborhood has changed, then neither has the
+        // result.
+        if let Some((previous_neighborhood, previous_result)) = previous {
+            if neighborhood.is_same_as(previous_neighborhood) {
+                return Ok(previous_result.clone());
+            }
+        }
+
+        if cancellation.is_cancelled() {
+            return Err(StepCancelled);
+        }
+
+        if layer == self.tile_layer {
+            return Ok(self.advance_tile(cache, neighborhood));
+        }
+
+        let mut completed_branches = 0;
+        cache.try_get_node_from_fn(|cache, branch_idx| {
+            let previous_branch = previous.map(|(previous_neighborhood, previous_result)| {
+                (
+                    previous_neighborhood.branch(branch_idx.clone()),
+                    previous_result[branch_idx.clone()].node().unwrap(),
+                )
+            });
+            let ret = self.advance_node(
+                cache,
+                &neighborhood.branch(branch_idx),
+                previous_branch
+                    .as_ref()
+                    .map(|(neighborhood, result)| (neighborhood, *result)),
+                cancellation,
+                None,
+            )?;
+            if let Some(progress) = progress {
+                completed_branches += 1;
+                progress(completed_branches as f64 / D::TREE_BRANCHES as f64);
+            }
+            Ok(NdTreeBranch::Node(ret))
+        })
+    }
+
+    /// Computes the center node of the given neighborhood of tiles after one
+    /// generation, by computing each cell directly.
+    fn advance_tile(
+        &self,
+        cache: &NdTreeCache<C, D>,
+        neighborhood: &Neighborhood<C, D>,
+    ) -> NdCachedNode<C, D> {
+        let Phase {
+            background: old_background,
+            odd_generation,
+        } = self.phases[self.phase];
+        let new_background = self.phases[self.phases.after(self.phase, &BigInt::one())].background;
+        let tile_len = 1 << self.tile_layer;
+        let margin = self.margin as isize;
+        // Gather the cells in the tile and the margin around it. The lower
+        // corner of the tile is at `margin` along each axis.
+        let rect = IRect::<D>::new(NdVec::repeat(-margin), NdVec::repeat(tile_len + 2 * margin));
+        let cells = rect
+            .iter()
+            .map(|pos| {
+                let neighbor = IVec::from_fn(|ax| pos[ax].div_euclid(tile_len) + 1);
+                let cell = neighborhood
+                    .get(&neighbor)
+                    .get_cell(&IVec::from_fn(|ax| pos[ax].rem_euclid(tile_len)));
+                swap_background(cell, old_background)
+            })
+            .collect();
+        let old_cell_ndarray = NdArray::from_flat_vec(rect.size().as_uvec(), cells);
+        match self.rule.as_block_rule() {
+            Some(block_rule) => {
+                cache.get_small_node_from_cell_fn(self.tile_layer, NdVec::origin(), &|pos| {
+                    // Find the lowest corner of the block containing this
+                    // cell, then compute the whole block. (The margin is even,
+                    // so this is the same partition as in the whole grid.)
+                    let pos = pos + margin;
+                    let block_corner = IVec::<D>::from_fn(|ax| {
+                        pos[ax] - (pos[ax] - odd_generation as isize).rem_euclid(2)
+                    });
+                    let mut block: Vec<C> = (0..D::TREE_BRANCHES)
+                        .map(|idx| old_cell_ndarray[&(block_corner.clone() + block_offset(idx))])
+                        .collect();
+                    block_rule.transition_block(&mut block, odd_generation);
+                    swap_background(block[block_index(&(pos - block_corner))], new_background)
+                })
+            }
+            None => cache.get_small_node_from_cell_fn(self.tile_layer, NdVec::origin(), &|pos| {
+                let slice = old_cell_ndarray.offset_slice(-&pos - margin);
+                swap_background(self.rule.transition(&slice), new_background)
+            }),
+        }
+    }
+}
+
+/// A generation that has been simulated.
+#[derive(Debug, Clone)]
+struct Generation<C: CellType, D: Dim> {
+    /// The phase of the generation.
+    phase: Phase<C>,
+    /// The tree before the generation.
+    before: NdTree<C, D>,
+    /// The root node after the generation, which covers the same cells as the
+    /// root node of `before`.
+    after: NdCachedNode<C, D>,
+}
+
+/// The neighborhood of a node before a generation, along with the node after
+/// it.
+type PreviousNode<'a, C, D> = (&'a Neighborhood<C, D>, &'a NdCachedNode<C, D>);
+
+/// A node along with the nodes around it at the same layer, in a grid that is
+/// 3 nodes long along each axis.
+struct Neighborhood<C: CellType, D: Dim>(Vec<NdCachedNode<C, D>>);
+impl<C: CellType, D: Dim> Neighborhood<C, D> {
+    /// Returns the neighborhood of the given node with empty nodes around it.
+    fn around(cache: &NdTreeCache<C, D>, node: &NdCachedNode<C, D>) -> Self {
+        let empty_node = cache.get_empty_node(node.layer);
+        Self(
+            IRect::<D>::new(NdVec::origin(), NdVec::repeat(3_isize))
+                .iter()
+                .map(|pos| {
+                    if pos == NdVec::repeat(1_isize) {
+                        node.clone()
+                    } else {
+                        empty_node.clone()
+                    }
+                })
+                .collect(),
+        )
+    }
+    /// Returns the node at the given position, where each component is 0, 1,
+    /// or 2.
+    fn get(&self, pos: &IVec<D>) -> &NdCachedNode<C, D> {
+        // The X axis varies fastest.
+        let idx = D::axes()
+            .iter()
+            .rev()
+            .fold(0, |idx, &ax| idx * 3 + pos[ax] as usize);
+        &self.0[idx]
+    }
+    /// Returns the node in the center of the neighborhood.
+    fn center(&self) -> &NdCachedNode<C, D> {
+        self.get(&NdVec::repeat(1_isize))
+    }
+    /// Returns true if every node in the neighborhood is empty, or false
+    /// otherwise.
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|node| node.is_empty())
+    }
+    /// Returns true if every node in the neighborhood is the same as the
+    /// corresponding one in the other neighborhood, or false otherwise.
+    fn is_same_as(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .all(|(node, other_node)| Arc::ptr_eq(node, other_node))
+    }
+    /// Returns the neighborhood of the given branch of the center node.
+    fn branch(&self, branch_idx: ByteVec<D>) -> Self {
+        Self(
+            IRect::<D>::new(NdVec::origin(), NdVec::repeat(3_isize))
+                .iter()
+                .map(|pos| {
+                    // Position in units of the branches, from 0 to 5 along
+                    // each axis, where the center node starts at 2.
+                    let pos = IVec::<D>::from_fn(|ax| pos[ax] + 1 + branch_idx[ax] as isize);
+                    self.get(&IVec::from_fn(|ax| pos[ax] / 2))
+                        [ByteVec::from_fn(|ax| (pos[ax] % 2) as u8)]
+                    .node()
+                    .expect("Cannot take neighborhood of node at layer 1")
+                    .clone()
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,511 @@
+//! The functions that apply a rule to each cell in a grid.
+//!
+//! There are several algorithms for this, each implementing Simulator, and
+//! Simulation can hold any of them.
+
+use num::{BigInt, One, ToPrimitive};
+use std::any::Any;
+use std::fmt;
+use std::ops::Index;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod hashlife;
+mod incremental;
+
+use super::*;
+pub use hashlife::HashLifeSimulator;
+pub use incremental::IncrementalSimulator;
+
+/// The default memory budget for the caches used by a simulation, in bytes.
+pub const DEFAULT_MEMORY_BUDGET: usize = 1 << 30;
+
+/// Statistics about the memory used by the caches of a simulation, as of the
+/// most recent step.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of nodes in the node cache.
+    pub node_count: usize,
+    /// Number of simulation results in the results cache.
+    pub result_count: usize,
+    /// Estimated number of bytes used by the nodes and results.
+    pub memory_usage: usize,
+    /// Number of times that cached results have been evicted because the
+    /// memory budget was exceeded.
+    pub gc_count: usize,
+}
+impl CacheStats {
+    /// Sets the number of nodes and results, and estimates the memory used by
+    /// them.
+    fn set_counts<C: CellType, D: Dim>(&mut self, node_count: usize, result_count: usize) {
+        // This is only an estimate, since it ignores unused capacity in the
+        // hash tables and the sizes of populations that don't fit in a single
+        // word.
+        let node_size = std::mem::size_of::<NdTreeNode<C, D>>()
+            + D::TREE_BRANCHES * std::mem::size_of::<NdTreeBranch<C, D>>()
+            // Reference counts and the entry in the node cache
+            + 3 * std::mem::size_of::<usize>();
+        let result_size = 3 * std::mem::size_of::<NdCachedNode<C, D>>();
+        self.node_count = node_count;
+        self.result_count = result_count;
+        self.memory_usage = node_count * node_size + result_count * result_size;
+    }
+}
+
+/// The root node of an NdTree of any cell type and dimensionality, whose nodes
+/// should keep their cached results when garbage is collected (see
+/// `Simulator::set_gc_roots()`).
+#[derive(Clone)]
+pub struct GcRoot(Arc<dyn Any + Send + Sync>);
+impl fmt::Debug for GcRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GcRoot({:p})", Arc::as_ptr(&self.0))
+    }
+}
+impl GcRoot {
+    /// Returns the root of the given NdTree.
+    pub fn new<C: CellType, D: Dim>(tree: &NdTree<C, D>) -> Self {
+        Self(tree.get_root().clone())
+    }
+    /// Returns the root node, or None if it has a different cell type or
+    /// dimensionality.
+    pub fn get<C: CellType, D: Dim>(&self) -> Option<NdCachedNode<C, D>> {
+        self.0.clone().downcast().ok()
+    }
+}
+
+/// A token that can be used to cancel a step from another thread.
+///
+/// Clones of a token share the same state, so cancelling one cancels all of
+/// them.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken(Arc<AtomicBool>);
+impl CancellationToken {
+    /// Returns a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Cancels any step using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    /// Returns true if this token has been cancelled, or false otherwise.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The error returned when a step is cancelled before it finishes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StepCancelled;
+
+/// An algorithm that can be used to simulate an automaton.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Algorithm {
+    /// HashLife, which caches the result of simulating each node so that
+    /// repetitive patterns can be simulated exponentially fast. (See
+    /// `HashLifeSimulator`.)
+    #[default]
+    HashLife,
+    /// Simulation one generation at a time without caching any results, only
+    /// recomputing the parts of the pattern that are changing. (See
+    /// `IncrementalSimulator`.)
+    Incremental,
+}
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::HashLife => write!(f, "HashLife"),
+            Self::Incremental => write!(f, "Incremental"),
+        }
+    }
+}
+impl Algorithm {
+    /// Every algorithm, in the order that they should be listed.
+    pub const ALL: &'static [Self] = &[Self::HashLife, Self::Incremental];
+
+    /// Constructs a new simulator using this algorithm with the given rule and
+    /// step size.
+    pub fn new_simulator<C: CellType, D: Dim>(
+        self,
+        rule: Arc<dyn Rule<C, D>>,
+        step_size: BigInt,
+    ) -> Box<dyn Simulator<C, D>> {
+        match self {
+            Self::HashLife => Box::new(HashLifeSimulator::new(rule, step_size)),
+            Self::Incremental => Box::new(IncrementalSimulator::new(rule, step_size)),
+        }
+    }
+}
+
+/// A method for simulating a rule on an NdTree.
+pub trait Simulator<C: CellType, D: Dim>: fmt::Debug + Send + Sync {
+    /// Returns the algorithm used by this simulator.
+    fn get_algorithm(&self) -> Algorithm;
+    /// Returns a clone of this simulator, which shares any caches with it.
+    fn clone_box(&self) -> Box<dyn Simulator<C, D>>;
+
+    /// Returns the rule used by this simulation.
+    fn get_rule(&self) -> &Arc<dyn Rule<C, D>>;
+
+    /// Returns the step size of this simulation.
+    fn get_step_size(&self) -> &BigInt;
+    /// Sets the step size of this simulation to the given value, disabling
+    /// hyperspeed. On a grid that is bounded along any axis, the step size is
+    /// limited to `MAX_BOUNDED_STEP_SIZE`.
+    fn set_step_size(&mut self, new_step_size: BigInt);
+
+    /// Returns the base-2 logarithm of the step size if hyperspeed is enabled,
+    /// or `None` if it is disabled.
+    fn get_hyperspeed_exponent(&self) -> Option<usize>;
+    /// Enables or disables hyperspeed, in which the step size starts at 1 and
+    /// doubles whenever the simulation can keep up.
+    fn set_hyperspeed(&mut self, hyperspeed: bool);
+    /// Resets the step size to 1 if hyperspeed is enabled, so that it can
+    /// start doubling again (e.g. after the pattern is edited).
+    fn reset_hyperspeed(&mut self);
+
+    /// Returns the state of the infinite background around the pattern, as of
+    /// the most recent step.
+    ///
+    /// For most rules this is always the default state, but rules where empty
+    /// space does not stay empty (e.g. B0 rules) make the background change
+    /// from one generation to the next. An NdTree being simulated stores each
+    /// cell relative to the background: cells in the background state are
+    /// stored as the default state, and cells in the default state are stored
+    /// as the background state. (All other cells are stored unchanged.) For
+    /// 2-state rules, this means that the pattern is complemented whenever the
+    /// background is not empty, as in Golly.
+    fn get_background(&self) -> C;
+    /// Sets the generation count of the next generation to be simulated, which
+    /// determines the state of the background and (for block rules) the
+    /// partition into blocks. This is updated automatically after each step,
+    /// so it only needs to be set when the generation count changes some
+    /// other way. Negative generation counts are treated as zero.
+    fn set_generation_count(&mut self, generations: &BigInt);
+
+    /// Returns the topology of the grid that this simulation runs on.
+    fn get_topology(&self) -> &Topology<D>;
+    /// Sets the topology of the grid that this simulation runs on, or returns
+    /// an error if the rule cannot be simulated on it.
+    ///
+    /// This does not remove cells outside of the grid until the next step, but
+    /// it does limit the step size if the grid is bounded.
+    fn set_topology(&mut self, topology: Topology<D>) -> Result<(), String>;
+
+    /// Returns the maximum number of bytes that the simulation's caches should
+    /// use before cached results are evicted.
+    fn get_memory_budget(&self) -> usize;
+    /// Sets the maximum number of bytes that the simulation's caches should
+    /// use before cached results are evicted.
+    fn set_memory_budget(&mut self, memory_budget: usize);
+    /// Returns statistics about the memory used by the caches, as of the most
+    /// recent step.
+    fn get_cache_stats(&self) -> CacheStats;
+    /// Evicts cached results that are not needed to simulate the given NdTree
+    /// or the ones passed to `set_gc_roots()`.
+    fn collect_garbage(&mut self, tree: &NdTree<C, D>);
+    /// Sets the roots of other NdTrees (such as those in the undo history)
+    /// that should keep their cached results when garbage is collected,
+    /// including garbage collected during a step. Roots with a different cell
+    /// type or dimensionality are ignored.
+    fn set_gc_roots(&mut self, roots: &[GcRoot]);
+
+    /// Returns the maximum number of threads to use for simulation.
+    fn get_thread_count(&self) -> usize;
+    /// Sets the maximum number of threads to use for simulation. The default
+    /// is the number of CPUs available.
+    fn set_thread_count(&mut self, thread_count: usize);
+
+    /// Advances the given NdTree by a single generation.
+    fn step_single(&mut self, tree: &mut NdTree<C, D>) {
+        self.try_step_single(tree, &CancellationToken::new(), &|_| ())
+            .expect("Step was cancelled without a way to cancel it");
+    }
+    /// Advances the given NdTree by a single generation, unless the step is
+    /// cancelled using the given token.
+    ///
+    /// See `Simulator::try_step()` for details.
+    fn try_step_single(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled>;
+
+    /// Advances the given NdTree by a number of generations equal to this
+    /// simulation's step size.
+    fn step(&mut self, tree: &mut NdTree<C, D>) {
+        self.try_step(tree, &CancellationToken::new(), &|_| ())
+            .expect("Step was cancelled without a way to cancel it");
+    }
+    /// Advances the given NdTree by a number of generations equal to this
+    /// simulation's step size, unless the step is cancelled using the given
+    /// token.
+    ///
+    /// While the step is running, `progress` is called from time to time with
+    /// the fraction of the step that has been completed. It may be called from
+    /// any of the threads used for the step.
+    ///
+    /// If the step is cancelled, `Err(StepCancelled)` is returned and the
+    /// NdTree and simulation are left as they were before the step.
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled>;
+}
+
+/// A container for any type of Simulator.
+#[derive(Debug)]
+pub struct Simulation<C: CellType, D: Dim>(pub Box<dyn Simulator<C, D>>);
+impl<C: CellType, D: Dim> Clone for Simulation<C, D> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+impl<C: CellType, D: Dim> Default for Simulation<C, D> {
+    fn default() -> Self {
+        Self::new(Arc::new(DummyRule), BigInt::one())
+    }
+}
+impl<C: CellType, D: Dim> Simulation<C, D> {
+    /// Constructs a new HashLife simulation with the given rule and step size.
+    pub fn new(rule: Arc<dyn Rule<C, D>>, step_size: BigInt) -> Self {
+        Self::with_algorithm(Algorithm::default(), rule, step_size)
+    }
+    /// Constructs a new simulation using the given algorithm with the given
+    /// rule and step size.
+    pub fn with_algorithm(
+        algorithm: Algorithm,
+        rule: Arc<dyn Rule<C, D>>,
+        step_size: BigInt,
+    ) -> Self {
+        Self(algorithm.new_simulator(rule, step_size))
+    }
+    /// Switches to the given algorithm, keeping the rule, step size, topology,
+    /// memory budget, and thread count. If hyperspeed is enabled, then the
+    /// step size starts doubling again from 1.
+    ///
+    /// Nothing that has been cached is kept, and the generation count must be
+    /// set again before the next step.
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        if algorithm == self.get_algorithm() {
+            return;
+        }
+        let mut new_sim = algorithm.new_simulator(self.get_rule().clone(), BigInt::one());
+        new_sim.set_step_size(self.get_step_size().clone());
+        new_sim.set_hyperspeed(self.get_hyperspeed_exponent().is_some());
+        new_sim
+            .set_topology(self.get_topology().clone())
+            .expect("Topology is not valid for the same rule");
+        new_sim.set_memory_budget(self.get_memory_budget());
+        new_sim.set_thread_count(self.get_thread_count());
+        self.0 = new_sim;
+    }
+}
+impl<C: CellType, D: Dim> Simulator<C, D> for Simulation<C, D> {
+    fn get_algorithm(&self) -> Algorithm {
+        self.0.get_algorithm()
+    }
+    fn clone_box(&self) -> Box<dyn Simulator<C, D>> {
+        self.0.clone_box()
+    }
+    fn get_rule(&self) -> &Arc<dyn Rule<C, D>> {
+        self.0.get_rule()
+    }
+    fn get_step_size(&self) -> &BigInt {
+        self.0.get_step_size()
+    }
+    fn set_step_size(&mut self, new_step_size: BigInt) {
+        self.0.set_step_size(new_step_size);
+    }
+    fn get_hyperspeed_exponent(&self) -> Option<usize> {
+        self.0.get_hyperspeed_exponent()
+    }
+    fn set_hyperspeed(&mut self, hyperspeed: bool) {
+        self.0.set_hyperspeed(hyperspeed);
+    }
+    fn reset_hyperspeed(&mut self) {
+        self.0.reset_hyperspeed();
+    }
+    fn get_background(&self) -> C {
+        self.0.get_background()
+    }
+    fn set_generation_count(&mut self, generations: &BigInt) {
+        self.0.set_generation_count(generations);
+    }
+    fn get_topology(&self) -> &Topology<D> {
+        self.0.get_topology()
+    }
+    fn set_topology(&mut self, topology: Topology<D>) -> Result<(), String> {
+        self.0.set_topology(topology)
+    }
+    fn get_memory_budget(&self) -> usize {
+        self.0.get_memory_budget()
+    }
+    fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.0.set_memory_budget(memory_budget);
+    }
+    fn get_cache_stats(&self) -> CacheStats {
+        self.0.get_cache_stats()
+    }
+    fn collect_garbage(&mut self, tree: &NdTree<C, D>) {
+        self.0.collect_garbage(tree);
+    }
+    fn set_gc_roots(&mut self, roots: &[GcRoot]) {
+        self.0.set_gc_roots(roots);
+    }
+    fn get_thread_count(&self) -> usize {
+        self.0.get_thread_count()
+    }
+    fn set_thread_count(&mut self, thread_count: usize) {
+        self.0.set_thread_count(thread_count);
+    }
+    fn try_step_single(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.0.try_step_single(tree, cancellation, progress)
+    }
+    fn try_step(
+        &mut self,
+        tree: &mut NdTree<C, D>,
+        cancellation: &CancellationToken,
+        progress: &(dyn Fn(f64) + Sync),
+    ) -> Result<(), StepCancelled> {
+        self.0.try_step(tree, cancellation, progress)
+    }
+}
+
+/// The state of a simulation on a particular generation, apart from the
+/// pattern itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Phase<C: CellType> {
+    /// The state of the infinite background.
+    background: C,
+    /// Whether the generation is odd. This is only tracked for block rules,
+    /// because their partition alternates each generation; for other rules it
+    /// is always false, to avoid caching the same results twice.
+    odd_generation: bool,
+}
+
+/// Every phase that a simulation of a rule can be in, starting from generation
+/// 0. The phases after `cycle_start` repeat forever.
+#[derive(Debug, Clone)]
+struct Phases<C: CellType> {
+    phases: Vec<Phase<C>>,
+    cycle_start: usize,
+}
+impl<C: CellType> Index<usize> for Phases<C> {
+    type Output = Phase<C>;
+    fn index(&self, phase: usize) -> &Phase<C> {
+        &self.phases[phase]
+    }
+}
+impl<C: CellType> Phases<C> {
+    /// Computes the sequence of phases of the given rule, starting from an
+    /// empty background on generation 0, until it repeats.
+    fn new<D: Dim>(rule: &dyn Rule<C, D>) -> Self {
+        let mut phases = vec![Phase {
+            background: C::default(),
+            odd_generation: false,
+        }];
+        loop {
+            let Phase {
+                background,
+                odd_generation,
+            } = *phases.last().unwrap();
+            let next = match rule.as_block_rule() {
+                Some(block_rule) => {
+                    let mut block = vec![background; D::TREE_BRANCHES];
+                    block_rule.transition_block(&mut block, odd_generation);
+                    // Block rules that don't keep the background uniform are
+                    // rejected when they're parsed; see
+                    // `rule::keeps_background_uniform()`.
+                    debug_assert!(block.iter().all(|&cell| cell == block[0]));
+                    Phase {
+                        background: block[0],
+                        odd_generation: !odd_generation,
+                    }
+                }
+                None => {
+                    let radius = rule.radius();
+                    let napkin = NdArray::from_flat_vec(
+                        UVec::repeat(2 * radius + 1),
+                        vec![background; (2 * radius + 1).pow(D::NDIM as u32)],
+                    );
+                    let slice = napkin.offset_slice(-IVec::repeat(radius as isize));
+                    Phase {
+                        background: rule.transition(&slice),
+                        odd_generation: false,
+                    }
+                }
+            };
+            match phases.iter().position(|phase| *phase == next) {
+                Some(cycle_start) => {
+                    return Self {
+                        phases,
+                        cycle_start,
+                    }
+                }
+                None => phases.push(next),
+            }
+        }
+    }
+
+    /// Returns the index of the phase that comes the given number of
+    /// generations after the phase with the given index.
+    fn after(&self, phase: usize, generations: &BigInt) -> usize {
+        let remaining = self.phases.len() - phase;
+        if *generations < BigInt::from(remaining) {
+            phase + generations.to_usize().unwrap()
+        } else {
+            let cycle_len = self.phases.len() - self.cycle_start;
+            let offset = (generations - remaining) % cycle_len;
+            self.cycle_start + offset.to_usize().unwrap()
+        }
+    }
+
+    /// Returns an error if a rule with these phases cannot be simulated on the
+    /// given topology.
+    fn check_topology<D: Dim>(
+        &self,
+        rule: &dyn Rule<C, D>,
+        topology: &Topology<D>,
+    ) -> Result<(), String> {
+        // Dead cells beyond the edges of a plane must be stored as the
+        // background state, which is impossible if there are infinitely many
+        // of them.
+        let is_partial_plane =
+            !topology.wraps() && !topology.is_unbounded() && topology.rect().is_none();
+        if is_partial_plane
+            && self
+                .phases
+                .iter()
+                .any(|phase| phase.background != C::default())
+        {
+            return Err(format!(
+                "Rule {} cannot be simulated on a plane that is unbounded along some axis, \
+                 because empty space does not stay empty",
+                rule
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Converts between a cell state and the state that it is stored as when the
+/// infinite background is in the given state. (This is its own inverse.)
+pub(crate) fn swap_background<C: CellType>(cell: C, background: C) -> C {
+    if cell == background {
+        C::default()
+    } else if cell == C::default() {
+        background
+    } else {
+        cell
+    }
+}
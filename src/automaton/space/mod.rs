@@ -18,8 +18,8 @@ pub use ndvec::*;
 
 /// A "trait alias" for a cell type that has a "default" value and can be copied
 /// for free or near-free.
-pub trait CellType: Debug + Copy + Default + Eq + Hash {}
-impl<T: Debug + Copy + Default + Eq + Hash> CellType for T {}
+pub trait CellType: Debug + Copy + Default + Eq + Hash + Send + Sync + 'static {}
+impl<T: Debug + Copy + Default + Eq + Hash + Send + Sync + 'static> CellType for T {}
 
 /// A trait to allow overloading of the contains() method.
 pub trait CanContain<I> {
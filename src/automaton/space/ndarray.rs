@@ -52,6 +52,18 @@ impl<T, D: Dim> IndexMut<&IVec<D>> for NdArray<T, D> {
 }
 
 impl<T, D: Dim> NdArray<T, D> {
+    /// Constructs an NdArray of the given size from a flat Vec of elements,
+    /// ordered with the X axis varying fastest.
+    ///
+    /// Panics if the length of the Vec does not match the size.
+    pub fn from_flat_vec(size: UVec<D>, data: Vec<T>) -> Self {
+        assert_eq!(
+            size.product(),
+            data.len(),
+            "Wrong number of elements for NdArray"
+        );
+        Self { size, data }
+    }
     fn flatten_idx(&self, pos: &IVec<D>) -> usize {
         flatten_idx(&self.size, pos)
     }
@@ -72,6 +84,14 @@ impl<T, D: Dim> NdArray<T, D> {
     pub fn rect(&self) -> URect<D> {
         URect::new(UVec::origin(), self.size.clone())
     }
+    /// Returns an NdArray of the same size with the given function applied to
+    /// each element.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> NdArray<U, D> {
+        NdArray {
+            size: self.size,
+            data: self.data.into_iter().map(f).collect(),
+        }
+    }
 }
 
 /// An offset immutable slice of a NdArray.
@@ -1,6 +1,6 @@
 use seahash::SeaHasher;
 use std::hash::BuildHasherDefault;
-use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex, Weak};
 use weak_table::WeakHashSet;
 
 use crate::automaton::*;
@@ -9,17 +9,35 @@ use crate::automaton::*;
 pub type NodeHasher = BuildHasherDefault<SeaHasher>;
 
 /// A cached NdTreeNode.
-pub type NdCachedNode<C, D> = Rc<NdTreeNode<C, D>>;
+pub type NdCachedNode<C, D> = Arc<NdTreeNode<C, D>>;
 
-/// A cache of NdTreeNodes.
-#[derive(Debug, Clone, Default)]
+/// Number of independently locked shards that the node cache is split into,
+/// so that many threads can create nodes at once.
+const NODE_CACHE_SHARDS: usize = 64;
+
+/// A set of weak references to cached nodes.
+type NodeSet<C, D> = WeakHashSet<Weak<NdTreeNode<C, D>>, NodeHasher>;
+
+/// A cache of NdTreeNodes, which may be shared between threads.
+#[derive(Debug)]
 pub struct NdTreeCache<C: CellType, D: Dim> {
-    /// A HashSet of all of the nodes.
-    nodes: WeakHashSet<Weak<NdTreeNode<C, D>>, NodeHasher>,
+    /// A HashSet of all of the nodes, split into shards by hash code.
+    shards: Vec<Mutex<NodeSet<C, D>>>,
     /// A cache of empty nodes at various layers.
     ///
     /// The element at index N is the empty node at layer N-1.
-    empty_nodes: Vec<NdCachedNode<C, D>>,
+    empty_nodes: Mutex<Vec<NdCachedNode<C, D>>>,
+}
+
+impl<C: CellType, D: Dim> Default for NdTreeCache<C, D> {
+    fn default() -> Self {
+        Self {
+            shards: (0..NODE_CACHE_SHARDS)
+                .map(|_| Mutex::new(WeakHashSet::default()))
+                .collect(),
+            empty_nodes: Mutex::new(vec![]),
+        }
+    }
 }
 
 impl<C: CellType, D: Dim> NdTreeCache<C, D> {
@@ -30,45 +48,61 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
 
     /// Returns the cached node with the given branches, creating it if it does
     /// not exist.
-    pub fn get_node(&mut self, branches: Vec<NdTreeBranch<C, D>>) -> NdCachedNode<C, D> {
+    pub fn get_node(&self, branches: Vec<NdTreeBranch<C, D>>) -> NdCachedNode<C, D> {
         // Create an NdBaseTreeNode (cheaper than a full NdTreeNode) for HashSet
         // lookup.
         let base_node = NdBaseTreeNode::from(branches);
+        let mut nodes = self.shards[base_node.hash_code as usize % NODE_CACHE_SHARDS]
+            .lock()
+            .unwrap();
         // If the node is already in the cache, return the one from the cache.
-        if let Some(existing_node) = self.nodes.get(&base_node) {
+        if let Some(existing_node) = nodes.get(&base_node) {
             return existing_node;
         }
         // Otherwise, make it a full node, add it to the cache, and return it.
-        let ret = Rc::new(NdTreeNode::from(base_node));
-        self.nodes.insert(ret.clone());
+        let ret = Arc::new(NdTreeNode::from(base_node));
+        nodes.insert(ret.clone());
         ret
     }
+    /// Returns the number of nodes in the cache that are still in use.
+    pub fn node_count(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let mut nodes = shard.lock().unwrap();
+                nodes.remove_expired();
+                nodes.len()
+            })
+            .sum()
+    }
     /// Returns the NdTreeNode at the given layer with all default cells.
-    pub fn get_empty_node(&mut self, layer: usize) -> NdCachedNode<C, D> {
-        if let Some(ret) = self.empty_nodes.get(layer - 1) {
+    pub fn get_empty_node(&self, layer: usize) -> NdCachedNode<C, D> {
+        if let Some(ret) = self.empty_nodes.lock().unwrap().get(layer - 1) {
             // Cache hit
-            ret.clone()
-        } else {
-            // Cache miss
-            let branches = vec![self.get_empty_branch(layer - 1); D::TREE_BRANCHES];
-            let ret = self.get_node(branches);
-            // All lower entries in the cache have been filled by the recursive
-            // call.
-            self.empty_nodes.push(ret.clone());
-            ret
+            return ret.clone();
         }
+        // Cache miss
+        let branches = vec![self.get_empty_branch(layer - 1); D::TREE_BRANCHES];
+        let ret = self.get_node(branches);
+        // All lower entries in the cache have been filled by the recursive
+        // call, but another thread may have already filled this one.
+        let mut empty_nodes = self.empty_nodes.lock().unwrap();
+        if empty_nodes.len() == layer - 1 {
+            empty_nodes.push(ret.clone());
+        }
+        ret
     }
     /// Returns the NdTreeBranch containing a node at the given layer with all
     /// default cells (or just an NdTreeBranch::Leaf of the default cell state).
-    pub fn get_empty_branch(&mut self, layer: usize) -> NdTreeBranch<C, D> {
+    pub fn get_empty_branch(&self, layer: usize) -> NdTreeBranch<C, D> {
         match layer {
             0 => NdTreeBranch::Leaf(C::default()),
             _ => NdTreeBranch::Node(self.get_empty_node(layer)),
         }
     }
     /// Returns a cached node, using a function to generate each branch.
-    pub fn get_node_from_fn<F: FnMut(&mut Self, ByteVec<D>) -> NdTreeBranch<C, D>>(
-        &mut self,
+    pub fn get_node_from_fn<F: FnMut(&Self, ByteVec<D>) -> NdTreeBranch<C, D>>(
+        &self,
         mut generator: F,
     ) -> NdCachedNode<C, D> {
         let branches = (0..D::TREE_BRANCHES)
@@ -77,11 +111,24 @@ impl<C: CellType, D: Dim> NdTreeCache<C, D> {
             .collect();
         self.get_node(branches)
     }
+    /// Returns a cached node, using a function that may fail to generate each
+    /// branch. If it fails for any branch, then the error is returned
+    /// immediately.
+    pub fn try_get_node_from_fn<E, F: FnMut(&Self, ByteVec<D>) -> Result<NdTreeBranch<C, D>, E>>(
+        &self,
+        mut generator: F,
+    ) -> Result<NdCachedNode<C, D>, E> {
+        let branches = (0..D::TREE_BRANCHES)
+            .map(ByteVec::from_array_idx)
+            .map(|branch_idx| generator(self, branch_idx))
+            .collect::<Result<_, _>>()?;
+        Ok(self.get_node(branches))
+    }
     /// Returns a cached node, using a function of the cell position to generate
     /// each cell state. This can only be used for relatively small nodes, since
     /// an IVec is used for the position vector.
     pub fn get_small_node_from_cell_fn<F: Fn(IVec<D>) -> C>(
-        &mut self,
+        &self,
         layer: usize,
         offset: IVec<D>,
         generator: &F,
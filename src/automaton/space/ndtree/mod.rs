@@ -1,6 +1,5 @@
-use std::cell::RefCell;
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 
 mod cache;
 mod indexed;
@@ -17,7 +16,7 @@ pub use slice::*;
 #[derive(Debug, Clone)]
 pub struct NdTree<C: CellType, D: Dim> {
     /// The cache for this tree's nodes.
-    pub cache: Rc<RefCell<NdTreeCache<C, D>>>,
+    pub cache: Arc<NdTreeCache<C, D>>,
     /// The slice describing the root node and offset.
     pub slice: NdTreeSlice<C, D>,
 }
@@ -60,11 +59,11 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
     /// Constructs a new empty NdTree with an empty node cache centered on the
     /// origin.
     pub fn new() -> Self {
-        let mut cache = NdTreeCache::default();
+        let cache = NdTreeCache::default();
         let root = cache.get_empty_node(1);
         let offset = NdVec::repeat(-1);
         Self {
-            cache: Rc::new(RefCell::new(cache)),
+            cache: Arc::new(cache),
             slice: NdTreeSlice { root, offset },
         }
     }
@@ -91,7 +90,7 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
     /// corner. The final result is that the entire tree contains the same
     /// contents as before, but with 25% padding on each edge.
     pub fn expand(&mut self) {
-        let mut cache = self.cache.borrow_mut();
+        let cache = &self.cache;
         let empty_sub_branch = cache.get_empty_branch(self.slice.root.layer - 1);
         let old_root = self.slice.root.clone();
         self.slice.root = cache.get_node_from_fn(move |cache, branch_idx| {
@@ -128,7 +127,7 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
         if self.get_root().layer == 1 {
             return 0;
         }
-        let new_node = self.get_root().get_inner_node(&mut self.cache.borrow_mut());
+        let new_node = self.get_root().get_inner_node(&self.cache);
         // Make sure the populations are the same (i.e. we haven't lost any
         // cells); otherwise don't do anything.
         if new_node.population == self.get_root().population {
@@ -150,11 +149,15 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
     /// Sets the state of the cell at the given position.
     pub fn set_cell(&mut self, pos: &BigVec<D>, cell_state: C) {
         self.expand_to(&pos);
-        self.slice.root = self.slice.root.set_cell(
-            &mut self.cache.borrow_mut(),
-            &(pos - &self.slice.offset),
-            cell_state,
-        );
+        self.slice.root =
+            self.slice
+                .root
+                .set_cell(&self.cache, &(pos - &self.slice.offset), cell_state);
+    }
+
+    /// Sets every cell outside the given rectangle to the default state.
+    pub fn crop(&mut self, rect: &BigRect<D>) {
+        self.slice.root = crop_node(&self.cache, &self.slice.root, &self.slice.offset, rect);
     }
 
     /// Returns an NdTreeSlice of the smallest node in the grid containing the
@@ -171,7 +174,7 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
         let mut slice = self.slice.clone();
         let mut smaller_slice = self.slice.clone();
         self.shrink();
-        let mut cache = self.cache.borrow_mut();
+        let cache = &self.cache;
         // "Zoom in" on either a corner, an edge/face, or the center until it
         // can't shrink any more. Each iteration of the loop "zooms in" by a
         // factor of 2. If we've zoomed in too far, break out of the loop and
@@ -224,6 +227,39 @@ impl<C: CellType, D: Dim> NdTree<C, D> {
     }
 }
 
+/// Returns a node with every cell outside the given rectangle set to the
+/// default state, given the position of the lower bound of the node.
+fn crop_node<C: CellType, D: Dim>(
+    cache: &NdTreeCache<C, D>,
+    node: &NdCachedNode<C, D>,
+    offset: &BigVec<D>,
+    rect: &BigRect<D>,
+) -> NdCachedNode<C, D> {
+    let node_rect = node.rect() + offset;
+    if node.is_empty() || rect.contains(&node_rect) {
+        return node.clone();
+    }
+    if !rect.clone().intersects(node_rect) {
+        return cache.get_empty_node(node.layer);
+    }
+    let new_branches = node
+        .branch_iter()
+        .map(|(branch_idx, branch)| {
+            let branch_offset = offset + node.branch_offset(branch_idx);
+            match branch {
+                NdTreeBranch::Leaf(cell_state) if rect.contains(&branch_offset) => {
+                    NdTreeBranch::Leaf(*cell_state)
+                }
+                NdTreeBranch::Leaf(_) => NdTreeBranch::Leaf(C::default()),
+                NdTreeBranch::Node(node) => {
+                    NdTreeBranch::Node(crop_node(cache, node, &branch_offset, rect))
+                }
+            }
+        })
+        .collect();
+    cache.get_node(new_branches)
+}
+
 #[cfg(test)]
 mod tests {
     use num::BigInt;
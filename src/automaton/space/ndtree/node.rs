@@ -1,6 +1,7 @@
 use num::{BigInt, One, ToPrimitive, Zero};
 use seahash::SeaHasher;
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::convert::From;
 use std::fmt;
 use std::hash::{Hash, Hasher};
@@ -227,7 +228,7 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
     /// A node's inner node is the node one layer down, centered on the original
     /// node. For example, the inner node of a 16x16 node (layer 4) is the 8x8
     /// node (layer 3) centered on it.
-    pub fn get_inner_node(&self, cache: &mut NdTreeCache<C, D>) -> NdCachedNode<C, D> {
+    pub fn get_inner_node(&self, cache: &NdTreeCache<C, D>) -> NdCachedNode<C, D> {
         assert_ne!(1, self.layer, "Cannot take inner node of node at layer 1");
         let new_branches = self
             .branches
@@ -281,7 +282,7 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
     #[must_use]
     pub fn set_cell<I: NdTreeIndex<D>>(
         &self,
-        cache: &mut NdTreeCache<C, D>,
+        cache: &NdTreeCache<C, D>,
         pos: &I,
         cell_state: C,
     ) -> NdCachedNode<C, D> {
@@ -296,6 +297,48 @@ impl<C: CellType, D: Dim> NdTreeNode<C, D> {
         }
         cache.get_node(new_branches)
     }
+    /// Returns the lowest and highest coordinates along the given axis of the
+    /// non-default cells in this node, relative to its lowest corner, or None
+    /// if the node is empty.
+    pub fn population_span(&self, axis: Axis) -> Option<(BigInt, BigInt)> {
+        self.population_span_memoized(axis, &mut HashMap::new())
+    }
+    fn population_span_memoized(
+        &self,
+        axis: Axis,
+        memo: &mut HashMap<*const Self, Option<(BigInt, BigInt)>>,
+    ) -> Option<(BigInt, BigInt)> {
+        if self.is_empty() {
+            return None;
+        }
+        if let Some(span) = memo.get(&(self as *const Self)) {
+            return span.clone();
+        }
+        let half_len = Self::len_at_layer(self.layer - 1);
+        let mut ret: Option<(BigInt, BigInt)> = None;
+        for (branch_idx, branch) in self.branch_iter() {
+            let span = match branch {
+                NdTreeBranch::Leaf(cell_state) if *cell_state != C::default() => {
+                    Some((BigInt::zero(), BigInt::zero()))
+                }
+                NdTreeBranch::Leaf(_) => None,
+                NdTreeBranch::Node(node) => node.population_span_memoized(axis, memo),
+            };
+            if let Some((mut min, mut max)) = span {
+                if branch_idx[axis] != 0 {
+                    min += &half_len;
+                    max += &half_len;
+                }
+                ret = Some(match ret {
+                    Some((old_min, old_max)) => (old_min.min(min), old_max.max(max)),
+                    None => (min, max),
+                });
+            }
+        }
+        memo.insert(self as *const Self, ret.clone());
+        ret
+    }
+
     /// Returns an iterator over the branches of this node.
     pub fn branch_iter(&self) -> impl Iterator<Item = (ByteVec<D>, &NdTreeBranch<C, D>)> {
         self.branches
@@ -359,7 +402,11 @@ pub trait NdTreeIndex<D: Dim> {
 }
 impl<D: Dim> NdTreeIndex<D> for BigVec<D> {
     fn branch_idx(&self, layer: usize) -> ByteVec<D> {
-        ByteVec::from_fn(|ax| (&self[ax] >> (layer - 1)).to_u8().unwrap() & 1)
+        ByteVec::from_fn(|ax| {
+            ((&self[ax] >> (layer - 1)) & BigInt::one())
+                .to_u8()
+                .unwrap()
+        })
     }
 }
 impl<D: Dim> NdTreeIndex<D> for IVec<D> {
@@ -443,4 +490,20 @@ mod tests {
             assert_eq!(idx, vec.to_array_idx());
         }
     }
+
+    #[test]
+    fn test_population_span() {
+        let mut tree = NdTree::<u8, Dim2D>::new();
+        assert_eq!(None, tree.get_root().population_span(X));
+        tree.set_cell(&NdVec::big([3, -5]), 1);
+        tree.set_cell(&NdVec::big([-2, 7]), 2);
+        tree.set_cell(&NdVec::big([-2, 1]), 1);
+        let min = tree.slice.min();
+        let span = |ax: Axis| {
+            let (lo, hi) = tree.get_root().population_span(ax).unwrap();
+            (lo + &min[ax], hi + &min[ax])
+        };
+        assert_eq!((BigInt::from(-2), BigInt::from(3)), span(X));
+        assert_eq!((BigInt::from(-5), BigInt::from(7)), span(Y));
+    }
 }
@@ -10,7 +10,9 @@ use super::*;
 /// uses any number type instead of usize (which is crucial for this
 /// application). Similar to ndarray's Dimension trait, this trait should not
 /// and cannot be implemented outside of this crate.
-pub trait Dim: DimFor<BigInt> + DimFor<R64> + DimFor<isize> + DimFor<usize> + DimFor<u8> {
+pub trait Dim:
+    'static + DimFor<BigInt> + DimFor<R64> + DimFor<isize> + DimFor<usize> + DimFor<u8>
+{
     /// The number of dimensions (number of axes).
     const NDIM: usize;
 
@@ -72,35 +74,35 @@ impl Dim for Dim6D {
 /// Once generic associated types come along, this can be merged into Dim to
 /// simplify things.
 pub trait DimFor<T: Default + Clone + Eq>:
-    Debug + Default + Copy + Eq + Hash + private::Sealed
+    Debug + Default + Copy + Eq + Hash + Send + Sync + private::Sealed
 {
     /// The pure Dim type associated with this DimFor (i.e. Self)
     type Dim: Dim;
     /// The array type used for vectors.
-    type Array: Debug + Default + Clone + Eq + Hash + AsRef<[T]> + AsMut<[T]>;
+    type Array: Debug + Default + Clone + Eq + Hash + Send + Sync + AsRef<[T]> + AsMut<[T]>;
 }
 
-impl<T: Debug + Default + Clone + Eq + Hash> DimFor<T> for Dim1D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim1D {
     type Dim = Dim1D;
     type Array = [T; 1];
 }
-impl<T: Debug + Default + Clone + Eq + Hash> DimFor<T> for Dim2D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim2D {
     type Dim = Dim2D;
     type Array = [T; 2];
 }
-impl<T: Debug + Default + Clone + Eq + Hash> DimFor<T> for Dim3D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim3D {
     type Dim = Dim3D;
     type Array = [T; 3];
 }
-impl<T: Debug + Default + Clone + Eq + Hash> DimFor<T> for Dim4D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim4D {
     type Dim = Dim4D;
     type Array = [T; 4];
 }
-impl<T: Debug + Default + Clone + Eq + Hash> DimFor<T> for Dim5D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim5D {
     type Dim = Dim5D;
     type Array = [T; 5];
 }
-impl<T: Debug + Default + Clone + Eq + Hash> DimFor<T> for Dim6D {
+impl<T: Debug + Default + Clone + Eq + Hash + Send + Sync> DimFor<T> for Dim6D {
     type Dim = Dim6D;
     type Array = [T; 6];
 }
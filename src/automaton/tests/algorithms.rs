@@ -0,0 +1,138 @@
+use super::*;
+
+/// Checks that two NdTrees contain the same nodes at the same offset.
+///
+/// Nodes are compared by value rather than by pointer, since the node cache
+/// does not guarantee that there is only one copy of each node.
+fn assert_same_tree<C: CellType, D: Dim>(expected: &NdTree<C, D>, actual: &NdTree<C, D>) {
+    assert!(**expected.get_root() == **actual.get_root());
+    assert_eq!(expected.slice.offset, actual.slice.offset);
+}
+
+/// Checks that simulating a pattern with the incremental algorithm produces
+/// exactly the same tree as simulating it with HashLife.
+fn assert_incremental_matches<D: Dim>(
+    rule_str: &str,
+    topology_str: &str,
+    cells: Vec<(IVec<D>, u8)>,
+    step_size: usize,
+    steps: usize,
+) {
+    let rule = rule::from_str::<D>(rule_str).unwrap();
+    let topology: Topology<D> = topology_str.parse().unwrap();
+    let mut hashlife_sim =
+        Simulation::with_algorithm(Algorithm::HashLife, rule.clone(), BigInt::from(step_size));
+    hashlife_sim.set_topology(topology.clone()).unwrap();
+    let mut incremental_sim =
+        Simulation::with_algorithm(Algorithm::Incremental, rule, BigInt::from(step_size));
+    incremental_sim.set_topology(topology.clone()).unwrap();
+    let mut hashlife_tree = NdTree::new();
+    for (pos, cell_state) in &cells {
+        if topology.contains(pos) {
+            hashlife_tree.set_cell(&pos.convert(), *cell_state);
+        }
+    }
+    let mut incremental_tree = hashlife_tree.clone();
+    for _ in 0..steps {
+        hashlife_sim.step(&mut hashlife_tree);
+        incremental_sim.step(&mut incremental_tree);
+        assert_eq!(
+            hashlife_sim.get_background(),
+            incremental_sim.get_background()
+        );
+        assert_same_tree(&hashlife_tree, &incremental_tree);
+    }
+}
+
+#[test]
+fn test_incremental_unbounded() {
+    assert_incremental_matches::<Dim2D>("B3/S23", "P0,0", make_soup(32, 2), 1, 20);
+    assert_incremental_matches::<Dim2D>("B3/S23", "P0,0", make_soup(32, 2), 7, 4);
+    assert_incremental_matches::<Dim2D>("B036/S1237", "P0,0", make_soup(16, 2), 3, 5);
+    assert_incremental_matches::<Dim2D>("B0/S1/C3", "P0,0", make_soup(16, 3), 2, 5);
+    assert_incremental_matches::<Dim2D>("BBM", "P0,0", make_soup(16, 2), 5, 4);
+    assert_incremental_matches::<Dim2D>("Critters", "P0,0", make_soup(16, 2), 5, 4);
+    assert_incremental_matches::<Dim2D>("R3,C0,M0,S2..4,B3..3,NN", "P0,0", make_soup(16, 2), 2, 4);
+    assert_incremental_matches::<Dim1D>("W110", "P0", make_soup(64, 2), 16, 4);
+    assert_incremental_matches::<Dim3D>("B4/S45", "P0,0,0", make_soup(8, 2), 2, 3);
+}
+
+#[test]
+fn test_incremental_bounded() {
+    assert_incremental_matches::<Dim2D>("B3/S23", "T12,11", make_soup(16, 2), 1, 20);
+    assert_incremental_matches::<Dim2D>("B3/S23", "P16,15", make_soup(16, 2), 6, 4);
+    assert_incremental_matches::<Dim2D>("BBM", "T12,10", make_soup(16, 2), 3, 4);
+    assert_incremental_matches::<Dim1D>("W30", "T17", make_soup(32, 2), 5, 4);
+}
+
+#[test]
+fn test_switch_algorithm() {
+    let mut sim = Simulation::new(rule::from_str::<Dim2D>("B3/S23").unwrap(), BigInt::from(8));
+    sim.set_topology("T32,32".parse().unwrap()).unwrap();
+    sim.set_memory_budget(1 << 24);
+    sim.set_thread_count(3);
+    assert_eq!(Algorithm::HashLife, sim.get_algorithm());
+
+    sim.set_algorithm(Algorithm::Incremental);
+    assert_eq!(Algorithm::Incremental, sim.get_algorithm());
+    assert_eq!(&BigInt::from(8), sim.get_step_size());
+    assert_eq!(
+        &"T32,32".parse::<Topology<Dim2D>>().unwrap(),
+        sim.get_topology()
+    );
+    assert_eq!(1 << 24, sim.get_memory_budget());
+    assert_eq!(3, sim.get_thread_count());
+}
+
+#[test]
+fn test_switch_algorithm_mid_run() {
+    let cells = make_soup(32, 2);
+    let mut expected = NdAutomaton::<Dim2D> {
+        sim: Simulation::new(rule::from_str("B3/S23").unwrap(), BigInt::from(4)),
+        ..Default::default()
+    };
+    for (pos, cell_state) in &cells {
+        expected.tree.set_cell(&pos.convert(), *cell_state);
+    }
+    let mut automaton = expected.clone();
+    for &algorithm in &[
+        Algorithm::Incremental,
+        Algorithm::HashLife,
+        Algorithm::Incremental,
+    ] {
+        automaton.set_algorithm(algorithm);
+        automaton.step();
+        expected.step();
+        assert_eq!(algorithm, automaton.get_algorithm());
+        assert_eq!(expected.generations, automaton.generations);
+        assert_same_tree(&expected.tree, &automaton.tree);
+    }
+}
+
+#[test]
+fn test_incremental_cancel() {
+    let mut sim = Simulation::with_algorithm(
+        Algorithm::Incremental,
+        rule::from_str::<Dim2D>("B3/S23").unwrap(),
+        BigInt::from(16),
+    );
+    let mut tree = NdTree::new();
+    for (pos, cell_state) in make_soup(32, 2) {
+        tree.set_cell(&pos.convert(), cell_state);
+    }
+    let before = tree.clone();
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+    assert_eq!(
+        Err(StepCancelled),
+        sim.try_step(&mut tree, &cancellation, &|_| ())
+    );
+    assert!(Arc::ptr_eq(before.get_root(), tree.get_root()));
+    assert_eq!(before.slice.offset, tree.slice.offset);
+
+    // The simulation still works afterwards.
+    let mut expected = before.clone();
+    Simulation::new(rule::from_str("B3/S23").unwrap(), BigInt::from(16)).step(&mut expected);
+    sim.step(&mut tree);
+    assert_same_tree(&expected, &tree);
+}
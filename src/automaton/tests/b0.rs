@@ -0,0 +1,98 @@
+use super::*;
+
+/// Checks that HashLife matches the reference simulation, stepping the given
+/// number of generations at a time, and returns the background states seen
+/// after each step.
+fn assert_matches_reference<D: Dim>(
+    rule_str: &str,
+    cells: Vec<(IVec<D>, u8)>,
+    step_size: usize,
+    steps: usize,
+) -> Vec<u8> {
+    let rule = rule::from_str::<D>(rule_str).unwrap();
+    let mut sim = Simulation::new(rule.clone(), BigInt::from(step_size));
+    let mut tree = NdTree::new();
+    for (pos, cell_state) in &cells {
+        tree.set_cell(&pos.convert(), *cell_state);
+    }
+    let mut expected = ReferenceSim::new(rule, cells, 0, false);
+    let mut backgrounds = vec![];
+    for _ in 0..steps {
+        sim.step(&mut tree);
+        for _ in 0..step_size {
+            expected.step();
+        }
+        assert_eq!(expected.get_background(), sim.get_background());
+        backgrounds.push(expected.get_background());
+        expected.assert_matches(&tree, sim.get_background());
+    }
+    assert!(!expected.is_empty(), "pattern died out");
+    backgrounds
+}
+
+#[test]
+fn test_b0_with_s8() {
+    // Empty space becomes full and then stays full.
+    let backgrounds = assert_matches_reference::<Dim2D>("B0123478/S34678", make_soup(16, 2), 1, 30);
+    assert!(backgrounds.iter().all(|&background| background == 1));
+    assert_matches_reference::<Dim2D>("B0123478/S34678", make_soup(16, 2), 4, 8);
+}
+
+#[test]
+fn test_b0_without_s8() {
+    // Empty space becomes full and then empty again.
+    let backgrounds = assert_matches_reference::<Dim2D>("B036/S1237", make_soup(16, 2), 1, 30);
+    assert_eq!(vec![1, 0, 1, 0], backgrounds[..4].to_vec());
+    assert_matches_reference::<Dim2D>("B036/S1237", make_soup(16, 2), 2, 10);
+    assert_matches_reference::<Dim2D>("B036/S1237", make_soup(16, 2), 3, 10);
+    assert_matches_reference::<Dim1D>("W1", make_soup(32, 2), 1, 30);
+}
+
+#[test]
+fn test_b0_generations() {
+    // Empty space cycles through all three states.
+    let backgrounds = assert_matches_reference::<Dim2D>("B0/S1/C3", make_soup(16, 3), 1, 30);
+    assert_eq!(vec![1, 2, 0, 1, 2, 0], backgrounds[..6].to_vec());
+    assert_matches_reference::<Dim2D>("B0/S1/C3", make_soup(16, 3), 2, 15);
+    assert_matches_reference::<Dim2D>("B0/S1/C3", make_soup(16, 3), 16, 3);
+}
+
+#[test]
+fn test_b0_automaton() {
+    // The automaton tells the simulation what the generation count is, which
+    // determines the state of the background.
+    let mut automaton = NdAutomaton::<Dim2D> {
+        sim: Simulation::new(rule::from_str("B036/S1237").unwrap(), BigInt::from(5)),
+        ..Default::default()
+    };
+    automaton.step();
+    assert_eq!(1, automaton.sim.get_background());
+    automaton.step_single();
+    assert_eq!(0, automaton.sim.get_background());
+    automaton.set_generation_count(BigInt::from(2));
+    automaton.step_single();
+    assert_eq!(1, automaton.sim.get_background());
+}
+
+#[test]
+fn test_b0_huge_generation_count() {
+    // The background depends only on the parity of the generation count.
+    let mut automaton = NdAutomaton::<Dim2D> {
+        sim: Simulation::new(
+            rule::from_str("B036/S1237").unwrap(),
+            BigInt::from(1) << 100,
+        ),
+        ..Default::default()
+    };
+    automaton.set_generation_count((BigInt::from(1) << 100) + 1);
+    automaton.step_single();
+    assert_eq!(0, automaton.sim.get_background());
+    automaton.step();
+    assert_eq!(0, automaton.sim.get_background());
+    automaton.step_single();
+    assert_eq!(1, automaton.sim.get_background());
+    assert_eq!(
+        (BigInt::from(1) << 101) + 3,
+        *automaton.get_generation_count()
+    );
+}
@@ -0,0 +1,105 @@
+use std::sync::Mutex;
+
+use super::*;
+
+const GOSPER_GLIDER_GUN_RLE: &str = "x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bo
+bo$10bo5bo7bo$11bo3bo$12b2o!";
+
+/// Returns an automaton that is not stepped, along with the same automaton
+/// after it has been stepped without any cancellation.
+fn make_automata(step_size: usize, topology: &str) -> (NdAutomaton<Dim2D>, NdAutomaton<Dim2D>) {
+    let mut automaton: NdAutomaton<Dim2D> =
+        rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    automaton.set_topology(topology.parse().unwrap()).unwrap();
+    automaton.set_step_size(BigInt::from(step_size));
+    // Don't clone the automaton, because clones share the same cache.
+    let mut expected: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    expected.set_topology(topology.parse().unwrap()).unwrap();
+    expected.set_step_size(BigInt::from(step_size));
+    expected.step();
+    (automaton, expected)
+}
+
+/// Checks that a cancelled step leaves the automaton as it was, and that it
+/// can still be stepped correctly afterwards.
+fn assert_cancelled_step_is_consistent(
+    automaton: &mut NdAutomaton<Dim2D>,
+    expected: &NdAutomaton<Dim2D>,
+    cancellation: &CancellationToken,
+    progress: &(dyn Fn(f64) + Sync),
+) {
+    let before = automaton.clone();
+    assert_eq!(
+        Err(StepCancelled),
+        automaton.try_step(cancellation, progress)
+    );
+    assert!(Arc::ptr_eq(
+        before.tree.get_root(),
+        automaton.tree.get_root()
+    ));
+    assert_eq!(before.tree.slice.offset, automaton.tree.slice.offset);
+    assert_eq!(before.generations, automaton.generations);
+    assert_eq!(before.get_step_size(), automaton.get_step_size());
+
+    automaton
+        .try_step(&CancellationToken::new(), &|_| ())
+        .unwrap();
+    assert_eq!(expected.generations, automaton.generations);
+    assert_eq!(
+        get_non_default_set(&expected.tree.slice),
+        get_non_default_set(&automaton.tree.slice)
+    );
+}
+
+#[test]
+fn test_cancel_before_step() {
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+    assert!(cancellation.is_cancelled());
+    let (mut automaton, expected) = make_automata(64, "P0,0");
+    assert_cancelled_step_is_consistent(&mut automaton, &expected, &cancellation, &|_| ());
+    let (mut automaton, expected) = make_automata(3, "T40,30");
+    assert_cancelled_step_is_consistent(&mut automaton, &expected, &cancellation, &|_| ());
+}
+
+#[test]
+fn test_cancel_during_step() {
+    for &threads in &[1, 4] {
+        // Cancel the step as soon as any progress is reported.
+        let cancellation = CancellationToken::new();
+        let cancel = |_| cancellation.cancel();
+        let (mut automaton, expected) = make_automata(64, "P0,0");
+        automaton.sim.set_thread_count(threads);
+        assert_cancelled_step_is_consistent(&mut automaton, &expected, &cancellation, &cancel);
+
+        let cancellation = CancellationToken::new();
+        let cancel = |_| cancellation.cancel();
+        let (mut automaton, expected) = make_automata(3, "T40,30");
+        automaton.sim.set_thread_count(threads);
+        assert_cancelled_step_is_consistent(&mut automaton, &expected, &cancellation, &cancel);
+    }
+}
+
+#[test]
+fn test_step_progress() {
+    for topology in &["P0,0", "T40,30"] {
+        let (mut automaton, expected) = make_automata(16, topology);
+        automaton.sim.set_thread_count(1);
+        let reported = Mutex::new(vec![]);
+        automaton
+            .try_step(&CancellationToken::new(), &|fraction| {
+                reported.lock().unwrap().push(fraction)
+            })
+            .unwrap();
+        assert_eq!(
+            get_non_default_set(&expected.tree.slice),
+            get_non_default_set(&automaton.tree.slice)
+        );
+        let reported = reported.into_inner().unwrap();
+        assert!(!reported.is_empty());
+        assert!(reported.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(0.0 < reported[0]);
+        assert_eq!(1.0, *reported.last().unwrap());
+    }
+}
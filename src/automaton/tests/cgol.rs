@@ -1,37 +1,13 @@
-use std::collections::HashSet;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::*;
 
-fn get_non_default_set<C: CellType, D: Dim>(slice: &NdTreeSlice<C, D>) -> HashSet<BigVec<D>> {
-    let mut ret = HashSet::new();
-    for (branch_idx, branch) in slice.root.branch_iter() {
-        let branch_offset = &slice.offset + slice.root.branch_offset(branch_idx);
-        match branch {
-            NdTreeBranch::Leaf(cell_state) => {
-                if *cell_state != C::default() {
-                    ret.insert(branch_offset);
-                }
-            }
-            NdTreeBranch::Node(node) => ret.extend(get_non_default_set(&NdTreeSlice {
-                root: node.clone(),
-                offset: branch_offset,
-            })),
-        }
-    }
-    ret
-}
-
-fn make_cell_coords_set<D: Dim>(coords_vec: Vec<IVec<D>>) -> HashSet<BigVec<D>> {
-    coords_vec.iter().map(NdVec::convert).collect()
-}
-
 #[test]
 fn test_cgol() {
     // TODO: load RLE instead of manually setting individual cells.
     let mut grid = NdTree::new();
     let rule = rule::LIFE;
-    let mut sim = Simulation::new(Rc::new(rule), 1);
+    let mut sim = Simulation::new(Arc::new(rule), BigInt::from(1));
 
     // Make a glider
     grid.set_cell(&NdVec::big([3, 3]), 1);
@@ -79,7 +55,7 @@ fn test_cgol() {
         ]),
         get_non_default_set(&grid.slice)
     );
-    sim.set_step_size(64);
+    sim.set_step_size(BigInt::from(64));
     sim.step(&mut grid);
     assert_eq!(
         make_cell_coords_set(vec![
@@ -91,7 +67,7 @@ fn test_cgol() {
         ]),
         get_non_default_set(&grid.slice)
     );
-    sim.set_step_size(1024);
+    sim.set_step_size(BigInt::from(1024));
     sim.step(&mut grid);
     assert_eq!(
         make_cell_coords_set(vec![
@@ -104,3 +80,73 @@ fn test_cgol() {
         get_non_default_set(&grid.slice)
     );
 }
+
+#[test]
+fn test_cgol_huge_step() {
+    let mut automaton = NdAutomaton::<Dim2D> {
+        sim: Simulation::new(Arc::new(rule::LIFE), BigInt::from(1) << 100),
+        ..Default::default()
+    };
+    let glider = vec![
+        NdVec([3, 3]),
+        NdVec([4, 3]),
+        NdVec([5, 3]),
+        NdVec([5, 2]),
+        NdVec([4, 1]),
+    ];
+    for pos in &glider {
+        automaton.tree.set_cell(&pos.convert(), 1);
+    }
+    automaton.step();
+    assert_eq!(BigInt::from(1) << 100, *automaton.get_generation_count());
+    // The glider travels one cell diagonally every four generations.
+    let offset = BigVec2D::repeat(BigInt::from(1) << 98);
+    let expected: HashSet<BigVec2D> = make_cell_coords_set(glider)
+        .into_iter()
+        .map(|pos| pos + &offset)
+        .collect();
+    assert_eq!(expected, get_non_default_set(&automaton.tree.slice));
+}
+
+#[test]
+fn test_hyperspeed() {
+    // The R-pentomino is chaotic for about 1100 generations, and then it
+    // settles down and emits gliders.
+    let rle = "x = 3, y = 3, rule = B3/S23\nb2o$2ob$bo!";
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    let mut expected = automaton.clone();
+    automaton.set_hyperspeed(true);
+    assert_eq!(Some(0), automaton.get_hyperspeed_exponent());
+    assert_eq!(BigInt::from(1), *automaton.get_step_size());
+    let mut exponents = vec![];
+    for _ in 0..32 {
+        automaton.step();
+        let exponent = automaton.get_hyperspeed_exponent().unwrap();
+        assert_eq!(BigInt::from(1) << exponent, *automaton.get_step_size());
+        exponents.push(exponent);
+    }
+    // The step size should only ever double, and it should double faster once
+    // the pattern has stabilized.
+    assert!(exponents
+        .windows(2)
+        .all(|w| w[1] == w[0] || w[1] == w[0] + 1));
+    assert!(exponents[10] < 5);
+    assert!(exponents[31] >= 8);
+
+    expected.set_step_size(automaton.get_generation_count().clone());
+    expected.step();
+    assert_eq!(expected.generations, automaton.generations);
+    assert_eq!(expected.get_population(), automaton.get_population());
+    assert_eq!(
+        get_non_default_set(&expected.tree.slice),
+        get_non_default_set(&automaton.tree.slice)
+    );
+
+    automaton.reset_hyperspeed();
+    assert_eq!(Some(0), automaton.get_hyperspeed_exponent());
+    assert_eq!(BigInt::from(1), *automaton.get_step_size());
+    automaton.set_step_size(BigInt::from(10));
+    assert_eq!(None, automaton.get_hyperspeed_exponent());
+    automaton.reset_hyperspeed();
+    assert_eq!(BigInt::from(10), *automaton.get_step_size());
+}
@@ -0,0 +1,120 @@
+//! Randomized tests checking that each simulation algorithm matches a simple
+//! simulation that doesn't use HashLife, for many kinds of rules.
+
+use proptest::prelude::*;
+
+use super::*;
+
+/// Rules to test in 1D, along with the number of states used by each one.
+const RULES_1D: &[(&str, u8)] = &[
+    ("W30", 2),
+    ("W110", 2),
+    ("W1", 2),
+    ("T26,K2,R2", 2),
+    ("MS,D0;2;1;3", 2),
+];
+
+/// Rules to test in 2D, along with the number of states used by each one.
+const RULES_2D: &[(&str, u8)] = &[
+    ("B3/S23", 2),
+    ("B36/S23/N2", 2),
+    ("B2/S/C3", 3),
+    ("B036/S1237", 2),
+    ("B0/S1/C3", 3),
+    ("B2n3/S23-q", 2),
+    ("R3,C0,M0,S2..4,B3..3,NN", 2),
+    ("WireWorld", 4),
+    ("345/2/4", 4),
+    ("BBM", 2),
+    ("Critters", 2),
+    ("Tron", 2),
+];
+
+/// Rules to test in 3D, along with the number of states used by each one.
+const RULES_3D: &[(&str, u8)] = &[("B4/S45", 2), ("B5/S45/C5", 5)];
+
+/// Checks that the given algorithm matches the reference simulation for the
+/// given number of steps of the given size, starting from a random soup of the
+/// given size and optionally on an odd generation.
+fn assert_equivalent<D: Dim>(
+    algorithm: Algorithm,
+    (rule_str, states): (&str, u8),
+    size: isize,
+    seed: u32,
+    odd_generation: bool,
+    step_size: usize,
+    steps: usize,
+) {
+    let rule = rule::from_str::<D>(rule_str).unwrap();
+    let mut sim = Simulation::with_algorithm(algorithm, rule.clone(), BigInt::from(step_size));
+    sim.set_generation_count(&BigInt::from(odd_generation as u8));
+    let background = sim.get_background();
+    let cells = make_soup_with_seed(size, states, seed);
+    let mut tree = NdTree::new();
+    for (pos, cell_state) in &cells {
+        tree.set_cell(&pos.convert(), *cell_state);
+    }
+    // The tree stores cells relative to the background.
+    let cells = cells
+        .into_iter()
+        .map(|(pos, cell_state)| (pos, stored_state(cell_state, background)))
+        .collect();
+    let mut expected = ReferenceSim::new(rule, cells, background, odd_generation);
+    for _ in 0..steps {
+        sim.step(&mut tree);
+        for _ in 0..step_size {
+            expected.step();
+        }
+        assert_eq!(expected.get_background(), sim.get_background());
+        expected.assert_matches(&tree, sim.get_background());
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        ..Default::default()
+    })]
+
+    /// Tests each algorithm against the reference simulation for 1D rules.
+    #[test]
+    fn test_equivalence_1d(
+        algorithm in prop::sample::select(Algorithm::ALL),
+        rule in prop::sample::select(RULES_1D),
+        size in 1..=32_isize,
+        seed: u32,
+        odd_generation: bool,
+        step_size in 1..=16_usize,
+        steps in 1..=4_usize,
+    ) {
+        assert_equivalent::<Dim1D>(algorithm, rule, size, seed, odd_generation, step_size, steps);
+    }
+
+    /// Tests each algorithm against the reference simulation for 2D rules.
+    #[test]
+    fn test_equivalence_2d(
+        algorithm in prop::sample::select(Algorithm::ALL),
+        rule in prop::sample::select(RULES_2D),
+        size in 1..=12_isize,
+        seed: u32,
+        odd_generation: bool,
+        step_size in 1..=16_usize,
+        steps in 1..=4_usize,
+    ) {
+        assert_equivalent::<Dim2D>(algorithm, rule, size, seed, odd_generation, step_size, steps);
+    }
+
+    /// Tests each algorithm against the reference simulation for 3D rules.
+    #[test]
+    fn test_equivalence_3d(
+        algorithm in prop::sample::select(Algorithm::ALL),
+        rule in prop::sample::select(RULES_3D),
+        size in 1..=5_isize,
+        seed: u32,
+        odd_generation: bool,
+        step_size in 1..=4_usize,
+        steps in 1..=3_usize,
+    ) {
+        assert_equivalent::<Dim3D>(algorithm, rule, size, seed, odd_generation, step_size, steps);
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+
+/// Checks that a pattern moves the given displacement every generation,
+/// keeping the state of every cell, when simulated with the given step sizes.
+fn assert_moves<D: Dim>(
+    rule_str: &str,
+    cells: Vec<(IVec<D>, u8)>,
+    displacement: IVec<D>,
+    step_sizes: &[usize],
+    generations: usize,
+) {
+    let rule = rule::from_str_uncompiled::<D>(rule_str).unwrap();
+    for &step_size in step_sizes {
+        let mut sim = Simulation::new(rule.clone(), BigInt::from(step_size));
+        let mut tree = NdTree::new();
+        for (pos, cell_state) in &cells {
+            tree.set_cell(&pos.convert(), *cell_state);
+        }
+        for generation in (step_size..=generations).step_by(step_size) {
+            sim.step(&mut tree);
+            let offset = &displacement * generation as isize;
+            let expected: HashSet<_> = cells
+                .iter()
+                .map(|(pos, cell_state)| (pos.clone() + offset.clone(), *cell_state))
+                .collect();
+            assert_eq!(
+                expected,
+                get_cells(&tree).into_iter().collect::<HashSet<_>>(),
+                "Wrong cells after {} generations",
+                generation
+            );
+        }
+    }
+}
+
+#[test]
+fn test_brians_brain() {
+    // A pair of live cells moves one cell per generation, leaving a trail of
+    // dying cells behind it.
+    let mut automaton: NdAutomaton<Dim2D> =
+        rle::RleEncode::from_rle("x = 2, y = 2, rule = B2/S/C3\n2o$2B!").unwrap();
+    automaton.sim.step(&mut automaton.tree);
+    let expected = "#CXRLE Pos=0,-1 Gen=0\nx = 2, y = 2, rule = B2/S/C3\n2A$2B!\n";
+    assert_eq!(expected, rle::RleEncode::to_rle(&automaton));
+
+    let reference_rule = ReferenceRule::<Dim2D>::generations(moore_neighbors(), &[2], &[], 3);
+    assert_matches_reference("B2/S/C3", reference_rule, make_soup(12, 3), 1, 40);
+}
+
+#[test]
+fn test_star_wars() {
+    let rule = "345/2/4";
+    let reference_rule = ReferenceRule::generations(moore_neighbors(), &[2], &[3, 4, 5], 4);
+    assert_matches_reference(rule, reference_rule, make_soup::<Dim2D>(12, 4), 1, 40);
+
+    // A pair of live cells followed by two pairs of dying cells is a
+    // spaceship that moves one cell per generation: each live cell has only
+    // one live neighbor, so it starts dying, and the two cells in front are
+    // born.
+    let spaceship: Vec<(IVec2D, u8)> = vec![
+        (NdVec([0, 0]), 1),
+        (NdVec([1, 0]), 1),
+        (NdVec([0, 1]), 2),
+        (NdVec([1, 1]), 2),
+        (NdVec([0, 2]), 3),
+        (NdVec([1, 2]), 3),
+    ];
+    assert_moves(rule, spaceship, NdVec([0, -1]), &[1, 4, 16], 64);
+}
+
+#[test]
+fn test_generations_3d() {
+    // 445, which grows from a soup instead of dying out.
+    let rule = "B4/S4/C5";
+    let reference_rule = ReferenceRule::generations(moore_neighbors(), &[4], &[4], 5);
+    assert_matches_reference(rule, reference_rule, make_soup::<Dim3D>(6, 2), 1, 20);
+
+    // A 2x2 plate of live cells followed by a trail of dying cells is a
+    // spaceship that moves one cell per generation: each live cell has only
+    // three live neighbors, so it starts dying, and the cells in front with
+    // four live neighbors are born.
+    for &direction in &[1, -1] {
+        let mut spaceship: Vec<(IVec3D, u8)> = vec![];
+        for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+            for cell_state in 1..5 {
+                let z = direction * (5 - cell_state as isize);
+                spaceship.push((NdVec([x, y, z]), cell_state));
+            }
+        }
+        assert_moves(rule, spaceship, NdVec([0, 0, direction]), &[1, 4, 16], 64);
+    }
+
+    // A single plate turns into a pair of these spaceships, along with a ring
+    // of cells around it that soon dies out.
+    let mut automaton = NdAutomaton::<Dim3D> {
+        sim: Simulation::new(rule::from_str(rule).unwrap(), BigInt::from(8)),
+        ..Default::default()
+    };
+    for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+        automaton.tree.set_cell(&NdVec::big([x, y, 0]), 1);
+    }
+    automaton.step();
+    assert_eq!(BigInt::from(32), automaton.tree.get_root().population);
+    for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+        assert_eq!(1, automaton.tree.get_cell(&NdVec::big([x, y, 8])));
+        assert_eq!(1, automaton.tree.get_cell(&NdVec::big([x, y, -8])));
+    }
+}
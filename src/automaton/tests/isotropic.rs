@@ -0,0 +1,71 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::*;
+
+fn load_rle_with_rule(rle: &str, rule: rule::Isotropic2D) -> NdAutomaton<Dim2D> {
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    automaton.sim = Simulation::new(Arc::new(rule), BigInt::from(1));
+    automaton
+}
+
+#[test]
+fn test_isotropic_totalistic_equivalence() {
+    // Life, written with every Hensel letter spelled out.
+    let rule = rule::Isotropic2D::try_from("B3cekainyqjr/S2cekain3cekainyqjr").unwrap();
+    let soup =
+        "x = 8, y = 8, rule = B3/S23\nb2obo$o2b3o$3obob2o$bo3bo$2b2ob2o$obo2bobo$b4o$o2bobo!";
+    let mut life: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(soup).unwrap();
+    let mut isotropic = load_rle_with_rule(soup, rule);
+    for _ in 0..50 {
+        life.sim.step(&mut life.tree);
+        isotropic.sim.step(&mut isotropic.tree);
+        assert_eq!(
+            get_non_default_set(&life.tree.slice),
+            get_non_default_set(&isotropic.tree.slice)
+        );
+    }
+}
+
+#[test]
+fn test_isotropic_hensel_letters() {
+    let domino = "x = 2, y = 1\n2o!";
+
+    // Each cell next to the long side of a domino sees two adjacent neighbors.
+    let rule = rule::Isotropic2D::try_from("B2a/S").unwrap();
+    let mut automaton = load_rle_with_rule(domino, rule);
+    automaton.sim.step(&mut automaton.tree);
+    assert_eq!(
+        make_cell_coords_set(vec![
+            NdVec([0, 0]),
+            NdVec([1, 0]),
+            NdVec([0, -2]),
+            NdVec([1, -2]),
+        ]),
+        get_non_default_set(&automaton.tree.slice)
+    );
+
+    let rule = rule::Isotropic2D::try_from("B2-a/S").unwrap();
+    let mut automaton = load_rle_with_rule(domino, rule);
+    automaton.sim.step(&mut automaton.tree);
+    assert!(get_non_default_set(&automaton.tree.slice).is_empty());
+
+    // The cell between a diagonal pair of cells sees a 2n neighborhood.
+    let rule = rule::Isotropic2D::try_from("B2n3/S23-q").unwrap();
+    let mut automaton = load_rle_with_rule("x = 3, y = 3\no$$2bo!", rule);
+    automaton.sim.step(&mut automaton.tree);
+    assert_eq!(
+        make_cell_coords_set(vec![NdVec([1, -2])]),
+        get_non_default_set(&automaton.tree.slice)
+    );
+
+    // The center cell sees a 3q neighborhood, so it survives only if 3q is
+    // included.
+    let rle = "x = 3, y = 3\nb2o$bo$o!";
+    for &(rule_str, expected) in &[("B2n3/S23-q", 0), ("B2n3/S23", 1)] {
+        let rule = rule::Isotropic2D::try_from(rule_str).unwrap();
+        let mut automaton = load_rle_with_rule(rle, rule);
+        automaton.sim.step(&mut automaton.tree);
+        assert_eq!(expected, automaton.tree.get_cell(&NdVec::big([1, -2])));
+    }
+}
@@ -0,0 +1,149 @@
+use std::ops::RangeInclusive;
+
+use super::*;
+
+/// The parameters of a Larger-than-Life rule, for the reference simulation.
+#[derive(Clone)]
+struct LtlParams {
+    neighbors: Vec<IVec2D>,
+    states: u8,
+    include_middle: bool,
+    survival: RangeInclusive<usize>,
+    birth: RangeInclusive<usize>,
+}
+
+impl LtlParams {
+    /// Returns a reference implementation of the rule.
+    fn reference_rule(&self) -> ReferenceRule<Dim2D> {
+        // If the middle cell is included, then a live cell counts itself, so
+        // it survives with one fewer live neighbor.
+        let survival: Vec<usize> = if self.include_middle {
+            self.survival
+                .clone()
+                .filter_map(|n| n.checked_sub(1))
+                .collect()
+        } else {
+            self.survival.clone().collect()
+        };
+        let birth: Vec<usize> = self.birth.clone().collect();
+        ReferenceRule::generations(self.neighbors.clone(), &birth, &survival, self.states)
+    }
+}
+
+/// Returns the offsets of the cells within the given squared Euclidean
+/// distance, not including the center cell.
+fn circle(radius: isize, max_dist_squared: isize) -> Vec<IVec2D> {
+    IRect2D::centered(NdVec::origin(), radius)
+        .iter()
+        .filter(|&offset| {
+            offset != NdVec::origin()
+                && offset[X] * offset[X] + offset[Y] * offset[Y] <= max_dist_squared
+        })
+        .collect()
+}
+
+fn bosco_params() -> LtlParams {
+    LtlParams {
+        neighbors: IRect2D::centered(NdVec::origin(), 5)
+            .iter()
+            .filter(|&offset| offset != NdVec::origin())
+            .collect(),
+        states: 2,
+        include_middle: true,
+        survival: 34..=58,
+        birth: 34..=45,
+    }
+}
+
+#[test]
+fn test_bosco() {
+    let rule = "R5,C0,M1,S34..58,B34..45,NM";
+    // Bosco's Rule needs a denser soup than usual, so use every non-empty cell
+    // of a 3-state soup as a live cell.
+    let soup: Vec<_> = make_soup::<Dim2D>(30, 3)
+        .into_iter()
+        .map(|(pos, _)| (pos, 1))
+        .collect();
+    assert_matches_reference(rule, bosco_params().reference_rule(), soup.clone(), 1, 30);
+    // Larger steps exercise HashLife's recursive algorithm at radius 5.
+    assert_matches_reference(rule, bosco_params().reference_rule(), soup, 8, 4);
+}
+
+/// Tests the most common spaceship in Bosco's Rule, which moves 5 cells to the
+/// left every 6 generations.
+#[test]
+fn test_bosco_spaceship() {
+    let rle = "x = 11, y = 10, rule = R5,C0,M1,S34..58,B34..45,NM
+5b3o$3b6o$b9o$b5o3b2o$5o5bo$5o5bo$b5o3b2o$b9o$3b6o$5b3o!";
+    let start: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    let cells = get_non_default_set(&start.tree.slice);
+    assert_eq!(62, cells.len());
+    for &step_size in &[1, 6, 60] {
+        let mut automaton = start.clone();
+        automaton.set_step_size(BigInt::from(step_size));
+        for generations in (step_size..=60).step_by(step_size) {
+            automaton.step();
+            if generations % 6 == 0 {
+                let displacement = NdVec::big([-5 * generations as isize / 6, 0]);
+                let expected: HashSet<_> = cells.iter().map(|pos| pos + &displacement).collect();
+                assert_eq!(expected, get_non_default_set(&automaton.tree.slice));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ltl_von_neumann_and_circular() {
+    let params = LtlParams {
+        neighbors: IRect2D::centered(NdVec::origin(), 3)
+            .iter()
+            .filter(|&offset| offset != NdVec::origin() && offset[X].abs() + offset[Y].abs() <= 3)
+            .collect(),
+        states: 2,
+        include_middle: false,
+        survival: 2..=4,
+        birth: 3..=3,
+    };
+    let soup = make_soup::<Dim2D>(16, 2);
+    assert_matches_reference(
+        "R3,C0,M0,S2..4,B3..3,NN",
+        params.reference_rule(),
+        soup,
+        1,
+        20,
+    );
+
+    let params = LtlParams {
+        survival: 8..=14,
+        birth: 9..=11,
+        neighbors: circle(4, 20),
+        ..params
+    };
+    let soup = make_soup::<Dim2D>(16, 2);
+    assert_matches_reference(
+        "R4,C0,M0,S8..14,B9..11,NC",
+        params.reference_rule(),
+        soup,
+        1,
+        20,
+    );
+}
+
+#[test]
+fn test_ltl_multi_state() {
+    let params = LtlParams {
+        states: 4,
+        survival: 6..=10,
+        birth: 5..=6,
+        neighbors: circle(3, 12),
+        include_middle: true,
+    };
+    let soup = make_soup::<Dim2D>(16, 4);
+    assert_matches_reference(
+        "R3,C4,M1,S6..10,B5..6,NC",
+        params.reference_rule(),
+        soup,
+        1,
+        20,
+    );
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+/// Returns the position and state of every non-empty cell in an RLE pattern.
+fn rle_cells(rle: &str) -> Vec<(IVec2D, u8)> {
+    let automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    get_cells(&automaton.tree)
+}
+
+/// Returns a reference implementation of the Life-like rule with the given
+/// neighborhood.
+fn lifelike(birth: &[usize], survival: &[usize], neighbors: &[[isize; 2]]) -> ReferenceRule<Dim2D> {
+    let neighbors = neighbors.iter().map(|&offset| NdVec(offset)).collect();
+    ReferenceRule::generations(neighbors, birth, survival, 2)
+}
+
+const MOORE: &[[isize; 2]] = &[
+    [-1, -1],
+    [0, -1],
+    [1, -1],
+    [-1, 0],
+    [1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+];
+const VON_NEUMANN: &[[isize; 2]] = &[[0, -1], [-1, 0], [1, 0], [0, 1]];
+const HEXAGONAL: &[[isize; 2]] = &[[0, -1], [1, -1], [-1, 0], [1, 0], [-1, 1], [0, 1]];
+
+#[test]
+fn test_highlife() {
+    // The HighLife replicator copies itself every 12 generations.
+    let replicator = "x = 5, y = 5, rule = B36/S23\n2b3o$bo2bo$o3bo$o2bo$3o!";
+    let reference_rule = lifelike(&[3, 6], &[2, 3], MOORE);
+    assert_matches_reference("B36/S23", reference_rule, rle_cells(replicator), 1, 48);
+
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(replicator).unwrap();
+    automaton.sim.set_step_size(BigInt::from(12));
+    automaton.sim.step(&mut automaton.tree);
+    assert_eq!(BigInt::from(24), automaton.tree.get_root().population);
+}
+
+#[test]
+fn test_seeds() {
+    // A domino gives birth to a pair of dominoes and then dies.
+    let mut automaton: NdAutomaton<Dim2D> =
+        rle::RleEncode::from_rle("x = 2, y = 1, rule = B2/S\n2o!").unwrap();
+    automaton.sim.step(&mut automaton.tree);
+    assert_eq!(
+        make_cell_coords_set(vec![
+            NdVec([0, 0]),
+            NdVec([1, 0]),
+            NdVec([0, -2]),
+            NdVec([1, -2]),
+        ]),
+        get_non_default_set(&automaton.tree.slice)
+    );
+
+    let rle = "x = 5, y = 4, rule = Seeds\nbo2bo$o$3bo$b2o!";
+    assert_matches_reference("Seeds", lifelike(&[2], &[], MOORE), rle_cells(rle), 1, 40);
+}
+
+#[test]
+fn test_day_and_night() {
+    let rle = "x = 6, y = 6, rule = B3678/S34678\n2b3o$2b2obo$o2b2o$5o$bo3bo$b3obo!";
+    let reference_rule = lifelike(&[3, 6, 7, 8], &[3, 4, 6, 7, 8], MOORE);
+    assert_matches_reference("B3678/S34678", reference_rule, rle_cells(rle), 1, 40);
+}
+
+#[test]
+fn test_lifelike_neighborhoods() {
+    let rle = "x = 3, y = 3, rule = B1/S012V\nbo$obo$2o!";
+    let reference_rule = lifelike(&[1], &[0, 1, 2], VON_NEUMANN);
+    assert_matches_reference("B1/S012V", reference_rule, rle_cells(rle), 1, 20);
+    let rle = "x = 6, y = 6, rule = B2/S34H\nobo2bo$2obo$o2bo$2b4o$o2b2o$2bo2bo!";
+    let reference_rule = lifelike(&[2], &[3, 4], HEXAGONAL);
+    assert_matches_reference("B2/S34H", reference_rule, rle_cells(rle), 1, 30);
+}
@@ -0,0 +1,59 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::*;
+
+/// Checks that a rule gives the same results with and without a lookup table.
+fn assert_lut_matches<D: Dim>(rule_str: &str, cells: Vec<(IVec<D>, u8)>, generations: usize) {
+    let rule = rule::from_str_uncompiled::<D>(rule_str).unwrap();
+    let lut_rule = rule::LutRule::new(rule.clone()).expect("Lookup table too large");
+    assert_rules_match(Arc::new(lut_rule), rule, cells, generations);
+}
+
+#[test]
+fn test_lut_2d() {
+    assert_lut_matches::<Dim2D>("B3/S23", make_soup(16, 2), 40);
+    assert_lut_matches::<Dim2D>("B36/S23", make_soup(16, 2), 40);
+    assert_lut_matches::<Dim2D>("B2n3/S23-q", make_soup(16, 2), 40);
+    assert_lut_matches::<Dim2D>("B2/S/C3", make_soup(16, 3), 40);
+    assert_lut_matches::<Dim2D>("B1/S1/N1", make_soup(16, 2), 20);
+}
+
+#[test]
+fn test_lut_wireworld() {
+    let cells = make_soup::<Dim2D>(16, 4)
+        .into_iter()
+        .map(|(pos, cell_state)| (pos, if cell_state == 1 { 1 } else { 3 }))
+        .collect();
+    assert_lut_matches("WireWorld", cells, 30);
+}
+
+#[test]
+fn test_lut_1d() {
+    assert_lut_matches::<Dim1D>("W110", make_soup(64, 2), 100);
+    assert_lut_matches::<Dim1D>("T777,K3", make_soup(64, 3), 100);
+}
+
+#[test]
+fn test_lut_script() {
+    // A script with an asymmetric neighborhood, to make sure that the cells
+    // of the napkin are looked up in the right order.
+    let script = "
+        @states 3
+        let n = cell(1, 0) + 2 * cell(0, -1) + cell(-1, 1);
+        if this == 0 { n == 2 || n == 4 } else { (this + n) % 3 }
+    ";
+    let rule = rule::ScriptRule::<Dim2D>::parse("Test", script).unwrap();
+    let lut_rule = rule::LutRule::new(Arc::new(rule.clone())).unwrap();
+    assert_rules_match(Arc::new(lut_rule), Arc::new(rule), make_soup(16, 3), 20);
+}
+
+#[test]
+fn test_lut_fallback() {
+    // Rules with napkins that are too large are returned unchanged.
+    let rule = rule::Generations::<Dim3D>::try_from("B5/S45/C5").unwrap();
+    assert!(rule::LutRule::new(Arc::new(rule.clone())).is_none());
+    let rule = rule::LutRule::compile(Arc::new(rule));
+    assert_eq!("B5/S45/C5", rule.to_string());
+    assert_eq!(5, rule.num_states());
+}
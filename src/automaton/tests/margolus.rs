@@ -0,0 +1,100 @@
+use std::convert::TryFrom;
+
+use super::*;
+
+/// Checks that HashLife matches the reference simulation, stepping the given
+/// number of generations at a time.
+fn assert_matches_reference<D: Dim>(
+    rule_str: &str,
+    cells: Vec<(IVec<D>, u8)>,
+    odd_generation: bool,
+    step_size: usize,
+    steps: usize,
+) {
+    let rule = rule::from_str::<D>(rule_str).unwrap();
+    let mut sim = Simulation::new(rule.clone(), BigInt::from(step_size));
+    sim.set_generation_count(&BigInt::from(odd_generation as u8));
+    let mut tree = NdTree::new();
+    for (pos, cell_state) in &cells {
+        tree.set_cell(&pos.convert(), *cell_state);
+    }
+    // The tree stores cells relative to the background, so if the background
+    // starts out full then the live cells in the tree are really dead.
+    let background = sim.get_background();
+    let cells = cells
+        .into_iter()
+        .map(|(pos, cell_state)| (pos, stored_state(cell_state, background)))
+        .collect();
+    let mut expected = ReferenceSim::new(rule, cells, background, odd_generation);
+    for _ in 0..steps {
+        sim.step(&mut tree);
+        for _ in 0..step_size {
+            expected.step();
+        }
+        assert_eq!(expected.get_background(), sim.get_background());
+        expected.assert_matches(&tree, sim.get_background());
+    }
+    assert!(!expected.is_empty(), "pattern died out");
+}
+
+#[test]
+fn test_billiard_ball_model() {
+    // The billiard ball model leaves empty blocks empty, so it can be
+    // simulated one generation at a time, starting on either parity.
+    assert_matches_reference::<Dim2D>("BBM", make_soup(16, 2), false, 1, 50);
+    assert_matches_reference::<Dim2D>("BBM", make_soup(16, 2), true, 1, 50);
+    assert_matches_reference::<Dim2D>("BBM", make_soup(16, 2), false, 7, 10);
+    assert_matches_reference::<Dim2D>("BBM", make_soup(16, 2), true, 16, 5);
+}
+
+#[test]
+fn test_critters() {
+    // Critters and Tron complement empty blocks, so the background flashes
+    // every other generation.
+    assert_matches_reference::<Dim2D>("Critters", make_soup(16, 2), false, 1, 30);
+    assert_matches_reference::<Dim2D>("Critters", make_soup(16, 2), true, 1, 30);
+    assert_matches_reference::<Dim2D>("Critters", make_soup(16, 2), false, 2, 15);
+    assert_matches_reference::<Dim2D>("Critters", make_soup(16, 2), true, 7, 5);
+    assert_matches_reference::<Dim2D>("Tron", make_soup(16, 2), false, 1, 30);
+    assert_matches_reference::<Dim2D>("Tron", make_soup(16, 2), false, 3, 10);
+}
+
+#[test]
+fn test_margolus_1d_3d() {
+    // Swap the two cells of each block, so that every cell moves back and
+    // forth.
+    assert_matches_reference::<Dim1D>("MS,D0;2;1;3", make_soup(32, 2), false, 1, 20);
+    // Rotate each block around the Z axis, which can be seen as four balls
+    // chasing each other in a square.
+    let rotate = |n: usize| {
+        // Map the bits for (x, y) in each Z layer to (y, 1 - x), using the
+        // MCell ordering where the upper left cell has the lowest bit.
+        let mut ret = 0;
+        for z in 0..2 {
+            let layer = n >> (4 * z) & 15;
+            let rotated = (layer & 1) << 1 | (layer & 2) << 2 | (layer & 4) >> 2 | (layer & 8) >> 1;
+            ret |= rotated << (4 * z);
+        }
+        ret
+    };
+    let table: Vec<String> = (0..256).map(|n| rotate(n).to_string()).collect();
+    let rule_str = format!("MS,D{}", table.join(";"));
+    assert!(rule::MargolusRule::<Dim3D>::try_from(rule_str.as_str()).is_ok());
+    assert_matches_reference::<Dim3D>(&rule_str, make_soup(6, 2), false, 1, 12);
+}
+
+#[test]
+fn test_margolus_automaton_parity() {
+    // The automaton tells the simulation whether the generation is odd.
+    let rle = "#CXRLE Pos=0,0 Gen=1\nx = 2, y = 1, rule = BBM\n2o!";
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    assert_eq!(BigInt::from(1), automaton.generations);
+    automaton.step_single();
+    // On an odd generation, the two balls are in different blocks, so they
+    // each move diagonally away from each other. (On an even generation they
+    // would be in the same block and stay still.)
+    assert_eq!(
+        make_cell_coords_set(vec![NdVec([-1, 0]), NdVec([2, 0])]),
+        get_non_default_set(&automaton.tree.slice)
+    );
+}
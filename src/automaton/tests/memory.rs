@@ -0,0 +1,117 @@
+use super::*;
+
+const GOSPER_GLIDER_GUN_RLE: &str = "x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bo
+bo$10bo5bo7bo$11bo3bo$12b2o!";
+
+#[test]
+fn test_memory_budget() {
+    let mut unlimited: NdAutomaton<Dim2D> =
+        rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    unlimited.set_memory_budget(usize::MAX);
+    // Don't clone the automaton, because clones share the same cache.
+    let mut limited: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    // Any cached results at all are too many.
+    limited.set_memory_budget(0);
+    for step_size in &[1, 7, 30, 64] {
+        unlimited.set_step_size(BigInt::from(*step_size));
+        limited.set_step_size(BigInt::from(*step_size));
+        for _ in 0..10 {
+            unlimited.step();
+            limited.step();
+            assert_eq!(
+                get_non_default_set(&unlimited.tree.slice),
+                get_non_default_set(&limited.tree.slice)
+            );
+        }
+    }
+
+    let unlimited_stats = unlimited.get_cache_stats();
+    let limited_stats = limited.get_cache_stats();
+    assert_eq!(0, unlimited_stats.gc_count);
+    // Garbage is collected during each step as well as after it.
+    assert!(limited_stats.gc_count > 40);
+    assert!(limited_stats.result_count < unlimited_stats.result_count);
+    assert!(limited_stats.node_count < unlimited_stats.node_count);
+    assert!(limited_stats.memory_usage < unlimited_stats.memory_usage);
+}
+
+#[test]
+fn test_garbage_collection() {
+    let mut automaton: NdAutomaton<Dim2D> =
+        rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    let mut expected: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    automaton.set_step_size(BigInt::from(30));
+    expected.set_step_size(BigInt::from(30));
+    // Clones share the same results cache, like the undo history does.
+    let history = automaton.clone();
+    let initial_cells = get_non_default_set(&history.tree.slice);
+    for _ in 0..10 {
+        automaton.step();
+        expected.step();
+    }
+    assert_eq!(history.get_cache_stats(), automaton.get_cache_stats());
+    let before = automaton.get_cache_stats();
+
+    automaton.sim.collect_garbage(&automaton.tree);
+    let after = automaton.get_cache_stats();
+    assert_eq!(1, after.gc_count);
+    assert!(0 < after.result_count && after.result_count < before.result_count);
+    assert!(after.node_count < before.node_count);
+    assert!(after.memory_usage < before.memory_usage);
+    // The nodes in the old pattern are still held onto by the history.
+    assert_eq!(initial_cells, get_non_default_set(&history.tree.slice));
+
+    for _ in 0..5 {
+        automaton.step();
+        expected.step();
+        assert_eq!(
+            get_non_default_set(&expected.tree.slice),
+            get_non_default_set(&automaton.tree.slice)
+        );
+    }
+}
+
+#[test]
+fn test_garbage_collection_during_step() {
+    let mut unlimited: NdAutomaton<Dim2D> =
+        rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    unlimited.set_memory_budget(usize::MAX);
+    let mut limited: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+    limited.set_memory_budget(0);
+    // A single large step, like the ones that hyperspeed reaches.
+    unlimited.set_step_size(BigInt::from(1) << 12);
+    limited.set_step_size(BigInt::from(1) << 12);
+    unlimited.step();
+    limited.step();
+    assert_eq!(
+        get_non_default_set(&unlimited.tree.slice),
+        get_non_default_set(&limited.tree.slice)
+    );
+    // Garbage is collected after each of the top-level node's branches, and
+    // again after the step.
+    assert_eq!(0, unlimited.get_cache_stats().gc_count);
+    assert!(limited.get_cache_stats().gc_count > 4);
+}
+
+#[test]
+fn test_garbage_collection_roots() {
+    let run = |keep_history: bool| {
+        let mut automaton: NdAutomaton<Dim2D> =
+            rle::RleEncode::from_rle(GOSPER_GLIDER_GUN_RLE).unwrap();
+        automaton.set_step_size(BigInt::from(30));
+        let mut history = vec![];
+        for _ in 0..10 {
+            history.push(automaton.clone());
+            automaton.step();
+        }
+        if keep_history {
+            let roots: Vec<GcRoot> = history.iter().map(|h| h.get_gc_root()).collect();
+            automaton.set_gc_roots(&roots);
+        }
+        automaton.sim.collect_garbage(&automaton.tree);
+        automaton.get_cache_stats().result_count
+    };
+    // The results for the patterns in the history are kept as well.
+    assert!(run(false) < run(true));
+}
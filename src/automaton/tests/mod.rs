@@ -1,3 +1,112 @@
+use num::Zero;
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use super::*;
+use reference::{
+    assert_matches_reference, moore_neighbors, stored_state, ReferenceRule, ReferenceSim,
+};
 
+mod algorithms;
+mod b0;
+mod cancellation;
 mod cgol;
+mod equivalence;
+mod generations;
+mod isotropic;
+mod larger_than_life;
+mod lifelike;
+mod lut;
+mod margolus;
+mod memory;
+mod outer_totalistic;
+mod parallel;
+mod reference;
+mod rule_file;
+mod script;
+mod topology;
+mod turmite;
+mod wireworld;
+mod wolfram;
+
+fn get_non_default_set<C: CellType, D: Dim>(slice: &NdTreeSlice<C, D>) -> HashSet<BigVec<D>> {
+    let mut ret = HashSet::new();
+    for (branch_idx, branch) in slice.root.branch_iter() {
+        let branch_offset = &slice.offset + slice.root.branch_offset(branch_idx);
+        match branch {
+            NdTreeBranch::Leaf(cell_state) => {
+                if *cell_state != C::default() {
+                    ret.insert(branch_offset);
+                }
+            }
+            // Skip empty nodes, which may be very large.
+            NdTreeBranch::Node(node) if node.population.is_zero() => (),
+            NdTreeBranch::Node(node) => ret.extend(get_non_default_set(&NdTreeSlice {
+                root: node.clone(),
+                offset: branch_offset,
+            })),
+        }
+    }
+    ret
+}
+
+/// Returns the position and state of every non-empty cell in the given tree.
+fn get_cells<D: Dim>(tree: &NdTree<u8, D>) -> Vec<(IVec<D>, u8)> {
+    get_non_default_set(&tree.slice)
+        .into_iter()
+        .map(|pos| (pos.as_ivec(), tree.get_cell(&pos)))
+        .collect()
+}
+
+fn make_cell_coords_set<D: Dim>(coords_vec: Vec<IVec<D>>) -> HashSet<BigVec<D>> {
+    coords_vec.iter().map(NdVec::convert).collect()
+}
+
+/// Returns a pseudorandom soup of non-empty cells in a hypercube of the given
+/// size, using cell states less than `states`.
+fn make_soup<D: Dim>(size: isize, states: u8) -> Vec<(IVec<D>, u8)> {
+    make_soup_with_seed(size, states, 12345)
+}
+
+/// Returns a pseudorandom soup like `make_soup()`, using the given seed.
+fn make_soup_with_seed<D: Dim>(size: isize, states: u8, mut seed: u32) -> Vec<(IVec<D>, u8)> {
+    IRect::<D>::new(NdVec::origin(), NdVec::repeat(size))
+        .iter()
+        .filter_map(|pos| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let cell_state = ((seed >> 16) % (states as u32 * 2)) as u8;
+            if 0 < cell_state && cell_state < states {
+                Some((pos, cell_state))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Steps two simulations of the same pattern with different rules and checks
+/// that they match at every generation.
+fn assert_rules_match<D: Dim>(
+    rule: Arc<dyn rule::Rule<u8, D>>,
+    expected_rule: Arc<dyn rule::Rule<u8, D>>,
+    cells: Vec<(IVec<D>, u8)>,
+    generations: usize,
+) {
+    let mut sim = Simulation::new(rule, BigInt::from(1));
+    let mut expected_sim = Simulation::new(expected_rule, BigInt::from(1));
+    let mut tree = NdTree::new();
+    for (pos, cell_state) in cells {
+        tree.set_cell(&pos.convert(), cell_state);
+    }
+    let mut expected_tree = tree.clone();
+    for _ in 0..generations {
+        sim.step(&mut tree);
+        expected_sim.step(&mut expected_tree);
+        let cells = get_non_default_set(&tree.slice);
+        assert_eq!(get_non_default_set(&expected_tree.slice), cells);
+        for pos in &cells {
+            assert_eq!(expected_tree.get_cell(pos), tree.get_cell(pos));
+        }
+    }
+    assert!(!tree.get_root().is_empty(), "pattern died out");
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use super::*;
+use rule::{NdNeighborhood, OuterTotalistic};
+
+#[test]
+fn test_outer_totalistic_life() {
+    // Compare against the 2D-specific implementation.
+    let soup =
+        "x = 8, y = 8, rule = B3/S23\nb2obo$o2b3o$3obob2o$bo3bo$2b2ob2o$obo2bobo$b4o$o2bobo!";
+    let mut life: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(soup).unwrap();
+    let mut outer_totalistic = life.clone();
+    let rule = OuterTotalistic::<Dim2D>::new(&[3], &[2, 3], NdNeighborhood::Moore, 1);
+    outer_totalistic.sim = Simulation::new(Arc::new(rule), BigInt::from(1));
+    for _ in 0..50 {
+        life.sim.step(&mut life.tree);
+        outer_totalistic.sim.step(&mut outer_totalistic.tree);
+        assert_eq!(
+            get_non_default_set(&life.tree.slice),
+            get_non_default_set(&outer_totalistic.tree.slice)
+        );
+    }
+}
+
+#[test]
+fn test_outer_totalistic_2d_neighborhoods() {
+    let neighbors = vec![
+        NdVec([0, -2]),
+        NdVec([-1, -1]),
+        NdVec([0, -1]),
+        NdVec([1, -1]),
+        NdVec([-2, 0]),
+        NdVec([-1, 0]),
+        NdVec([1, 0]),
+        NdVec([2, 0]),
+        NdVec([-1, 1]),
+        NdVec([0, 1]),
+        NdVec([1, 1]),
+        NdVec([0, 2]),
+    ];
+    let reference_rule = ReferenceRule::generations(neighbors, &[3, 4], &[2, 3, 4, 5], 2);
+    let soup = make_soup::<Dim2D>(10, 2);
+    assert_matches_reference("B3-4/S2-5/N2", reference_rule, soup, 1, 30);
+
+    let neighbors = vec![
+        NdVec([0, -2]),
+        NdVec([0, -1]),
+        NdVec([-2, 0]),
+        NdVec([-1, 0]),
+        NdVec([1, 0]),
+        NdVec([2, 0]),
+        NdVec([0, 1]),
+        NdVec([0, 2]),
+    ];
+    let reference_rule = ReferenceRule::generations(neighbors, &[2], &[1, 2], 2);
+    let soup = make_soup::<Dim2D>(10, 2);
+    assert_matches_reference("B2/S12/+2", reference_rule, soup, 1, 30);
+
+    let neighbors = vec![
+        NdVec([-1, -2]),
+        NdVec([1, -2]),
+        NdVec([-2, -1]),
+        NdVec([0, -1]),
+        NdVec([2, -1]),
+        NdVec([-1, 0]),
+        NdVec([1, 0]),
+        NdVec([-2, 1]),
+        NdVec([0, 1]),
+        NdVec([2, 1]),
+        NdVec([-1, 2]),
+        NdVec([1, 2]),
+    ];
+    let reference_rule = ReferenceRule::generations(neighbors, &[3], &[2, 3, 4], 2);
+    let soup = make_soup::<Dim2D>(10, 2);
+    assert_matches_reference("B3/S2-4/B2", reference_rule, soup, 1, 30);
+}
+
+#[test]
+fn test_outer_totalistic_3d() {
+    // Bays's 3D Life.
+    let reference_rule = ReferenceRule::generations(moore_neighbors(), &[5], &[4, 5], 2);
+    let soup = make_soup::<Dim3D>(8, 2);
+    assert_matches_reference("B5/S45", reference_rule, soup, 1, 15);
+}
+
+#[test]
+fn test_outer_totalistic_4d() {
+    let reference_rule =
+        ReferenceRule::generations(moore_neighbors(), &[9, 10], &[8, 9, 10, 11], 2);
+    let soup = make_soup::<Dim4D>(4, 2);
+    assert_matches_reference("B9-10/S8-11", reference_rule, soup, 1, 4);
+}
@@ -0,0 +1,56 @@
+use super::*;
+
+/// Checks that simulating a pattern on many threads produces exactly the same
+/// nodes as simulating it on a single thread.
+fn assert_parallel_matches<D: Dim>(
+    rule_str: &str,
+    cells: Vec<(IVec<D>, u8)>,
+    step_size: usize,
+    steps: usize,
+    thread_count: usize,
+) {
+    let rule = rule::from_str::<D>(rule_str).unwrap();
+    let mut single_threaded_sim = Simulation::new(rule.clone(), BigInt::from(step_size));
+    single_threaded_sim.set_thread_count(1);
+    let mut multi_threaded_sim = Simulation::new(rule, BigInt::from(step_size));
+    multi_threaded_sim.set_thread_count(thread_count);
+    let mut single_threaded_tree = NdTree::new();
+    for (pos, cell_state) in &cells {
+        single_threaded_tree.set_cell(&pos.convert(), *cell_state);
+    }
+    // Both trees share the same node cache, so identical nodes must be the
+    // same node.
+    let mut multi_threaded_tree = single_threaded_tree.clone();
+    for _ in 0..steps {
+        single_threaded_sim.step(&mut single_threaded_tree);
+        multi_threaded_sim.step(&mut multi_threaded_tree);
+        assert!(Arc::ptr_eq(
+            single_threaded_tree.get_root(),
+            multi_threaded_tree.get_root()
+        ));
+        assert_eq!(
+            single_threaded_tree.slice.offset,
+            multi_threaded_tree.slice.offset
+        );
+    }
+    assert!(
+        !single_threaded_tree.get_root().population.is_zero(),
+        "pattern died out"
+    );
+}
+
+#[test]
+fn test_parallel_2d() {
+    assert_parallel_matches::<Dim2D>("B3/S23", make_soup(64, 2), 1, 10, 8);
+    assert_parallel_matches::<Dim2D>("B3/S23", make_soup(32, 2), 32, 3, 8);
+    // More threads than branches
+    assert_parallel_matches::<Dim2D>("B36/S23", make_soup(32, 2), 32, 2, 7);
+    assert_parallel_matches::<Dim2D>("B2/S/C3", make_soup(32, 3), 16, 4, 3);
+}
+
+#[test]
+fn test_parallel_other_dimensions() {
+    assert_parallel_matches::<Dim1D>("W30", make_soup(64, 2), 16, 4, 4);
+    // Fewer threads than branches
+    assert_parallel_matches::<Dim3D>("B4/S45", make_soup(8, 2), 2, 3, 3);
+}
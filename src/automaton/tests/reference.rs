@@ -0,0 +1,345 @@
+//! A simple simulation that doesn't use HashLife, for checking HashLife.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::*;
+
+/// Returns the state that a cell is stored as in an NdTree when the infinite
+/// background is in the given state. (See `Simulation::get_background()`.)
+pub fn stored_state<C: CellType>(cell_state: C, background: C) -> C {
+    if cell_state == background {
+        C::default()
+    } else if cell_state == C::default() {
+        background
+    } else {
+        cell_state
+    }
+}
+
+/// Returns the offsets of the cells in the Moore neighborhood of radius 1,
+/// not including the center cell.
+pub fn moore_neighbors<D: Dim>() -> Vec<IVec<D>> {
+    IRect::<D>::centered(NdVec::origin(), 1)
+        .iter()
+        .filter(|offset| *offset != NdVec::origin())
+        .collect()
+}
+
+/// A rule defined by a function that computes the next state of the cell at
+/// the center of a napkin, for writing straightforward implementations of
+/// rules to check the real ones against.
+pub struct ReferenceRule<D: Dim> {
+    radius: usize,
+    transition: TransitionFn<D>,
+}
+/// The transition function of a ReferenceRule.
+type TransitionFn<D> = Box<dyn Fn(&NdArraySlice<u8, D>) -> u8 + Send + Sync>;
+impl<D: Dim> ReferenceRule<D> {
+    /// Constructs a rule that sees cells up to the given distance away.
+    pub fn new(
+        radius: usize,
+        transition: impl Fn(&NdArraySlice<u8, D>) -> u8 + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            radius,
+            transition: Box::new(transition),
+        }
+    }
+    /// Constructs a rule that sees the cells at the given offsets, and
+    /// computes the next state of a cell from its own state and the states of
+    /// those cells (in the same order as the offsets).
+    pub fn with_neighbors(
+        neighbors: Vec<IVec<D>>,
+        transition: impl Fn(u8, &[u8]) -> u8 + Send + Sync + 'static,
+    ) -> Self {
+        let radius = neighbors
+            .iter()
+            .flat_map(|offset| D::axes().iter().map(move |&ax| offset[ax].abs()))
+            .max()
+            .unwrap_or(0);
+        Self::new(radius as usize, move |napkin| {
+            let neighbor_states: Vec<u8> = neighbors.iter().map(|offset| napkin[offset]).collect();
+            transition(napkin[&NdVec::origin()], &neighbor_states)
+        })
+    }
+    /// Constructs a Generations rule with the given neighborhood, in which a
+    /// dead cell is born if the number of live neighbors is in `birth`, a live
+    /// cell survives if it is in `survival`, and every other cell that isn't
+    /// dead moves on to the next state, dying after the last one. (Life-like
+    /// rules are the ones with 2 states.)
+    pub fn generations(
+        neighbors: Vec<IVec<D>>,
+        birth: &[usize],
+        survival: &[usize],
+        states: u8,
+    ) -> Self {
+        let birth = birth.to_vec();
+        let survival = survival.to_vec();
+        Self::with_neighbors(neighbors, move |cell_state, neighbors| {
+            let live_neighbors = neighbors.iter().filter(|&&n| n == 1).count();
+            match cell_state {
+                0 if birth.contains(&live_neighbors) => 1,
+                0 => 0,
+                1 if survival.contains(&live_neighbors) => 1,
+                _ => (cell_state + 1) % states,
+            }
+        })
+    }
+}
+impl<D: Dim> fmt::Debug for ReferenceRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ReferenceRule {{ radius: {} }}", self.radius)
+    }
+}
+impl<D: Dim> fmt::Display for ReferenceRule<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Reference")
+    }
+}
+impl<D: Dim> rule::Rule<u8, D> for ReferenceRule<D> {
+    fn radius(&self) -> usize {
+        self.radius
+    }
+    fn transition(&self, napkin: &NdArraySlice<u8, D>) -> u8 {
+        (self.transition)(napkin)
+    }
+}
+
+/// Checks that HashLife simulating the rule with the given rule string
+/// matches the reference simulation of `reference_rule`, stepping the given
+/// number of generations at a time, starting from the given cells.
+///
+/// The rule is not compiled into a lookup table, so that its own transition
+/// function is checked. (`LutRule` is checked against it separately.)
+pub fn assert_matches_reference<D: Dim>(
+    rule_str: &str,
+    reference_rule: ReferenceRule<D>,
+    cells: Vec<(IVec<D>, u8)>,
+    step_size: usize,
+    steps: usize,
+) {
+    let rule = rule::from_str_uncompiled::<D>(rule_str).unwrap();
+    let mut sim = Simulation::new(rule, BigInt::from(step_size));
+    let mut tree = NdTree::new();
+    for (pos, cell_state) in &cells {
+        tree.set_cell(&pos.convert(), *cell_state);
+    }
+    let mut expected = ReferenceSim::new(Arc::new(reference_rule), cells, 0, false);
+    for _ in 0..steps {
+        sim.step(&mut tree);
+        for _ in 0..step_size {
+            expected.step();
+        }
+        expected.assert_matches(&tree, sim.get_background());
+    }
+    assert!(!expected.is_empty(), "pattern died out");
+}
+
+/// A dense simulation of any rule on a bounded region, without using HashLife.
+///
+/// On an unbounded grid, every cell outside the region is in the background
+/// state, which is simulated too so that rules where empty space does not stay
+/// empty (e.g. B0 rules) can be checked. The region grows by the radius of the
+/// rule each generation and then shrinks to fit the pattern. On a bounded grid,
+/// the region is the whole grid.
+pub struct ReferenceSim<C: CellType, D: Dim> {
+    rule: Arc<dyn rule::Rule<C, D>>,
+    /// The region containing every cell that differs from the background.
+    region: IRect<D>,
+    /// The state of every cell in the region.
+    cells: NdArray<C, D>,
+    /// The state of every cell outside the region.
+    background: C,
+    /// Whether the next generation to simulate is odd, which determines the
+    /// partition into blocks for block rules.
+    odd_generation: bool,
+}
+impl<C: CellType, D: Dim> ReferenceSim<C, D> {
+    /// Constructs a simulation of an unbounded grid containing the given cells,
+    /// with every other cell in the given background state.
+    pub fn new(
+        rule: Arc<dyn rule::Rule<C, D>>,
+        cells: Vec<(IVec<D>, C)>,
+        background: C,
+        odd_generation: bool,
+    ) -> Self {
+        let mut ret = Self {
+            rule,
+            region: IRect::single_cell(NdVec::origin()),
+            cells: NdArray::from_flat_vec(UVec::repeat(1_usize), vec![background]),
+            background,
+            odd_generation,
+        };
+        let mut positions = cells.iter().map(|(pos, _)| pos);
+        if let Some(first) = positions.next() {
+            let (min, max) = positions.fold((first.clone(), first.clone()), |(min, max), pos| {
+                (NdVec::min(&min, pos), NdVec::max(&max, pos))
+            });
+            ret.set_cells(IRect::span(min, max), |_| background);
+            for (pos, cell_state) in cells {
+                let idx = pos - ret.region.min();
+                ret.cells[&idx] = cell_state;
+            }
+        }
+        ret
+    }
+    /// Constructs a simulation of the given bounded grid containing the given
+    /// cells, with every other cell in the grid in the default state. Cells
+    /// outside the grid are ignored.
+    pub fn new_bounded(
+        rule: Arc<dyn rule::Rule<C, D>>,
+        topology: &Topology<D>,
+        cells: Vec<(IVec<D>, C)>,
+    ) -> Self {
+        let mut ret = Self::new(rule, vec![], C::default(), false);
+        ret.set_cells(topology.rect().unwrap(), |_| C::default());
+        for (pos, cell_state) in cells {
+            if topology.contains(&pos) {
+                let idx = pos - ret.region.min();
+                ret.cells[&idx] = cell_state;
+            }
+        }
+        ret
+    }
+
+    /// Returns the state of the cell at the given position.
+    pub fn get_cell(&self, pos: &IVec<D>) -> C {
+        if self.region.contains(pos) {
+            self.cells[&(pos - self.region.min())]
+        } else {
+            self.background
+        }
+    }
+    /// Returns the state of every cell outside the region.
+    pub fn get_background(&self) -> C {
+        self.background
+    }
+    /// Returns true if every cell is in the background state, or false
+    /// otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.region
+            .iter()
+            .all(|pos| self.get_cell(&pos) == self.background)
+    }
+
+    /// Replaces the region, computing the state of each cell in the new region
+    /// using the given function.
+    fn set_cells(&mut self, region: IRect<D>, f: impl FnMut(IVec<D>) -> C) {
+        let data = region.iter().map(f).collect();
+        self.cells = NdArray::from_flat_vec(region.size().as_uvec(), data);
+        self.region = region;
+    }
+
+    /// Returns the state of every cell in the given region after one
+    /// generation, using the given function to find the state of each cell in
+    /// the previous generation.
+    fn next_cells(&self, region: &IRect<D>, get_cell: &impl Fn(&IVec<D>) -> C) -> NdArray<C, D> {
+        let size = region.size().as_uvec();
+        match self.rule.as_block_rule() {
+            Some(block_rule) => {
+                let parity = self.odd_generation as isize;
+                let block_corner = |pos: &IVec<D>| {
+                    IVec::<D>::from_fn(|ax| pos[ax] - (pos[ax] - parity).rem_euclid(2))
+                };
+                let mut ret =
+                    NdArray::from_flat_vec(size, vec![C::default(); region.count() as usize]);
+                // Simulate each block that overlaps the region once.
+                for corner in IRect::span(block_corner(&region.min()), region.max()).iter() {
+                    if corner != block_corner(&corner) {
+                        continue;
+                    }
+                    let positions: Vec<IVec<D>> = (0..D::TREE_BRANCHES)
+                        .map(|idx| corner.clone() + rule::block_offset(idx))
+                        .collect();
+                    let mut block: Vec<C> = positions.iter().map(get_cell).collect();
+                    block_rule.transition_block(&mut block, self.odd_generation);
+                    for (pos, cell_state) in positions.into_iter().zip(block) {
+                        if region.contains(&pos) {
+                            ret[&(pos - region.min())] = cell_state;
+                        }
+                    }
+                }
+                ret
+            }
+            None => {
+                let radius = self.rule.radius() as isize;
+                let data = region
+                    .iter()
+                    .map(|pos| {
+                        let napkin_data = IRect::<D>::centered(pos, radius)
+                            .iter()
+                            .map(|pos| get_cell(&pos))
+                            .collect();
+                        let napkin = NdArray::from_flat_vec(
+                            UVec::repeat(2 * radius as usize + 1),
+                            napkin_data,
+                        );
+                        self.rule
+                            .transition(&napkin.offset_slice(-IVec::repeat(radius)))
+                    })
+                    .collect();
+                NdArray::from_flat_vec(size, data)
+            }
+        }
+    }
+
+    /// Simulates a single generation on an unbounded grid.
+    pub fn step(&mut self) {
+        let old_background = self.background;
+        let origin = IRect::single_cell(NdVec::origin());
+        let new_background = self.next_cells(&origin, &|_| old_background)[&NdVec::origin()];
+        let radius = self.rule.radius().max(1) as isize;
+        let new_region = self.region.clone().offset_min_max(-radius, radius);
+        let new_cells = self.next_cells(&new_region, &|pos| self.get_cell(pos));
+        let new_cells = new_region
+            .iter()
+            .map(|pos| {
+                let cell_state = new_cells[&(&pos - new_region.min())];
+                (pos, cell_state)
+            })
+            .filter(|&(_, cell_state)| cell_state != new_background)
+            .collect();
+        *self = Self::new(
+            self.rule.clone(),
+            new_cells,
+            new_background,
+            !self.odd_generation,
+        );
+    }
+    /// Simulates a single generation on the given bounded grid, which must be
+    /// the same one that this simulation was constructed with.
+    pub fn step_on(&mut self, topology: &Topology<D>) {
+        // Cells beyond the edges of a plane are dead.
+        let get_cell = |pos: &IVec<D>| match topology.wrap_pos(pos) {
+            Some(pos) => self.get_cell(&pos),
+            None => C::default(),
+        };
+        self.cells = self.next_cells(&self.region, &get_cell);
+        self.odd_generation = !self.odd_generation;
+    }
+
+    /// Checks that every cell in the region of the given NdTree, which stores
+    /// cells relative to the given background state, matches this simulation,
+    /// and that there are no other cells in the NdTree.
+    pub fn assert_matches(&self, tree: &NdTree<C, D>, background: C) {
+        let expected_cells: HashMap<BigVec<D>, C> = self
+            .region
+            .iter()
+            .filter(|pos| self.get_cell(pos) != background)
+            .map(|pos| (pos.convert(), stored_state(self.get_cell(&pos), background)))
+            .collect();
+        assert_eq!(
+            expected_cells.keys().cloned().collect::<HashSet<_>>(),
+            get_non_default_set(&tree.slice)
+        );
+        for (pos, &cell_state) in &expected_cells {
+            assert_eq!(
+                cell_state,
+                tree.get_cell(pos),
+                "Wrong cell state at {:?}",
+                pos
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::*;
+
+/// Conway's Game of Life as a rule table.
+const LIFE_TABLE: &str = "
+@RULE LifeTable
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+# Birth
+0,1,1,1,0,0,0,0,0,1
+# Survival
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+# Death
+1,a,b,c,d,e,f,g,h,0
+";
+
+/// Wireworld with the von Neumann neighborhood as a rule table.
+const WIREWORLD_VN_TABLE: &str = "
+@RULE WireWorldVN
+@TABLE
+n_states:4
+neighborhood:vonNeumann
+symmetries:permute
+var a={0,1,2,3}
+var b={a}
+var c={a}
+var d={a}
+var e={0,2,3}
+var f={e}
+var g={e}
+1,a,b,c,d,2
+2,a,b,c,d,3
+3,1,e,f,g,1
+3,1,1,e,f,1
+";
+
+/// Returns a Moore rule tree in Golly's format for the given function, which
+/// takes the states of the cells in the order NW, NE, SW, SE, N, W, E, S, C.
+fn make_moore_tree(num_states: u8, f: impl Fn(&[u8]) -> u8) -> String {
+    fn add_node(
+        prefix: &mut Vec<u8>,
+        num_states: u8,
+        f: &impl Fn(&[u8]) -> u8,
+        nodes: &mut Vec<String>,
+    ) -> usize {
+        let level = 9 - prefix.len();
+        let mut node = level.to_string();
+        for state in 0..num_states {
+            prefix.push(state);
+            let entry = if level == 1 {
+                f(prefix) as usize
+            } else {
+                add_node(prefix, num_states, f, nodes)
+            };
+            prefix.pop();
+            node += &format!(" {}", entry);
+        }
+        nodes.push(node);
+        nodes.len() - 1
+    }
+    let mut nodes = vec![];
+    add_node(&mut vec![], num_states, &f, &mut nodes);
+    format!(
+        "@RULE Tree\n@TREE\nnum_states={}\nnum_neighbors=8\nnum_nodes={}\n{}\n",
+        num_states,
+        nodes.len(),
+        nodes.join("\n")
+    )
+}
+
+#[test]
+fn test_life_table() {
+    let rule = rule::parse_rule_file(LIFE_TABLE).unwrap();
+    assert_eq!("LifeTable", rule.to_string());
+    assert_rules_match(rule, Arc::new(rule::LIFE), make_soup(16, 2), 40);
+}
+
+#[test]
+fn test_wireworld_von_neumann_table() {
+    let rule = rule::parse_rule_file(WIREWORLD_VN_TABLE).unwrap();
+    assert_eq!(4, rule.num_states());
+
+    // Simulate a soup of wire with some electrons without HashLife.
+    let mut cells: HashMap<IVec2D, u8> = HashMap::new();
+    for (pos, cell_state) in make_soup::<Dim2D>(16, 4) {
+        cells.insert(pos, if cell_state == 1 { 1 } else { 3 });
+    }
+    let mut automaton = NdAutomaton::<Dim2D> {
+        sim: Simulation::new(rule, BigInt::from(1)),
+        ..Default::default()
+    };
+    for (pos, &cell_state) in &cells {
+        automaton.tree.set_cell(&pos.convert(), cell_state);
+    }
+    for _ in 0..30 {
+        automaton.sim.step(&mut automaton.tree);
+        cells = cells
+            .iter()
+            .map(|(&pos, &cell_state)| {
+                let heads = [[0, 1], [1, 0], [0, -1], [-1, 0]]
+                    .iter()
+                    .filter(|&&offset| cells.get(&(pos + NdVec(offset))) == Some(&1))
+                    .count();
+                let new_state = match cell_state {
+                    1 => 2,
+                    2 => 3,
+                    _ if heads == 1 || heads == 2 => 1,
+                    _ => 3,
+                };
+                (pos, new_state)
+            })
+            .collect();
+        for (pos, &cell_state) in &cells {
+            assert_eq!(cell_state, automaton.tree.get_cell(&pos.convert()));
+        }
+    }
+}
+
+#[test]
+fn test_rule_tree() {
+    // Conway's Game of Life as a rule tree.
+    let tree = make_moore_tree(2, |cells| {
+        match (
+            cells[8],
+            cells[..8].iter().filter(|&&cell| cell == 1).count(),
+        ) {
+            (_, 3) | (1, 2) => 1,
+            _ => 0,
+        }
+    });
+    let rule = rule::parse_rule_file(&tree).unwrap();
+    assert_rules_match(rule, Arc::new(rule::LIFE), make_soup(16, 2), 40);
+
+    // Make sure the neighbors are looked up in the right order by using a rule
+    // where each cell copies its southeast neighbor, so that every pattern
+    // moves northwest (up and to the left).
+    let tree = make_moore_tree(2, |cells| cells[3]);
+    let rule = rule::parse_rule_file(&tree).unwrap();
+    let mut sim = Simulation::new(rule, BigInt::from(1));
+    let mut tree = NdTree::new();
+    tree.set_cell(&NdVec::big([0, 0]), 1);
+    sim.step(&mut tree);
+    assert_eq!(
+        make_cell_coords_set(vec![NdVec([-1, 1])]),
+        get_non_default_set(&tree.slice)
+    );
+}
+
+#[test]
+fn test_load_rule_file() {
+    let path = std::env::temp_dir().join("ndcell_test_load_rule_file.rule");
+    std::fs::write(&path, LIFE_TABLE).unwrap();
+    let rule = rule::load_rule_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!("LifeTable", rule.to_string());
+
+    let err = rule::load_rule_file(&path).unwrap_err();
+    assert!(err.contains("Unable to read"), "{:?}", err);
+}
@@ -0,0 +1,90 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::*;
+
+/// Conway's Game of Life as a rule script, which works in any number of
+/// dimensions but only makes sense in 2D.
+const LIFE_SCRIPT: &str = "
+# Conway's Game of Life
+@states dead, alive
+
+let n = count(alive);
+if n == 3 || this == alive && n == 2 { alive } else { dead }
+";
+
+/// Brian's Brain as a rule script.
+const BRIANS_BRAIN_SCRIPT: &str = "
+@states dead, alive, dying
+@neighborhood moore(1)
+
+if this == alive {
+    dying
+} else if this == dead && count(alive) == 2 {
+    alive
+} else {
+    dead
+}
+";
+
+#[test]
+fn test_life_script() {
+    let rule = rule::ScriptRule::<Dim2D>::parse("LifeScript", LIFE_SCRIPT).unwrap();
+    assert_eq!("LifeScript", rule.to_string());
+    assert_eq!(1, rule.radius());
+    assert_rules_match(Arc::new(rule), Arc::new(rule::LIFE), make_soup(16, 2), 40);
+}
+
+#[test]
+fn test_brians_brain_script() {
+    let rule = rule::ScriptRule::<Dim2D>::parse("BriansBrain", BRIANS_BRAIN_SCRIPT).unwrap();
+    assert_eq!(3, rule.num_states());
+    let expected = rule::Generations::<Dim2D>::try_from("B2/S/C3").unwrap();
+    assert_rules_match(Arc::new(rule), Arc::new(expected), make_soup(16, 3), 40);
+}
+
+#[test]
+fn test_3d_script() {
+    // 3D Life with the von Neumann neighborhood of radius 2.
+    let script = "
+        @neighborhood vonneumann(2)
+        let n = sum();
+        if this == 0 { n >= 5 && n <= 7 } else { n >= 4 && n <= 8 }
+    ";
+    let rule = rule::ScriptRule::<Dim3D>::parse("Test3D", script).unwrap();
+    let expected = rule::OuterTotalistic::<Dim3D>::try_from("B5-7/S4-8/N2").unwrap();
+    assert_rules_match(Arc::new(rule), Arc::new(expected), make_soup(8, 2), 10);
+}
+
+#[test]
+fn test_script_cell_offsets() {
+    // Each cell copies the cell two to the east and one to the south, so every
+    // pattern moves west and north.
+    let rule = rule::ScriptRule::<Dim2D>::parse("Shift", "cell(2, -1)").unwrap();
+    assert_eq!(2, rule.radius());
+    let mut sim = Simulation::new(Arc::new(rule), BigInt::from(1));
+    let mut tree = NdTree::new();
+    tree.set_cell(&NdVec::big([0, 0]), 1);
+    sim.step(&mut tree);
+    assert_eq!(
+        make_cell_coords_set(vec![NdVec([-2, 1])]),
+        get_non_default_set(&tree.slice)
+    );
+}
+
+#[test]
+fn test_load_script_file() {
+    let path = std::env::temp_dir().join("ndcell_test_load_script_file.ndrule");
+    std::fs::write(&path, BRIANS_BRAIN_SCRIPT).unwrap();
+    let rule = rule::load_script_file::<Dim3D>(&path).unwrap();
+    assert_eq!("ndcell_test_load_script_file", rule.to_string());
+    assert_eq!(3, rule.num_states());
+
+    std::fs::write(&path, "@states 1 0").unwrap();
+    let err = rule::load_script_file::<Dim3D>(&path).unwrap_err();
+    assert!(err.contains("Invalid rule script"), "{:?}", err);
+
+    std::fs::remove_file(&path).unwrap();
+    let err = rule::load_script_file::<Dim3D>(&path).unwrap_err();
+    assert!(err.contains("Unable to read"), "{:?}", err);
+}
@@ -0,0 +1,136 @@
+use super::*;
+
+/// Checks that HashLife matches the reference simulation on the given bounded
+/// grid, stepping the given number of generations at a time.
+fn assert_matches_reference<D: Dim>(
+    rule_str: &str,
+    topology_str: &str,
+    cells: Vec<(IVec<D>, u8)>,
+    step_size: usize,
+    steps: usize,
+) {
+    let rule = rule::from_str::<D>(rule_str).unwrap();
+    let topology: Topology<D> = topology_str.parse().unwrap();
+    let mut automaton = NdAutomaton {
+        sim: Simulation::new(rule.clone(), BigInt::from(step_size)),
+        ..Default::default()
+    };
+    for (pos, cell_state) in &cells {
+        automaton.tree.set_cell(&pos.convert(), *cell_state);
+    }
+    automaton.set_topology(topology.clone()).unwrap();
+    let mut expected = ReferenceSim::new_bounded(rule, &topology, cells);
+    for _ in 0..steps {
+        automaton.step();
+        for _ in 0..step_size {
+            expected.step_on(&topology);
+        }
+        // Cells are stored relative to the background, and there must not be
+        // any cells outside the grid.
+        expected.assert_matches(&automaton.tree, automaton.sim.get_background());
+    }
+    assert!(!expected.is_empty(), "pattern died out");
+}
+
+/// Offsets the cells of a soup so that it is centered on the origin.
+fn centered_soup<D: Dim>(size: isize, states: u8) -> Vec<(IVec<D>, u8)> {
+    make_soup(size, states)
+        .into_iter()
+        .map(|(pos, cell_state)| (pos - size / 2, cell_state))
+        .collect()
+}
+
+#[test]
+fn test_bounded_plane() {
+    // The soup is larger than the grid, so some of it is removed immediately.
+    assert_matches_reference::<Dim2D>("B3/S23", "P16,15", centered_soup(20, 2), 1, 30);
+    assert_matches_reference::<Dim2D>("B3/S23", "P16,15", centered_soup(20, 2), 5, 6);
+    assert_matches_reference::<Dim2D>("B2/S/C3", "P16,16", centered_soup(20, 3), 1, 20);
+}
+
+#[test]
+fn test_torus() {
+    assert_matches_reference::<Dim2D>("B3/S23", "T12,11", centered_soup(12, 2), 1, 40);
+    assert_matches_reference::<Dim2D>("B3/S23", "T12,11", centered_soup(12, 2), 3, 10);
+    assert_matches_reference::<Dim2D>("B36/S23/N2", "T12,10", centered_soup(12, 2), 1, 10);
+    assert_matches_reference::<Dim1D>("W30", "T31", centered_soup(31, 2), 4, 10);
+    assert_matches_reference::<Dim3D>("B4/S45", "T6,6,6", centered_soup(6, 2), 1, 10);
+}
+
+#[test]
+fn test_twisted_torus() {
+    assert_matches_reference::<Dim2D>("B3/S23", "T14+5,13", centered_soup(14, 2), 1, 30);
+    assert_matches_reference::<Dim2D>("B3/S23", "T12,11-2", centered_soup(12, 2), 1, 40);
+    assert_matches_reference::<Dim2D>("B3/S23", "K12*,11", centered_soup(12, 2), 1, 40);
+    assert_matches_reference::<Dim2D>("B3/S23", "K12,11*", centered_soup(12, 2), 2, 20);
+    assert_matches_reference::<Dim3D>("B4/S45", "K6,6*,6", centered_soup(6, 2), 1, 10);
+}
+
+#[test]
+fn test_bounded_b0() {
+    // Empty space does not stay empty, so the background inside the grid
+    // changes every generation.
+    assert_matches_reference::<Dim2D>("B036/S1237", "T12,12", centered_soup(12, 2), 1, 20);
+    assert_matches_reference::<Dim2D>("B036/S1237", "P12,12", centered_soup(12, 2), 1, 20);
+    assert_matches_reference::<Dim2D>("B0123478/S34678", "P12,12", centered_soup(12, 2), 1, 20);
+
+    let mut automaton = NdAutomaton::<Dim2D> {
+        sim: Simulation::new(rule::from_str("B036/S1237").unwrap(), BigInt::from(1)),
+        ..Default::default()
+    };
+    assert!(automaton.set_topology("P12,0".parse().unwrap()).is_err());
+    assert!(automaton.set_topology("T12,0".parse().unwrap()).is_ok());
+}
+
+#[test]
+fn test_cylinder() {
+    // A lightweight spaceship travels around a cylinder and returns to where
+    // it started.
+    let rle = "#CXRLE Pos=0,0\nx = 5, y = 4, rule = B3/S23:T20,0\nbo2bo$o4b$o3bo$4o!";
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    let initial_cells = get_non_default_set(&automaton.tree.slice);
+    automaton.set_step_size(BigInt::from(10));
+    for _ in 0..3 {
+        automaton.step();
+        assert_ne!(initial_cells, get_non_default_set(&automaton.tree.slice));
+    }
+    automaton.step();
+    assert_eq!(initial_cells, get_non_default_set(&automaton.tree.slice));
+}
+
+#[test]
+fn test_cylinder_far_from_origin() {
+    // The same spaceship works along the unbounded axis of the cylinder even
+    // beyond the range of an isize.
+    let rle = "#CXRLE Pos=0,-100000000000000000000\nx = 5, y = 4, rule = B3/S23:T20,0\nbo2bo$o4b$o3bo$4o!";
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    let initial_cells = get_non_default_set(&automaton.tree.slice);
+    automaton.set_step_size(BigInt::from(20));
+    automaton.step();
+    assert_ne!(initial_cells, get_non_default_set(&automaton.tree.slice));
+    automaton.step();
+    assert_eq!(initial_cells, get_non_default_set(&automaton.tree.slice));
+}
+
+#[test]
+fn test_bounded_step_size() {
+    let max_step_size = BigInt::from(MAX_BOUNDED_STEP_SIZE);
+    for &algorithm in Algorithm::ALL {
+        let mut automaton: NdAutomaton<Dim2D> =
+            rle::RleEncode::from_rle("x = 3, y = 1, rule = B3/S23\n3o!").unwrap();
+        automaton.set_algorithm(algorithm);
+        automaton.set_step_size(BigInt::from(1) << 100);
+        assert_eq!(BigInt::from(1) << 100, *automaton.get_step_size());
+        // Bounded grids are simulated one generation at a time, so huge steps
+        // would never finish.
+        automaton.set_topology("T8,8".parse().unwrap()).unwrap();
+        assert_eq!(max_step_size, *automaton.get_step_size());
+        automaton.set_step_size(BigInt::from(1) << 100);
+        assert_eq!(max_step_size, *automaton.get_step_size());
+        automaton.set_step_size(BigInt::from(12));
+        assert_eq!(BigInt::from(12), *automaton.get_step_size());
+        automaton.set_topology("T8,0".parse().unwrap()).unwrap();
+        automaton.set_step_size(BigInt::from(1) << 100);
+        assert_eq!(max_step_size, *automaton.get_step_size());
+    }
+}
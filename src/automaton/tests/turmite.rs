@@ -0,0 +1,228 @@
+use num::{One, Signed, ToPrimitive};
+use std::collections::HashMap;
+
+use super::*;
+
+/// The offset of the cell in front of an ant facing each direction, in the
+/// order north, east, south, west.
+const DIRECTIONS: [[isize; 2]; 4] = [[0, 1], [1, 0], [0, -1], [-1, 0]];
+
+/// A single Langton's Ant, simulated without using HashLife.
+struct ReferenceAnt {
+    black_cells: HashSet<BigVec2D>,
+    pos: BigVec2D,
+    direction: usize,
+}
+impl ReferenceAnt {
+    fn step(&mut self) {
+        // Turn right on white and left on black, flip the color of the cell,
+        // then move forward.
+        if self.black_cells.remove(&self.pos) {
+            self.direction = (self.direction + 3) % 4;
+        } else {
+            self.black_cells.insert(self.pos.clone());
+            self.direction = (self.direction + 1) % 4;
+        }
+        self.pos += &NdVec(DIRECTIONS[self.direction]).convert();
+    }
+}
+
+/// Returns the colors of all non-white cells and the position and cell state
+/// of the ant on a Langton's Ant grid.
+fn get_ant_grid(automaton: &NdAutomaton<Dim2D>) -> (HashSet<BigVec2D>, BigVec2D, u8) {
+    let cells: HashMap<BigVec2D, u8> = get_non_default_set(&automaton.tree.slice)
+        .into_iter()
+        .map(|pos| {
+            let cell_state = automaton.tree.get_cell(&pos);
+            (pos, cell_state)
+        })
+        .collect();
+    let black_cells = cells
+        .iter()
+        .filter(|(_, &cell_state)| cell_state % 2 == 1)
+        .map(|(pos, _)| pos.clone())
+        .collect();
+    let mut ants = cells.into_iter().filter(|&(_, cell_state)| cell_state >= 2);
+    let (ant_pos, ant_cell_state) = ants.next().expect("No ant");
+    assert!(ants.next().is_none(), "More than one ant");
+    (black_cells, ant_pos, ant_cell_state)
+}
+
+const LANGTONS_ANT: &str = "x = 1, y = 1, rule = LangtonsAnt\nB!";
+
+#[test]
+fn test_langtons_ant() {
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(LANGTONS_ANT).unwrap();
+    let mut expected = ReferenceAnt {
+        black_cells: HashSet::new(),
+        pos: NdVec::big([0, -1]),
+        direction: 0,
+    };
+    for _ in 0..500 {
+        automaton.sim.step(&mut automaton.tree);
+        expected.step();
+        let (black_cells, ant_pos, ant_cell_state) = get_ant_grid(&automaton);
+        assert_eq!(expected.black_cells, black_cells);
+        assert_eq!(expected.pos, ant_pos);
+        assert_eq!(2 + 2 * expected.direction as u8, ant_cell_state & !1);
+    }
+}
+
+#[test]
+fn test_langtons_ant_highway() {
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(LANGTONS_ANT).unwrap();
+    let mut expected = ReferenceAnt {
+        black_cells: HashSet::new(),
+        pos: NdVec::big([0, -1]),
+        direction: 0,
+    };
+    automaton.sim.set_step_size(BigInt::from(10_000));
+    automaton.sim.step(&mut automaton.tree);
+    for _ in 0..10_000 {
+        expected.step();
+    }
+    let (mut black_cells, mut ant_pos, _) = get_ant_grid(&automaton);
+    assert_eq!(expected.black_cells, black_cells);
+    assert_eq!(expected.pos, ant_pos);
+
+    // By generation 10,000 the ant is building a highway, moving 2 cells
+    // diagonally and leaving 12 more black cells every 104 generations.
+    automaton.sim.set_step_size(BigInt::from(104));
+    let mut last_displacement = None;
+    for _ in 0..5 {
+        automaton.sim.step(&mut automaton.tree);
+        let (new_black_cells, new_ant_pos, _) = get_ant_grid(&automaton);
+        let displacement = &new_ant_pos - &ant_pos;
+        assert_eq!(BigInt::from(2), displacement[X].abs());
+        assert_eq!(BigInt::from(2), displacement[Y].abs());
+        if let Some(last_displacement) = last_displacement {
+            assert_eq!(last_displacement, displacement);
+        }
+        assert_eq!(black_cells.len() + 12, new_black_cells.len());
+        last_displacement = Some(displacement);
+        black_cells = new_black_cells;
+        ant_pos = new_ant_pos;
+    }
+}
+
+#[test]
+fn test_turmite_fibonacci() {
+    // A 2-state turmite that draws a growing spiral.
+    let rle = "x = 1, y = 1, rule = {{{1,8,1},{1,8,1}},{{1,2,1},{0,1,0}}}\nB!";
+    let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle).unwrap();
+    assert_eq!(18, automaton.sim.get_rule().num_states());
+
+    // Simulate the same turmite without HashLife.
+    let table = [[(1, 3, 1), (1, 3, 1)], [(1, 1, 1), (0, 0, 0)]];
+    let mut colors: HashMap<BigVec2D, u8> = HashMap::new();
+    let mut pos: BigVec2D = NdVec::big([0, -1]);
+    let (mut direction, mut ant_state) = (0, 0);
+    for _ in 0..300 {
+        automaton.sim.step(&mut automaton.tree);
+        let color = colors.get(&pos).copied().unwrap_or(0);
+        let (new_color, quarter_turns, new_state) = table[ant_state][color as usize];
+        colors.insert(pos.clone(), new_color);
+        direction = (direction + quarter_turns) % 4;
+        ant_state = new_state;
+        pos += &NdVec(DIRECTIONS[direction]).convert();
+
+        for (cell_pos, &color) in &colors {
+            if *cell_pos != pos {
+                assert_eq!(color, automaton.tree.get_cell(cell_pos));
+            }
+        }
+        let expected_ant = colors.get(&pos).copied().unwrap_or(0)
+            + 2 * (1 + ant_state as u8 * 4 + direction as u8);
+        assert_eq!(expected_ant, automaton.tree.get_cell(&pos));
+    }
+}
+
+/// Returns a 3D automaton with a single ant on an empty grid, facing north
+/// with up being +Z.
+fn make_3d_ant(rule_str: &str) -> NdAutomaton<Dim3D> {
+    let mut automaton = NdAutomaton::<Dim3D> {
+        sim: Simulation::new(rule::from_str(rule_str).unwrap(), BigInt::one()),
+        ..Default::default()
+    };
+    automaton.tree.set_cell(&NdVec::big([0, -1, 0]), 2);
+    automaton
+}
+
+#[test]
+fn test_langtons_ant_3d_stays_flat() {
+    let mut automaton = make_3d_ant("LangtonsAnt");
+    let mut expected = ReferenceAnt {
+        black_cells: HashSet::new(),
+        pos: NdVec::big([0, -1]),
+        direction: 0,
+    };
+    for _ in 0..300 {
+        automaton.step();
+        expected.step();
+    }
+    let cells = get_non_default_set(&automaton.tree.slice);
+    assert!(cells.iter().all(|pos| pos[Z].is_zero()));
+    let flatten = |pos: &BigVec3D| NdVec([pos[X].clone(), pos[Y].clone()]);
+    let black_cells: HashSet<BigVec2D> = cells
+        .iter()
+        .filter(|pos| automaton.tree.get_cell(pos) % 2 == 1)
+        .map(flatten)
+        .collect();
+    assert_eq!(expected.black_cells, black_cells);
+    let ant_pos = cells
+        .iter()
+        .find(|pos| automaton.tree.get_cell(pos) >= 2)
+        .unwrap();
+    assert_eq!(expected.pos, flatten(ant_pos));
+}
+
+#[test]
+fn test_turmite_3d() {
+    // Turns right on white cells and up on black cells.
+    let mut automaton = make_3d_ant("{{{1,2,0},{0,16,0}}}");
+
+    // Simulate the same turmite without HashLife.
+    let cross = |a: IVec3D, b: IVec3D| {
+        NdVec([
+            a[Y] * b[Z] - a[Z] * b[Y],
+            a[Z] * b[X] - a[X] * b[Z],
+            a[X] * b[Y] - a[Y] * b[X],
+        ])
+    };
+    let mut black_cells: HashSet<IVec3D> = HashSet::new();
+    let mut pos: IVec3D = NdVec([0, -1, 0]);
+    let mut forward: IVec3D = NdVec([0, 1, 0]);
+    let mut up: IVec3D = NdVec([0, 0, 1]);
+    let mut left_plane = false;
+    for _ in 0..300 {
+        automaton.step();
+        if black_cells.remove(&pos) {
+            let old_forward = forward;
+            forward = up;
+            up = -old_forward;
+        } else {
+            black_cells.insert(pos);
+            forward = cross(forward, up);
+        }
+        pos += forward;
+        left_plane |= pos[Z] != 0;
+
+        let cells = get_non_default_set(&automaton.tree.slice);
+        for cell_pos in &cells {
+            let cell_state = automaton.tree.get_cell(cell_pos);
+            if *cell_pos == pos.convert() {
+                assert!(cell_state >= 2, "No ant at {:?}", pos);
+            } else {
+                assert!(cell_state < 2, "Extra ant at {:?}", cell_pos);
+            }
+            let cell_pos: IVec3D = NdVec::from_fn(|ax| cell_pos[ax].to_isize().unwrap());
+            assert_eq!(black_cells.contains(&cell_pos), cell_state % 2 == 1);
+        }
+        let black_count = cells
+            .iter()
+            .filter(|pos| automaton.tree.get_cell(pos) % 2 == 1)
+            .count();
+        assert_eq!(black_cells.len(), black_count);
+    }
+    assert!(left_plane, "Ant never left the XY plane");
+}
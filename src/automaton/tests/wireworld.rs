@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use super::*;
+use rule::{WIREWORLD_CONDUCTOR, WIREWORLD_HEAD, WIREWORLD_TAIL};
+
+/// Returns a reference implementation of Wireworld.
+fn wireworld_reference<D: Dim>() -> ReferenceRule<D> {
+    ReferenceRule::with_neighbors(
+        moore_neighbors(),
+        |cell_state, neighbors| match cell_state {
+            0 => 0,
+            WIREWORLD_HEAD => WIREWORLD_TAIL,
+            WIREWORLD_TAIL => WIREWORLD_CONDUCTOR,
+            _ => {
+                let heads = neighbors.iter().filter(|&&n| n == WIREWORLD_HEAD).count();
+                if heads == 1 || heads == 2 {
+                    WIREWORLD_HEAD
+                } else {
+                    WIREWORLD_CONDUCTOR
+                }
+            }
+        },
+    )
+}
+
+/// A clock with a period of 8 generations, which sends electrons down a wire.
+const CLOCK: &str = "x = 17, y = 3, rule = WireWorld\n.BAC$C3.C$.3C.12C!";
+
+#[test]
+fn test_wireworld_clock() {
+    let automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(CLOCK).unwrap();
+    assert_eq!("WireWorld", automaton.sim.get_rule().to_string());
+    assert_eq!(
+        WIREWORLD_HEAD,
+        automaton.tree.get_cell(&NdVec::big([2, -1]))
+    );
+    assert_eq!(
+        WIREWORLD_TAIL,
+        automaton.tree.get_cell(&NdVec::big([1, -1]))
+    );
+    assert_eq!(
+        WIREWORLD_CONDUCTOR,
+        automaton.tree.get_cell(&NdVec::big([3, -1]))
+    );
+    let cells = get_cells(&automaton.tree);
+    assert_matches_reference("WireWorld", wireworld_reference(), cells.clone(), 1, 64);
+    assert_matches_reference("WireWorld", wireworld_reference(), cells, 8, 16);
+
+    // Once the wire has filled up with electrons, the whole pattern repeats
+    // every 8 generations.
+    let mut automaton = automaton;
+    automaton.sim.set_step_size(BigInt::from(24));
+    automaton.step();
+    let rle_at_24 = rle::RleEncode::to_rle(&automaton);
+    automaton.sim.set_step_size(BigInt::from(8));
+    automaton.step();
+    assert_eq!(
+        rle_at_24.replace("Gen=24", "Gen=32"),
+        rle::RleEncode::to_rle(&automaton)
+    );
+    assert!(rle_at_24.contains("rule = WireWorld"));
+}
+
+#[test]
+fn test_wireworld_3d() {
+    let mut automaton = NdAutomaton::<Dim3D> {
+        sim: Simulation::new(Arc::new(rule::Wireworld::default()), BigInt::from(1)),
+        ..Default::default()
+    };
+    // Make a soup of mostly conductors with some electrons.
+    for (pos, cell_state) in make_soup::<Dim3D>(8, 4) {
+        let cell_state = if cell_state == 1 {
+            WIREWORLD_HEAD
+        } else {
+            WIREWORLD_CONDUCTOR
+        };
+        automaton.tree.set_cell(&pos.convert(), cell_state);
+    }
+    let cells = get_cells(&automaton.tree);
+    assert_matches_reference("WireWorld", wireworld_reference(), cells, 1, 20);
+}
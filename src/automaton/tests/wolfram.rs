@@ -0,0 +1,90 @@
+use num::ToPrimitive;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::*;
+
+/// Returns a reference implementation of a 1D rule with the given radius,
+/// given a function that computes the next state of a cell from the states of
+/// the cells in its neighborhood (from left to right).
+fn reference_1d(
+    radius: usize,
+    transition: impl Fn(&[u8]) -> u8 + Send + Sync + 'static,
+) -> ReferenceRule<Dim1D> {
+    ReferenceRule::new(radius, move |napkin| {
+        let r = radius as isize;
+        let neighborhood: Vec<u8> = (-r..=r).map(|x| napkin[&NdVec([x])]).collect();
+        transition(&neighborhood)
+    })
+}
+
+/// Returns a reference implementation of an elementary rule.
+fn elementary(number: u8) -> ReferenceRule<Dim1D> {
+    reference_1d(1, move |cells| {
+        (number >> (cells[0] << 2 | cells[1] << 1 | cells[2])) & 1
+    })
+}
+
+/// Returns a reference implementation of a totalistic rule with the given
+/// radius, given the next state for each sum.
+fn totalistic(radius: usize, table: &'static [u8]) -> ReferenceRule<Dim1D> {
+    reference_1d(radius, move |cells| {
+        table[cells.iter().map(|&cell| cell as usize).sum::<usize>()]
+    })
+}
+
+#[test]
+fn test_rule_110() {
+    let single_cell = vec![(NdVec([0]), 1)];
+    assert_matches_reference("W110", elementary(110), single_cell.clone(), 1, 300);
+    assert_matches_reference("W110", elementary(110), single_cell, 64, 16);
+    assert_matches_reference("W110", elementary(110), make_soup(64, 2), 16, 32);
+
+    // Rule 110 grows to the left, one cell per generation.
+    let rule = Arc::new(rule::Elementary1D::try_from("W110").unwrap());
+    let mut sim = Simulation::new(rule, BigInt::from(1000));
+    let mut tree = NdTree::new();
+    tree.set_cell(&NdVec::big([0]), 1);
+    sim.step(&mut tree);
+    let cells = get_non_default_set(&tree.slice);
+    assert_eq!(
+        Some(-1000),
+        cells.iter().map(|pos| pos[X].to_isize().unwrap()).min()
+    );
+    assert_eq!(
+        Some(0),
+        cells.iter().map(|pos| pos[X].to_isize().unwrap()).max()
+    );
+}
+
+#[test]
+fn test_rule_30() {
+    let single_cell = vec![(NdVec([0]), 1)];
+    assert_matches_reference("W30", elementary(30), single_cell, 1, 200);
+    assert_matches_reference("w30", elementary(30), make_soup(64, 2), 8, 32);
+}
+
+#[test]
+fn test_totalistic_1d() {
+    // k=3, r=1, code 777: 777 = 1001210 in base 3.
+    const CODE_777: &[u8] = &[0, 1, 2, 1, 0, 0, 1];
+    let single_cell = vec![(NdVec([0]), 1)];
+    assert_matches_reference("T777,K3", totalistic(1, CODE_777), single_cell, 1, 200);
+    assert_matches_reference(
+        "T777,K3,R1",
+        totalistic(1, CODE_777),
+        make_soup(64, 3),
+        8,
+        32,
+    );
+
+    // k=2, r=2, code 26: 26 = 011010 in binary.
+    const CODE_26: &[u8] = &[0, 1, 0, 1, 1, 0];
+    assert_matches_reference(
+        "T26,K2,R2",
+        totalistic(2, CODE_26),
+        make_soup(64, 2),
+        1,
+        200,
+    );
+}
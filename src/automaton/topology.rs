@@ -0,0 +1,447 @@
+//! Grid topologies other than the infinite plane, such as bounded planes and
+//! tori, described using Golly's notation for bounded grids:
+//! http://golly.sourceforge.net/Help/bounded.html
+
+use num::{BigInt, ToPrimitive};
+use std::fmt;
+use std::str::FromStr;
+
+use super::*;
+
+/// The largest step size allowed on a grid that is bounded along any axis,
+/// since those are simulated one generation at a time.
+pub const MAX_BOUNDED_STEP_SIZE: usize = 1 << 16;
+
+/// A change to the way that a torus wraps around along one axis (the "twisted"
+/// axis), which applies whenever a cell wraps around along any other axis.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Twist {
+    /// Cells that leave through the positive edge of another axis reappear at
+    /// its negative edge shifted by this amount along the twisted axis (and
+    /// vice versa), as in a twisted torus.
+    Shift(isize),
+    /// Cells that wrap around along another axis are reflected along the
+    /// twisted axis, as in a Klein bottle.
+    Flip,
+}
+
+/// The shape of the grid that an automaton is simulated on.
+///
+/// A grid is either unbounded (the default), a plane with dead cells beyond
+/// its edges, or a torus that wraps around at its edges. Planes and tori may
+/// still be unbounded along some axes (e.g. a torus that is unbounded along
+/// one axis is a cylinder).
+///
+/// The grid is centered on the origin in the same place as in Golly, so that
+/// bounded patterns can be loaded from RLE files: along an axis of length `n`,
+/// it spans from `-floor(n/2)` to `ceil(n/2)-1`. The exception is the Y axis,
+/// which spans from `-ceil(n/2)` to `floor(n/2)-1` because NDCell reflects RLE
+/// patterns over the X axis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topology<D: Dim> {
+    wrap: bool,
+    /// The length of the grid along each axis, or zero if it is unbounded
+    /// along that axis.
+    size: UVec<D>,
+    twist: Option<(Axis, Twist)>,
+}
+impl<D: Dim> Default for Topology<D> {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+impl<D: Dim> Topology<D> {
+    /// Returns the topology of an infinite grid.
+    pub fn unbounded() -> Self {
+        Self::plane(UVec::origin())
+    }
+    /// Returns the topology of a plane of the given size with dead cells
+    /// beyond its edges, where a size of zero means that the plane is
+    /// unbounded along that axis.
+    pub fn plane(size: UVec<D>) -> Self {
+        Self {
+            wrap: false,
+            size,
+            twist: None,
+        }
+    }
+    /// Returns the topology of a torus of the given size, where a size of zero
+    /// means that the torus is unbounded along that axis.
+    pub fn torus(size: UVec<D>) -> Self {
+        Self {
+            wrap: true,
+            size,
+            twist: None,
+        }
+    }
+    /// Returns the topology of a torus of the given size with a twist along
+    /// the given axis, or an error if the twist is impossible.
+    pub fn twisted_torus(size: UVec<D>, axis: Axis, twist: Twist) -> Result<Self, String> {
+        if !D::axes().contains(&axis) {
+            return Err(format!("Cannot twist {:?} axis in {}D", axis, D::NDIM));
+        }
+        if twist == Twist::Flip && size[axis] == 0 {
+            return Err("Cannot flip an unbounded axis".to_owned());
+        }
+        if D::axes().iter().all(|&ax| ax == axis || size[ax] == 0) {
+            return Err("Twisted axis must have another bounded axis to twist around".to_owned());
+        }
+        Ok(Self {
+            wrap: true,
+            size,
+            twist: Some((axis, twist)),
+        })
+    }
+
+    /// Returns true if the grid is unbounded along every axis.
+    pub fn is_unbounded(&self) -> bool {
+        D::axes().iter().all(|&ax| self.size[ax] == 0)
+    }
+    /// Returns true if cells wrap around at the edges of the grid, or false if
+    /// there are dead cells beyond the edges.
+    pub fn wraps(&self) -> bool {
+        self.wrap
+    }
+    /// Returns the length of the grid along each axis, or zero if it is
+    /// unbounded along that axis.
+    pub fn size(&self) -> &UVec<D> {
+        &self.size
+    }
+    /// Returns the twisted axis and its twist, if there is one.
+    pub fn twist(&self) -> Option<(Axis, Twist)> {
+        self.twist
+    }
+
+    /// Returns the lowest coordinate of the grid along the given axis.
+    fn min(&self, axis: Axis) -> isize {
+        let size = self.size[axis] as isize;
+        if axis == Y {
+            size / 2 - size
+        } else {
+            -(size / 2)
+        }
+    }
+    /// Returns the rectangle covered by the grid, or None if it is unbounded
+    /// along any axis.
+    pub fn rect(&self) -> Option<IRect<D>> {
+        if D::axes().iter().any(|&ax| self.size[ax] == 0) {
+            return None;
+        }
+        let min = IVec::from_fn(|ax| self.min(ax));
+        let size = IVec::from_fn(|ax| self.size[ax] as isize);
+        Some(IRect::new(min, size))
+    }
+    /// Returns true if the given position is inside the grid.
+    pub fn contains(&self, pos: &IVec<D>) -> bool {
+        D::axes().iter().all(|&ax| {
+            let size = self.size[ax] as isize;
+            let min = self.min(ax);
+            size == 0 || (min <= pos[ax] && pos[ax] < min + size)
+        })
+    }
+    /// Returns the position inside the grid of the cell at the given position,
+    /// which may be outside the grid if it wraps around, or None if the
+    /// position is beyond the edge of a plane.
+    pub fn wrap_pos(&self, pos: &IVec<D>) -> Option<IVec<D>> {
+        if !self.wrap {
+            return if self.contains(pos) {
+                Some(pos.clone())
+            } else {
+                None
+            };
+        }
+        let mut ret = pos.clone();
+        let twist_axis = self.twist.map(|(ax, _)| ax);
+        for &ax in D::axes() {
+            if self.size[ax] == 0 || Some(ax) == twist_axis {
+                continue;
+            }
+            let size = self.size[ax] as isize;
+            let wraps = (ret[ax] - self.min(ax)).div_euclid(size);
+            ret[ax] -= wraps * size;
+            match self.twist {
+                Some((twist_axis, Twist::Shift(shift))) => ret[twist_axis] += wraps * shift,
+                Some((twist_axis, Twist::Flip)) if wraps % 2 != 0 => {
+                    ret[twist_axis] = 2 * self.min(twist_axis) + self.size[twist_axis] as isize
+                        - 1
+                        - ret[twist_axis];
+                }
+                _ => (),
+            }
+        }
+        if let Some(ax) = twist_axis {
+            if self.size[ax] != 0 {
+                let min = self.min(ax);
+                ret[ax] = min + (ret[ax] - min).rem_euclid(self.size[ax] as isize);
+            }
+        }
+        Some(ret)
+    }
+
+    /// Sets every cell outside the grid to the default state.
+    pub fn clear_outside<C: CellType>(&self, tree: &mut NdTree<C, D>) {
+        if self.is_unbounded() {
+            return;
+        }
+        // Along unbounded axes, the whole tree is inside the grid.
+        let tree_rect = tree.slice.rect();
+        let min = BigVec::from_fn(|ax| match self.size[ax] {
+            0 => tree_rect.min()[ax].clone(),
+            _ => BigInt::from(self.min(ax)),
+        });
+        let size = BigVec::from_fn(|ax| match self.size[ax] {
+            0 => tree_rect.len(ax),
+            size => BigInt::from(size),
+        });
+        tree.crop(&BigRect::new(min, size));
+    }
+    /// Sets each cell within `radius` of the grid to the state of the cell
+    /// inside the grid that it wraps around to, or to `dead_cell` if it is
+    /// beyond the edge of a plane, so that a single generation of a rule with
+    /// that radius can be simulated as if the grid were unbounded.
+    ///
+    /// This assumes that every cell outside the grid is in the default state.
+    pub fn add_border_cells<C: CellType>(
+        &self,
+        tree: &mut NdTree<C, D>,
+        radius: usize,
+        dead_cell: C,
+    ) {
+        if radius == 0 {
+            return;
+        }
+        let radius = radius as isize;
+        // Along unbounded axes, only the part of the border next to the
+        // pattern matters. Positions along those axes are relative to the
+        // lowest cell of the pattern, which may be too far from the origin to
+        // fit in an isize. Nothing wraps around along them, so this doesn't
+        // change the border.
+        let tree_min = tree.slice.min();
+        let mut origin = BigVec::<D>::origin();
+        let mut pattern_max = IVec::<D>::origin();
+        for &ax in D::axes() {
+            if self.size[ax] != 0 {
+                continue;
+            }
+            match tree.get_root().population_span(ax) {
+                Some((min, max)) => {
+                    pattern_max[ax] = (max - &min)
+                        .to_isize()
+                        .unwrap_or(isize::MAX)
+                        .min(isize::MAX - radius);
+                    origin[ax] = &tree_min[ax] + min;
+                }
+                // There are no cells to copy, and there are no dead cells in
+                // other states beyond the edges of a plane that is unbounded
+                // along some axis (see `Phases::check_topology()`).
+                None => return,
+            }
+        }
+        for &border_ax in D::axes() {
+            if self.size[border_ax] == 0 {
+                continue;
+            }
+            let min = self.min(border_ax);
+            let max = min + self.size[border_ax] as isize - 1;
+            for &(border_min, border_max) in &[(min - radius, min - 1), (max + 1, max + radius)] {
+                // Each part of the border along an axis includes the corners
+                // it shares with the borders along later axes, but not the
+                // corners it shares with the borders along earlier axes.
+                let start = IVec::from_fn(|ax| {
+                    if ax == border_ax {
+                        border_min
+                    } else if self.size[ax] == 0 {
+                        -radius
+                    } else if (ax as usize) < (border_ax as usize) {
+                        self.min(ax)
+                    } else {
+                        self.min(ax) - radius
+                    }
+                });
+                let end = IVec::from_fn(|ax| {
+                    if ax == border_ax {
+                        border_max
+                    } else if self.size[ax] == 0 {
+                        pattern_max[ax] + radius
+                    } else if (ax as usize) < (border_ax as usize) {
+                        self.min(ax) + self.size[ax] as isize - 1
+                    } else {
+                        self.min(ax) + self.size[ax] as isize - 1 + radius
+                    }
+                });
+                for pos in IRect::span(start, end).iter() {
+                    let cell_state = match self.wrap_pos(&pos) {
+                        Some(inner_pos) => tree.get_cell(&(inner_pos.convert() + &origin)),
+                        None => dead_cell,
+                    };
+                    if cell_state != C::default() {
+                        tree.set_cell(&(pos.convert() + &origin), cell_state);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the given step size, limited to `MAX_BOUNDED_STEP_SIZE` if the
+    /// grid is bounded along any axis.
+    pub fn clamp_step_size(&self, step_size: BigInt) -> BigInt {
+        if self.is_unbounded() {
+            step_size
+        } else {
+            step_size.min(BigInt::from(MAX_BOUNDED_STEP_SIZE))
+        }
+    }
+}
+
+impl<D: Dim> fmt::Display for Topology<D> {
+    /// Formats the topology using Golly's notation (e.g. `T64,64`), except
+    /// that there is one size for each axis.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match self.twist {
+            _ if !self.wrap => 'P',
+            Some((_, Twist::Flip)) => 'K',
+            _ => 'T',
+        };
+        let axes: Vec<String> = D::axes()
+            .iter()
+            .map(|&ax| match self.twist {
+                Some((twist_ax, Twist::Shift(shift))) if twist_ax == ax => {
+                    format!("{}{:+}", self.size[ax], shift)
+                }
+                Some((twist_ax, Twist::Flip)) if twist_ax == ax => format!("{}*", self.size[ax]),
+                _ => self.size[ax].to_string(),
+            })
+            .collect();
+        write!(f, "{}{}", letter, axes.join(","))
+    }
+}
+
+impl<D: Dim> FromStr for Topology<D> {
+    type Err = String;
+    /// Parses a topology in Golly's notation, with one size for each axis:
+    /// `P` for a plane (e.g. `P30,20`), `T` for a torus (e.g. `T30,20`, or
+    /// `T30+5,20` with a shift), or `K` for a Klein bottle (e.g. `K30*,20`,
+    /// with the flipped axis marked by `*`). A size of zero means that the
+    /// grid is unbounded along that axis.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let err = || format!("Invalid topology: {:?}", s);
+        let mut chars = s.chars();
+        let letter = chars.next().ok_or_else(err)?;
+        let axis_strs: Vec<&str> = chars.as_str().split(',').collect();
+        if axis_strs.len() != D::NDIM {
+            return Err(format!(
+                "Topology {:?} does not have {} dimensions",
+                s,
+                D::NDIM
+            ));
+        }
+
+        let mut size = UVec::origin();
+        let mut twist = None;
+        for (&ax, &axis_str) in D::axes().iter().zip(&axis_strs) {
+            let digits_end = axis_str
+                .find(|ch: char| !ch.is_ascii_digit())
+                .unwrap_or(axis_str.len());
+            let (digits, suffix) = axis_str.split_at(digits_end);
+            size[ax] = digits.parse().map_err(|_| err())?;
+            let axis_twist = match (letter, suffix) {
+                (_, "") => continue,
+                ('K', "*") => Twist::Flip,
+                ('T', shift) if shift.starts_with('+') || shift.starts_with('-') => {
+                    Twist::Shift(shift.parse().map_err(|_| err())?)
+                }
+                _ => return Err(err()),
+            };
+            if twist.replace((ax, axis_twist)).is_some() {
+                return Err(format!("Topology {:?} has more than one twisted axis", s));
+            }
+        }
+
+        match (letter, twist) {
+            ('P', None) => Ok(Self::plane(size)),
+            ('T', None) => Ok(Self::torus(size)),
+            ('T', Some((ax, twist))) | ('K', Some((ax, twist))) => {
+                Self::twisted_torus(size, ax, twist)
+            }
+            ('K', None) => Err(format!("Klein bottle {:?} has no flipped axis", s)),
+            _ => Err(format!("Unsupported topology: {:?}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topology_parse() {
+        for s in &[
+            "P30,20", "T30,0", "T30+5,20", "T30,20-2", "K30*,20", "K30,20*",
+        ] {
+            assert_eq!(*s, s.parse::<Topology<Dim2D>>().unwrap().to_string());
+        }
+        assert_eq!(
+            "T8,8,8",
+            "T8,8,8".parse::<Topology<Dim3D>>().unwrap().to_string()
+        );
+        assert!("P0,0".parse::<Topology<Dim2D>>().unwrap().is_unbounded());
+        for s in &[
+            "",
+            "T30",
+            "T30,20,10",
+            "X30,20",
+            "T30*,20",
+            "K30,20",
+            "K30*,20*",
+            "T30+1,20+1",
+            "P30+1,20",
+            "K0*,20",
+            "T30+1,0",
+            "S50",
+            "T-30,20",
+        ] {
+            assert!(s.parse::<Topology<Dim2D>>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_topology_rect() {
+        let topology: Topology<Dim2D> = "P5,5".parse().unwrap();
+        assert_eq!(
+            Some(IRect::span(NdVec([-2, -3]), NdVec([2, 1]))),
+            topology.rect()
+        );
+        assert!(topology.contains(&NdVec([2, -3])));
+        assert!(!topology.contains(&NdVec([-3, 0])));
+        assert_eq!(None, topology.wrap_pos(&NdVec([0, 2])));
+        let topology: Topology<Dim2D> = "T0,4".parse().unwrap();
+        assert_eq!(None, topology.rect());
+        assert!(topology.contains(&NdVec([1000, -2])));
+        assert!(!topology.contains(&NdVec([1000, 2])));
+    }
+
+    #[test]
+    fn test_topology_wrap() {
+        // The grid spans -4..=3 on each axis.
+        let torus: Topology<Dim2D> = "T8,8".parse().unwrap();
+        assert_eq!(Some(NdVec([-4, 3])), torus.wrap_pos(&NdVec([4, -5])));
+        assert_eq!(Some(NdVec([1, 2])), torus.wrap_pos(&NdVec([17, -14])));
+
+        let cylinder: Topology<Dim2D> = "T8,0".parse().unwrap();
+        assert_eq!(Some(NdVec([-4, 100])), cylinder.wrap_pos(&NdVec([4, 100])));
+
+        // Leaving through the top edge shifts by +3 along X.
+        let shifted: Topology<Dim2D> = "T8+3,8".parse().unwrap();
+        assert_eq!(Some(NdVec([3, -4])), shifted.wrap_pos(&NdVec([0, 4])));
+        assert_eq!(Some(NdVec([-1, 3])), shifted.wrap_pos(&NdVec([2, -5])));
+        assert_eq!(Some(NdVec([-4, 0])), shifted.wrap_pos(&NdVec([4, 0])));
+
+        // Wrapping around along Y flips X.
+        let klein: Topology<Dim2D> = "K8*,8".parse().unwrap();
+        assert_eq!(Some(NdVec([-4, -4])), klein.wrap_pos(&NdVec([3, 4])));
+        assert_eq!(Some(NdVec([-2, -3])), klein.wrap_pos(&NdVec([-2, 13])));
+        assert_eq!(Some(NdVec([1, -4])), klein.wrap_pos(&NdVec([-2, 20])));
+        assert_eq!(Some(NdVec([-3, 0])), klein.wrap_pos(&NdVec([5, 0])));
+    }
+}
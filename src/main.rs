@@ -7,12 +7,44 @@
 extern crate pest_derive;
 
 use log::{debug, info};
+use std::path::PathBuf;
 
 pub mod automaton;
 mod math;
 mod ui;
 
+/// Command-line arguments.
+#[derive(Debug, Default)]
+struct Args {
+    /// Pattern file to open at startup.
+    open: Option<PathBuf>,
+}
+impl Args {
+    /// Parses command-line arguments, exiting with a usage message if they
+    /// are invalid.
+    fn parse() -> Self {
+        let mut ret = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--open" => match args.next() {
+                    Some(path) => ret.open = Some(path.into()),
+                    None => Self::exit_with_usage("Missing path after --open"),
+                },
+                other => Self::exit_with_usage(&format!("Unknown argument: {}", other)),
+            }
+        }
+        ret
+    }
+    fn exit_with_usage(message: &str) -> ! {
+        eprintln!("{}", message);
+        eprintln!("Usage: ndcell [--open <path>]");
+        std::process::exit(1);
+    }
+}
+
 fn main() {
+    let args = Args::parse();
     simple_logger::init().unwrap();
     info!("Starting NDCell v{} ...", env!("CARGO_PKG_VERSION"));
 
@@ -28,7 +60,7 @@ fn main() {
         debug!("Spawning UI thread {} MB stack size", MSVC_STACK_SIZE_MB);
         let _ = thread::Builder::new()
             .stack_size(MSVC_STACK_SIZE_MB * 1024 * 1024)
-            .spawn(ui::show_gui)
+            .spawn(move || ui::show_gui(args.open.as_deref()))
             .unwrap()
             .join();
     }
@@ -37,6 +69,6 @@ fn main() {
     #[cfg(unix)]
     {
         debug!("Launching UI");
-        ui::show_gui();
+        ui::show_gui(args.open.as_deref());
     }
 }
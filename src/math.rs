@@ -1,5 +1,7 @@
 //! Miscellaneous math functions
 
+use num::{BigInt, One};
+
 /// Computes the base-2 logarithm of a number, rounded up.
 ///
 /// Put another way: returns the smallest number `p` (minimum `0`) such that `n <= 2
@@ -15,6 +17,36 @@ pub fn ceil_log_base_2(mut n: usize) -> usize {
     real_bits
 }
 
+/// Computes the base-2 logarithm of a BigInt, rounded up, the same as
+/// `ceil_log_base_2()`.
+pub fn ceil_log_base_2_bigint(n: &BigInt) -> usize {
+    if *n <= BigInt::one() {
+        return 0;
+    }
+    (n - BigInt::one()).bits()
+}
+
+/// The largest exponent accepted by `parse_bigint()`, which keeps the result
+/// to a reasonable size (128 KiB).
+pub const MAX_PARSED_EXPONENT: usize = 1 << 20;
+
+/// Parses an integer written either in decimal (e.g. `-123`) or as a power of
+/// two (e.g. `2^100`), either of which may be arbitrarily large. Returns
+/// `None` if the exponent is greater than `MAX_PARSED_EXPONENT`.
+pub fn parse_bigint(s: &str) -> Option<BigInt> {
+    let s = s.trim();
+    match s.strip_prefix("2^") {
+        Some(exponent) => {
+            let exponent: usize = exponent.trim().parse().ok()?;
+            if exponent > MAX_PARSED_EXPONENT {
+                return None;
+            }
+            Some(BigInt::one() << exponent)
+        }
+        None => s.parse().ok(),
+    }
+}
+
 /// Rounds a number to the nearest multiple of another number.
 pub fn round_to(n: f32, m: f32) -> f32 {
     (n / m).round() * m
@@ -36,6 +68,31 @@ mod tests {
                 assert!(n <= 1 << p);
                 assert!(n > 1 << (p - 1));
             }
+            assert_eq!(p, ceil_log_base_2_bigint(&BigInt::from(n)));
         }
     }
+
+    #[test]
+    fn test_parse_bigint() {
+        assert_eq!(Some(BigInt::from(123)), parse_bigint("123"));
+        assert_eq!(Some(BigInt::from(-5)), parse_bigint(" -5 "));
+        assert_eq!(Some(BigInt::from(1024)), parse_bigint("2^10"));
+        assert_eq!(Some(BigInt::one() << 100), parse_bigint("2^100"));
+        assert_eq!(
+            "1267650600228229401496703205376".parse::<BigInt>().ok(),
+            parse_bigint("2^100")
+        );
+        assert_eq!(None, parse_bigint("2^-1"));
+        assert_eq!(
+            Some(BigInt::one() << MAX_PARSED_EXPONENT),
+            parse_bigint(&format!("2^{}", MAX_PARSED_EXPONENT))
+        );
+        assert_eq!(
+            None,
+            parse_bigint(&format!("2^{}", MAX_PARSED_EXPONENT + 1))
+        );
+        assert_eq!(None, parse_bigint("2^18446744073709551615"));
+        assert_eq!(None, parse_bigint("3^2"));
+        assert_eq!(None, parse_bigint(""));
+    }
 }
@@ -237,6 +237,13 @@ struct RenderInProgress<'a> {
     /// number of cells in a chunk) within quadtree_slice for the chunks that
     /// are visible.
     chunk_visible_rect: IRect2D,
+    /// The number of cell states used by the rule.
+    num_states: usize,
+    /// The color of each cell state specified by the rule, if any.
+    colors: Option<&'static [[u8; 3]]>,
+    /// The state of the infinite background, relative to which cells are
+    /// stored.
+    background: u8,
     // /// The view matrix converting from quadtree_slice space (1 unit = 1 render
     // /// cell; (0, 0) = bottom left) to screen space ((-1, -1) = bottom left; (1,
     // /// 1) = top right).
@@ -339,6 +346,10 @@ impl<'a> RenderInProgress<'a> {
             }
         }
 
+        let num_states = g.automaton.get_num_states();
+        let colors = g.automaton.get_colors();
+        let background = g.automaton.get_background();
+
         Self {
             cache,
             viewport,
@@ -349,6 +360,9 @@ impl<'a> RenderInProgress<'a> {
             pos,
             visible_rect,
             chunk_visible_rect,
+            num_states,
+            colors,
+            background,
         }
     }
 
@@ -361,10 +375,13 @@ impl<'a> RenderInProgress<'a> {
             .div_outward(&(BigInt::from(1) << self.render_cell_layer))
             .as_irect();
         // Encode the quadtree as a texture.
+        let num_states = self.num_states;
+        let colors = self.colors;
+        let background = self.background;
         let gl_quadtree = GlQuadtree::from_node(
             &self.quadtree_slice.root,
             self.render_cell_layer,
-            Self::get_branch_pixel_color,
+            |branch| Self::get_branch_pixel_color(branch, num_states, colors, background),
         );
         let unscaled_cells_w = render_cells_rect.len(X) as u32;
         let unscaled_cells_h = render_cells_rect.len(Y) as u32;
@@ -516,19 +533,54 @@ impl<'a> RenderInProgress<'a> {
             )
             .expect("Failed to draw cells");
     }
-    fn get_branch_pixel_color(branch: &NdTreeBranch<u8, Dim2D>) -> [u8; 4] {
+    /// Returns the color of a render cell. If the rule does not specify a color
+    /// for each state, live cells (state 1) are brightest, and each subsequent
+    /// state is dimmer than the one before it.
+    ///
+    /// Cells are stored relative to the given background state (see
+    /// `Simulator::get_background()`), and are drawn as their actual states.
+    fn get_branch_pixel_color(
+        branch: &NdTreeBranch<u8, Dim2D>,
+        num_states: usize,
+        colors: Option<&[[u8; 3]]>,
+        background: u8,
+    ) -> [u8; 4] {
         let ratio = match branch {
-            NdTreeBranch::Leaf(cell_state) => *cell_state as f64,
-            NdTreeBranch::Node(node) => {
-                if node.population.is_zero() {
-                    0.0
-                } else if let Some(node_len) = node.len().to_f64() {
-                    let population = node.population.to_f64().unwrap();
-                    (population / 2.0) / node_len.powf(2.0) + 0.5
-                } else {
-                    1.0
+            NdTreeBranch::Leaf(cell_state) => {
+                let cell_state = swap_background(*cell_state, background) as usize;
+                if let Some([r, g, b]) = colors.and_then(|colors| colors.get(cell_state)) {
+                    return [*r, *g, *b, 255];
+                }
+                match cell_state {
+                    0 => 0.0,
+                    _ if cell_state < num_states => {
+                        (num_states - cell_state) as f64 / (num_states - 1) as f64
+                    }
+                    _ => 1.0,
                 }
             }
+            // Empty nodes are entirely in the background state.
+            NdTreeBranch::Node(node) if node.population.is_zero() => {
+                return Self::get_branch_pixel_color(
+                    &NdTreeBranch::Leaf(0),
+                    num_states,
+                    colors,
+                    background,
+                );
+            }
+            NdTreeBranch::Node(node) => match node.len().to_f64() {
+                Some(node_len) => {
+                    let area = node_len.powf(2.0);
+                    let mut population = node.population.to_f64().unwrap();
+                    // If the background is not empty, then the cells that are
+                    // stored as empty are the live ones.
+                    if background != 0 {
+                        population = area - population;
+                    }
+                    (population / 2.0) / area + 0.5
+                }
+                None => 1.0,
+            },
         };
         let r = ((LIVE_COLOR.0 as f64).powf(2.0) * ratio
             + (DEAD_COLOR.0 as f64).powf(2.0) * (1.0 - ratio))
@@ -0,0 +1,40 @@
+use imgui::*;
+
+use crate::ui::State;
+
+#[derive(Default)]
+pub struct WindowState {
+    message: Option<String>,
+    needs_open: bool,
+}
+impl WindowState {
+    /// Displays the given error message in a modal popup.
+    pub fn show(&mut self, message: String) {
+        self.message = Some(message);
+        self.needs_open = true;
+    }
+}
+
+/// Builds the error popup.
+pub fn build(state: &mut State, ui: &imgui::Ui) {
+    let error = &mut state.gui.error;
+    if std::mem::replace(&mut error.needs_open, false) {
+        ui.open_popup(im_str!("Error"));
+    }
+    if let Some(message) = &error.message {
+        let mut closed = false;
+        ui.popup_modal(im_str!("Error"))
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(message);
+                ui.spacing();
+                if ui.button(im_str!("OK"), [120.0, 30.0]) {
+                    ui.close_current_popup();
+                    closed = true;
+                }
+            });
+        if closed {
+            error.message = None;
+        }
+    }
+}
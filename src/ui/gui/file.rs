@@ -0,0 +1,58 @@
+use imgui::*;
+use std::path::Path;
+
+use crate::ui::State;
+
+/// The file path shown when the file window is first opened.
+const DEFAULT_PATH: &str = "pattern.rle";
+
+pub struct WindowState {
+    pub visible: bool,
+    path: ImString,
+}
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            path: ImString::new(DEFAULT_PATH),
+        }
+    }
+}
+impl WindowState {
+    /// Sets the file path shown in the window.
+    pub fn set_path(&mut self, path: &Path) {
+        self.path = ImString::new(path.to_string_lossy());
+    }
+}
+
+/// Builds the file window.
+pub fn build(state: &mut State, ui: &imgui::Ui) {
+    if state.gui.file.visible {
+        Window::new(&ImString::new("File")).build(ui, || {
+            let mut width = ui.window_content_region_width();
+            if width < 100.0 {
+                width = 200.0;
+            }
+            ui.input_text(im_str!("Path"), &mut state.gui.file.path)
+                .resize_buffer(true)
+                .build();
+            ui.spacing();
+            ui.spacing();
+            let button_width = (width - 20.0) / 2.0;
+            if ui.button(im_str!("Open"), [button_width, 40.0]) {
+                state.stop_running();
+                let path = state.gui.file.path.to_str().to_owned();
+                if let Err(e) = state.load_rle_from_file(Path::new(&path)) {
+                    state.gui.error.show(e);
+                }
+            }
+            ui.same_line(button_width + 20.0);
+            if ui.button(im_str!("Save"), [button_width, 40.0]) {
+                let path = state.gui.file.path.to_str().to_owned();
+                if let Err(e) = state.save_rle_to_file(Path::new(&path)) {
+                    state.gui.error.show(e);
+                }
+            }
+        })
+    }
+}
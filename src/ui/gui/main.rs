@@ -36,6 +36,7 @@ pub fn build(state: &mut State, ui: &imgui::Ui) {
             }
             _ => unimplemented!(),
         };
+        ui.checkbox(im_str!("File"), &mut state.gui.file.visible);
         ui.checkbox(im_str!("Simulation"), &mut state.gui.simulation.visible);
     });
 }
@@ -1,3 +1,5 @@
+mod error;
+mod file;
 mod main;
 mod simulation;
 
@@ -5,6 +7,8 @@ use crate::ui::State;
 
 #[derive(Default)]
 pub struct GuiWindows {
+    pub error: error::WindowState,
+    pub file: file::WindowState,
     pub main: main::WindowState,
     pub simulation: simulation::WindowState,
 }
@@ -12,5 +16,7 @@ pub struct GuiWindows {
 /// Builds all the imgui windows.
 pub fn build_windows(state: &mut State, ui: &imgui::Ui) {
     main::build(state, ui);
+    file::build(state, ui);
     simulation::build(state, ui);
+    error::build(state, ui);
 }
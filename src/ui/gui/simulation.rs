@@ -1,13 +1,24 @@
 use imgui::*;
+use log::warn;
+use num::{BigInt, One, Signed};
+use std::time::Duration;
 
-use crate::automaton::NdSimulate;
+use crate::automaton::{Algorithm, NdSimulate};
+use crate::math::parse_bigint;
 use crate::ui::State;
 
+/// How long a step must take before its progress is shown, so that it doesn't
+/// flicker while the simulation is running.
+const SHOW_PROGRESS_AFTER: Duration = Duration::from_millis(500);
+
 #[derive(Default)]
 pub struct WindowState {
     pub visible: bool,
     pub running: bool,
-    jump_to_gen: isize,
+    /// Text of the step size input, which may be in decimal or `2^n` form.
+    step_size_text: ImString,
+    /// Text of the jump-to input, which may be in decimal or `2^n` form.
+    jump_to_gen_text: ImString,
 }
 
 /// Builds the main window.
@@ -26,20 +37,40 @@ pub fn build(state: &mut State, ui: &imgui::Ui) {
             ui.separator();
             ui.spacing();
             ui.spacing();
-            let old_sim_step_size = state.grid_view.get_step_size();
-            let mut sim_step_size = old_sim_step_size as i32;
-            ui.input_int(im_str!("Sim step"), &mut sim_step_size)
-                .step(16)
-                .step_fast(256)
+            let step_size_text = &mut state.gui.simulation.step_size_text;
+            let edited = ui
+                .input_text(im_str!("Sim step"), step_size_text)
+                .resize_buffer(true)
                 .build();
-            if sim_step_size <= 0 {
-                sim_step_size = 1;
+            match parse_bigint(step_size_text.to_str()) {
+                Some(new_step_size) if edited && new_step_size.is_positive() => {
+                    state.grid_view.set_step_size(new_step_size);
+                }
+                // Don't overwrite the text while it's being edited.
+                _ if ui.is_item_active() => (),
+                _ => *step_size_text = ImString::new(state.grid_view.get_step_size().to_string()),
+            }
+            let mut hyperspeed = state.grid_view.get_hyperspeed_exponent().is_some();
+            if ui.checkbox(im_str!("Hyperspeed"), &mut hyperspeed) {
+                state.grid_view.set_hyperspeed(hyperspeed);
             }
-            if old_sim_step_size as i32 != sim_step_size {
-                state.grid_view.set_step_size(sim_step_size as usize);
+            if let Some(exponent) = state.grid_view.get_hyperspeed_exponent() {
+                ui.same_line(0.0);
+                ui.text(format!("(step = 2^{})", exponent));
+            }
+            let mut algorithm = state.grid_view.get_algorithm();
+            for &option in Algorithm::ALL {
+                if ui.radio_button(&ImString::new(option.to_string()), &mut algorithm, option) {
+                    state.grid_view.set_algorithm(algorithm);
+                }
+                ui.same_line(0.0);
             }
+            ui.text("Algorithm");
             if ui.button(
-                &ImString::new(format!("Step {} generations", sim_step_size)),
+                &ImString::new(format!(
+                    "Step {} generations",
+                    state.grid_view.get_step_size()
+                )),
                 [width, 40.0],
             ) {
                 state.step(true);
@@ -58,33 +89,80 @@ pub fn build(state: &mut State, ui: &imgui::Ui) {
                     state.toggle_running();
                 }
             }
+            if let Some(progress) = state.worker.progress() {
+                if progress.elapsed >= SHOW_PROGRESS_AFTER {
+                    ui.text(format!(
+                        "Simulating {} generation{} ({:.1}s)",
+                        progress.generations,
+                        if progress.generations.is_one() {
+                            ""
+                        } else {
+                            "s"
+                        },
+                        progress.elapsed.as_secs_f64()
+                    ));
+                    ProgressBar::new(progress.fraction as f32)
+                        .size([width, 0.0])
+                        .build(ui);
+                    if ui.button(im_str!("Cancel (Esc)"), [width, 40.0]) {
+                        state.cancel_step();
+                    }
+                }
+            }
             ui.spacing();
             ui.spacing();
             ui.separator();
             ui.spacing();
             ui.spacing();
-            let jump_to_gen = &mut state.gui.simulation.jump_to_gen;
-            let mut jump_to_gen_i32 = *jump_to_gen as i32;
-            ui.input_int(im_str!("Jump to"), &mut jump_to_gen_i32)
-                .step(16)
-                .step_fast(256)
+            let jump_to_gen_text = &mut state.gui.simulation.jump_to_gen_text;
+            ui.input_text(im_str!("Jump to"), jump_to_gen_text)
+                .resize_buffer(true)
                 .build();
-            *jump_to_gen = jump_to_gen_i32 as isize;
-            if *jump_to_gen <= state.grid_view.get_generation_count() {
-                *jump_to_gen = state.grid_view.get_generation_count();
-            }
+            let current_gen = state.grid_view.get_generation_count().clone();
+            let jump_to_gen: BigInt = match parse_bigint(jump_to_gen_text.to_str()) {
+                Some(gen) if gen >= current_gen => gen,
+                _ => {
+                    // Don't overwrite the text while it's being edited.
+                    if !ui.is_item_active() {
+                        *jump_to_gen_text = ImString::new(current_gen.to_string());
+                    }
+                    current_gen.clone()
+                }
+            };
             if ui.button(
-                &ImString::new(format!("Jump to generation {}", *jump_to_gen)),
+                &ImString::new(format!("Jump to generation {}", jump_to_gen)),
                 [width, 40.0],
-            ) {
-                if state.grid_view.get_generation_count() < *jump_to_gen {
-                    let old_sim_step_size = state.grid_view.get_step_size();
-                    state.grid_view.set_step_size(
-                        (*jump_to_gen - state.grid_view.get_generation_count()) as usize,
-                    );
-                    state.step(true);
-                    state.grid_view.set_step_size(old_sim_step_size);
-                }
+            ) && current_gen < jump_to_gen
+                && !state.jump_to_generation(jump_to_gen.clone())
+            {
+                warn!("Unable to jump to generation {}", jump_to_gen);
+            }
+            ui.spacing();
+            ui.spacing();
+            ui.separator();
+            ui.spacing();
+            ui.spacing();
+            let stats = state.grid_view.get_cache_stats();
+            let mut memory_budget_mib = (state.grid_view.get_memory_budget() >> 20) as i32;
+            ui.text(format!(
+                "Memory: {} / {} MiB",
+                stats.memory_usage >> 20,
+                memory_budget_mib
+            ));
+            ui.text(format!(
+                "{} nodes, {} results",
+                stats.node_count, stats.result_count
+            ));
+            ui.text(format!("Garbage collections: {}", stats.gc_count));
+            if ui
+                .input_int(im_str!("Budget (MiB)"), &mut memory_budget_mib)
+                .step(64)
+                .step_fast(1024)
+                .build()
+            {
+                state
+                    .grid_view
+                    .set_memory_budget((memory_budget_mib.max(1) as usize) << 20);
             }
             ui.spacing();
             ui.spacing();
@@ -102,7 +180,6 @@ pub fn build(state: &mut State, ui: &imgui::Ui) {
             ui.spacing();
             ui.spacing();
             if ui.button(im_str!("Reset"), [width, 40.0]) {
-                state.stop_running();
                 state.reset();
             }
         })
@@ -21,6 +21,15 @@ impl HistoryStack {
     pub fn has_redo(&self) -> bool {
         !self.redo.is_empty()
     }
+    /// Returns the root of the pattern in every state in the undo and redo
+    /// stacks.
+    pub fn gc_roots(&self) -> Vec<GcRoot> {
+        self.undo
+            .iter()
+            .chain(&self.redo)
+            .map(|entry| entry.0.get_gc_root())
+            .collect()
+    }
     /// Restores the last state from the undo stack, pushing the current state
     /// onto the redo stack.
     ///
@@ -73,8 +82,14 @@ impl HistoryEntry {
             }
             GridView::View3D { .. } => unimplemented!(),
         }
-        // Preserve step size.
-        self.0.set_step_size(current.get_step_size());
+        // Preserve step size and memory budget, restarting hyperspeed because
+        // the pattern has changed.
+        self.0.set_memory_budget(current.get_memory_budget());
+        if current.get_hyperspeed_exponent().is_some() {
+            self.0.set_hyperspeed(true);
+        } else {
+            self.0.set_step_size(current.get_step_size().clone());
+        }
         // Perform the replacement.
         std::mem::replace(current, self.0)
     }
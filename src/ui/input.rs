@@ -5,7 +5,7 @@ use std::collections::HashSet;
 use std::ops::Index;
 
 use super::gridview;
-use crate::automaton::{NdSimulate, NdVec};
+use crate::automaton::NdVec;
 
 const FALSE_REF: &bool = &false;
 const TRUE_REF: &bool = &true;
@@ -169,6 +169,9 @@ pub fn handle_key(state: &mut super::State, input: &KeyboardInput) {
                     Some(VirtualKeyCode::Return) => {
                         state.toggle_running();
                     }
+                    Some(VirtualKeyCode::Escape) => {
+                        state.cancel_step();
+                    }
                     _ => (),
                 },
 
@@ -181,26 +184,22 @@ pub fn handle_key(state: &mut super::State, input: &KeyboardInput) {
                 } => match virtual_keycode {
                     // Undo.
                     Some(VirtualKeyCode::Z) => {
-                        state.stop_running();
-                        state.history.undo(&mut state.grid_view);
+                        state.undo();
                     }
                     // Redo.
                     Some(VirtualKeyCode::Y) => {
-                        state.stop_running();
-                        state.history.redo(&mut state.grid_view);
+                        state.redo();
                     }
                     // Reset.
                     Some(VirtualKeyCode::R) => {
-                        state.stop_running();
-                        while state.grid_view.get_generation_count() > 0
-                            && state.history.undo(&mut state.grid_view)
-                        {}
+                        state.reset();
                     }
                     // Paste.
                     Some(VirtualKeyCode::V) => {
                         state.stop_running();
-                        if state.load_rle_from_clipboard().is_err() {
+                        if let Err(e) = state.load_rle_from_clipboard() {
                             warn!("Failed to load RLE from clipboard");
+                            state.gui.error.show(e);
                         };
                     }
                     _ => (),
@@ -214,8 +213,7 @@ pub fn handle_key(state: &mut super::State, input: &KeyboardInput) {
                 } => match virtual_keycode {
                     // Redo.
                     Some(VirtualKeyCode::Z) => {
-                        state.stop_running();
-                        state.history.redo(&mut state.grid_view);
+                        state.redo();
                     }
                     _ => (),
                 },
@@ -228,6 +226,7 @@ pub fn handle_key(state: &mut super::State, input: &KeyboardInput) {
 }
 
 pub fn do_frame(state: &mut super::State) {
+    state.finish_step();
     if state.input_state.is_running {
         state.step(false);
     }
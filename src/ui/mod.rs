@@ -7,6 +7,9 @@ use imgui::{Context, FontSource};
 use imgui_glium_renderer::Renderer;
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 use log::warn;
+use num::{BigInt, Signed};
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
 use std::time::Instant;
 
@@ -15,11 +18,13 @@ mod gridview;
 mod gui;
 mod history;
 mod input;
+mod worker;
 
 use crate::automaton::*;
 use clipboard_compat::*;
-use gridview::{GridView, GridViewTrait};
+use gridview::{GridView, GridView2D, GridViewTrait};
 use history::*;
+use worker::SimulationWorker;
 
 const FPS: f32 = 60.0;
 
@@ -32,6 +37,7 @@ pub struct State {
     pub display: Rc<glium::Display>,
     pub grid_view: GridView,
     pub history: HistoryStack,
+    pub worker: SimulationWorker,
     pub input_state: input::InputState,
     pub gui: gui::GuiWindows,
     pub dpi: f64,
@@ -45,8 +51,9 @@ x = 47, y = 14, rule = B3/S23
 $26bobo!
 ";
 
-/// Display the main application window.
-pub fn show_gui() {
+/// Display the main application window, optionally opening a pattern file
+/// at startup.
+pub fn show_gui(open_path: Option<&Path>) {
     // Initialize all glium/glutin stuff.
     let mut events_loop = glutin::EventsLoop::new();
     let wb = glutin::WindowBuilder::new().with_title(TITLE.to_owned());
@@ -87,15 +94,23 @@ pub fn show_gui() {
             warn!("Unable to parse default pattern; using empty pattern instead");
             Default::default()
         });
-    automaton.sim = Simulation::new(Rc::new(rule::LIFE), 4);
+    automaton.sim.set_step_size(BigInt::from(4));
     let mut state = State {
         display: display.clone(),
         grid_view: GridView::new_2d(display.clone(), automaton),
         history: Default::default(),
+        worker: Default::default(),
         input_state: Default::default(),
         gui: Default::default(),
         dpi: hidpi_factor,
     };
+    if let Some(path) = open_path {
+        state.gui.file.set_path(path);
+        if let Err(e) = state.load_rle_from_file(path) {
+            warn!("{}", e);
+            state.gui.error.show(e);
+        }
+    }
 
     // Main loop
     let mut last_frame_time = Instant::now();
@@ -149,40 +164,117 @@ pub fn show_gui() {
 
 impl State {
     pub fn undo(&mut self) -> bool {
-        self.stop_running();
+        self.cancel_step();
         self.history.undo(&mut self.grid_view)
     }
     pub fn redo(&mut self) -> bool {
-        self.stop_running();
+        self.cancel_step();
         self.history.redo(&mut self.grid_view)
     }
     pub fn reset(&mut self) -> usize {
-        self.stop_running();
+        self.cancel_step();
         let mut i = 0;
-        while self.grid_view.get_generation_count() > 0 && self.undo() {
+        while self.grid_view.get_generation_count().is_positive() && self.undo() {
             i += 1;
         }
         i
     }
     /// Step forward a number of generations in the simulation.
+    ///
+    /// The step is simulated in the background; nothing happens if another
+    /// step is already being simulated.
     pub fn step(&mut self, record_history: bool) {
-        if record_history {
-            if self.stop_running() {
-                return;
-            }
-            self.record_state();
+        if record_history && self.stop_running() {
+            return;
         }
-        self.grid_view.step();
+        self.start_step(false, record_history);
     }
     /// Step forward one generation in the simulation.
+    ///
+    /// The step is simulated in the background; nothing happens if another
+    /// step is already being simulated.
     pub fn step_single(&mut self, record_history: bool) {
-        if record_history {
-            if self.stop_running() {
-                return;
+        if record_history && self.stop_running() {
+            return;
+        }
+        self.start_step(true, record_history);
+    }
+    /// Jumps forward to the given generation, simulating the generations in
+    /// between in the background like a single step. Any step that is already
+    /// being simulated is discarded first.
+    ///
+    /// Returns true if the jump was started, or false otherwise (e.g. if the
+    /// generation is not after the current one, or is too far ahead on a
+    /// bounded grid).
+    pub fn jump_to_generation(&mut self, generation: BigInt) -> bool {
+        self.cancel_step();
+        let generations = generation - self.grid_view.get_generation_count();
+        if !generations.is_positive() {
+            return false;
+        }
+        let old_step_size = self.grid_view.get_step_size().clone();
+        // Setting the step size turns off hyperspeed, so turn it back on
+        // afterwards.
+        let hyperspeed = self.grid_view.get_hyperspeed_exponent().is_some();
+        self.grid_view.set_step_size(generations.clone());
+        // Bounded grids limit the step size, so the jump might fall short.
+        let started =
+            *self.grid_view.get_step_size() == generations && self.start_step(false, true);
+        self.grid_view.set_step_size(old_step_size);
+        if hyperspeed {
+            self.grid_view.set_hyperspeed(true);
+        }
+        started
+    }
+    /// Starts simulating a step in the background, returning true if it was
+    /// started or false if another step is already being simulated.
+    fn start_step(&mut self, single: bool, record_history: bool) -> bool {
+        if self.worker.is_busy() {
+            return false;
+        }
+        match &self.grid_view {
+            GridView::View2D(view2d) => {
+                let mut automaton = view2d.automaton.clone();
+                // Keep the cached results for the patterns in the undo
+                // history if garbage is collected during the step.
+                automaton.set_gc_roots(&self.history.gc_roots());
+                self.worker.start(automaton, single, record_history);
             }
-            self.record_state();
+            GridView::View3D(_) => unimplemented!(),
         }
-        self.grid_view.step_single();
+        true
+    }
+    /// Hands the result of the step being simulated in the background to the
+    /// GridView, if it has finished.
+    pub fn finish_step(&mut self) {
+        if let Some(finished) = self.worker.poll() {
+            if finished.record_history {
+                self.record_state();
+            }
+            if let GridView::View2D(view2d) = &mut self.grid_view {
+                let mut automaton = finished.automaton;
+                // Don't hold onto the old history's patterns after the step.
+                automaton.set_gc_roots(&[]);
+                // Keep any settings that were changed during the step.
+                automaton.set_algorithm(view2d.get_algorithm());
+                automaton.set_memory_budget(view2d.get_memory_budget());
+                if view2d.get_hyperspeed_exponent().is_none() {
+                    automaton.set_step_size(view2d.get_step_size().clone());
+                } else if automaton.get_hyperspeed_exponent().is_none() {
+                    automaton.set_hyperspeed(true);
+                }
+                view2d.automaton = automaton;
+            }
+        }
+    }
+    /// Stops running the simulation and discards the step being simulated in
+    /// the background, if there is one, leaving the pattern as it was before
+    /// the step.
+    ///
+    /// Returns true if a step was discarded, or false otherwise.
+    pub fn cancel_step(&mut self) -> bool {
+        self.stop_running();
+        self.worker.cancel()
     }
     pub fn toggle_running(&mut self) -> bool {
         if self.input_state.is_running {
@@ -204,14 +296,41 @@ impl State {
     fn record_state(&mut self) {
         self.history.record(self.grid_view.clone());
     }
-    pub fn load_rle_from_clipboard(&mut self) -> Result<(), String> {
+    /// Load a pattern from an RLE string, recording the current state in the
+    /// undo history first.
+    ///
+    /// If the RLE cannot be parsed, the current pattern is left untouched.
+    pub fn load_rle(&mut self, rle: &str) -> Result<(), String> {
+        let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(rle)?;
+        self.cancel_step();
+        // Preserve step size and memory budget, restarting hyperspeed for the
+        // new pattern.
+        automaton.set_memory_budget(self.grid_view.get_memory_budget());
+        if self.grid_view.get_hyperspeed_exponent().is_some() {
+            automaton.set_hyperspeed(true);
+        } else {
+            automaton.set_step_size(self.grid_view.get_step_size().clone());
+        }
         self.record_state();
-        let mut automaton: NdAutomaton<Dim2D> = rle::RleEncode::from_rle(
-            &clipboard_get().map_err(|_| "Unable to access clipboard contents")?,
-        )?;
-        automaton.sim =
-            Simulation::new(Rc::new(rule::LIFE), self.grid_view.ndsim().get_step_size());
         self.grid_view = GridView::new_2d(self.display.clone(), automaton);
         Ok(())
     }
+    pub fn load_rle_from_clipboard(&mut self) -> Result<(), String> {
+        self.load_rle(&clipboard_get().map_err(|_| "Unable to access clipboard contents")?)
+    }
+    pub fn load_rle_from_file(&mut self, path: &Path) -> Result<(), String> {
+        let rle = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        self.load_rle(&rle)
+    }
+    pub fn save_rle_to_file(&self, path: &Path) -> Result<(), String> {
+        let rle = match &self.grid_view {
+            GridView::View2D(GridView2D {
+                automaton: ProjectedAutomaton::From2D(inner),
+                ..
+            }) => rle::RleEncode::to_rle(&inner.automaton),
+            _ => Err("Only 2D patterns can be saved as RLE")?,
+        };
+        fs::write(path, rle).map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
 }
@@ -0,0 +1,135 @@
+//! Simulation on a background thread, so that a large step doesn't freeze the
+//! window while it is being computed.
+
+use log::error;
+use num::BigInt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::automaton::*;
+
+/// A step that is being simulated on a background thread.
+struct PendingStep {
+    /// Channel on which the worker thread sends back the stepped automaton.
+    receiver: mpsc::Receiver<ProjectedAutomaton<Dim2D>>,
+    /// Token used to cancel the step.
+    cancellation: CancellationToken,
+    /// Fraction of the step that has been completed.
+    progress: Arc<Mutex<f64>>,
+    /// Number of generations being simulated.
+    generations: BigInt,
+    /// Whether to record the state from before the step in the undo history
+    /// once the step has finished.
+    record_history: bool,
+    /// When the step started.
+    start_time: Instant,
+}
+
+/// A step that has finished simulating.
+pub struct FinishedStep {
+    /// The automaton after the step.
+    pub automaton: ProjectedAutomaton<Dim2D>,
+    /// Whether to record the state from before the step in the undo history.
+    pub record_history: bool,
+}
+
+/// The progress of a step that is being simulated.
+pub struct StepProgress<'a> {
+    /// Number of generations being simulated.
+    pub generations: &'a BigInt,
+    /// Fraction of the step that has been completed.
+    pub fraction: f64,
+    /// How long the step has taken so far.
+    pub elapsed: Duration,
+}
+
+/// Simulates steps on a background thread, one at a time.
+#[derive(Default)]
+pub struct SimulationWorker {
+    pending: Option<PendingStep>,
+}
+impl SimulationWorker {
+    /// Returns true if a step is being simulated, or false otherwise.
+    pub fn is_busy(&self) -> bool {
+        self.pending.is_some()
+    }
+    /// Starts simulating a step (or a single generation, if `single` is true)
+    /// of the given automaton on a background thread.
+    ///
+    /// The automaton should be a clone of the one being displayed, which is
+    /// cheap because clones share their nodes and cached results.
+    pub fn start(
+        &mut self,
+        mut automaton: ProjectedAutomaton<Dim2D>,
+        single: bool,
+        record_history: bool,
+    ) {
+        let generations = if single {
+            BigInt::from(1)
+        } else {
+            automaton.get_step_size().clone()
+        };
+        let (sender, receiver) = mpsc::channel();
+        let cancellation = CancellationToken::new();
+        let progress = Arc::new(Mutex::new(0.0));
+        let thread_cancellation = cancellation.clone();
+        let thread_progress = progress.clone();
+        thread::spawn(move || {
+            let report_progress = |fraction| *thread_progress.lock().unwrap() = fraction;
+            let result = if single {
+                automaton.try_step_single(&thread_cancellation, &report_progress)
+            } else {
+                automaton.try_step(&thread_cancellation, &report_progress)
+            };
+            // If the step was cancelled then there's no one to send it to.
+            if result.is_ok() {
+                let _ = sender.send(automaton);
+            }
+        });
+        self.pending = Some(PendingStep {
+            receiver,
+            cancellation,
+            progress,
+            generations,
+            record_history,
+            start_time: Instant::now(),
+        });
+    }
+    /// Returns the step that was being simulated if it has finished.
+    pub fn poll(&mut self) -> Option<FinishedStep> {
+        let result = self.pending.as_ref()?.receiver.try_recv();
+        match result {
+            Ok(automaton) => Some(FinishedStep {
+                automaton,
+                record_history: self.pending.take()?.record_history,
+            }),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                error!("Simulation thread panicked");
+                self.pending = None;
+                None
+            }
+        }
+    }
+    /// Cancels the step that is being simulated, returning true if there was
+    /// one or false otherwise.
+    pub fn cancel(&mut self) -> bool {
+        match self.pending.take() {
+            Some(pending) => {
+                pending.cancellation.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns the progress of the step that is being simulated, or None if
+    /// no step is being simulated.
+    pub fn progress(&self) -> Option<StepProgress<'_>> {
+        self.pending.as_ref().map(|pending| StepProgress {
+            generations: &pending.generations,
+            fraction: *pending.progress.lock().unwrap(),
+            elapsed: pending.start_time.elapsed(),
+        })
+    }
+}